@@ -89,6 +89,7 @@ impl From<ExternalCompressionInput> for CompressionInput {
         let mut dsl = lambda::Language {
             primitives,
             invented: vec![],
+            invented_names: vec![],
             variable_logprob,
             symmetry_violations: vec![],
         };
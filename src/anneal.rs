@@ -0,0 +1,320 @@
+//! Simulated annealing and parallel tempering for any [`GP`] representation.
+//!
+//! [`GP`]: trait.GP.html
+
+use rand::Rng;
+use rayon::prelude::*;
+use std::f64;
+
+use {Task, GP};
+
+/// A temperature schedule for simulated annealing, giving the temperature at each step.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Schedule {
+    /// The temperature is always the same.
+    Constant(f64),
+    /// The temperature moves in equal steps from `initial` at the first step to `end` at the last
+    /// step.
+    Linear { initial: f64, end: f64 },
+    /// The temperature at step \\(k\\) is `initial` \\(\cdot\\) `rate`\\(\^k\\).
+    Exponential { initial: f64, rate: f64 },
+    /// The temperature at step \\(k\\) is `initial` \\(/ \ln(k + e)\\).
+    Logarithmic { initial: f64 },
+}
+impl Schedule {
+    /// The temperature at `step` of an annealing run taking `n_steps` steps.
+    pub fn temperature(&self, step: usize, n_steps: usize) -> f64 {
+        match *self {
+            Schedule::Constant(t) => t,
+            Schedule::Linear { initial, end } => {
+                let progress = if n_steps > 1 {
+                    step as f64 / (n_steps - 1) as f64
+                } else {
+                    1.0
+                };
+                initial + (end - initial) * progress
+            }
+            Schedule::Exponential { initial, rate } => initial * rate.powi(step as i32),
+            Schedule::Logarithmic { initial } => initial / (step as f64 + f64::consts::E).ln(),
+        }
+    }
+}
+
+/// Parameters for simulated annealing with [`anneal`].
+///
+/// [`anneal`]: fn.anneal.html
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnnealParams {
+    /// The temperature at each step.
+    pub schedule: Schedule,
+    /// The number of steps to take.
+    pub n_steps: usize,
+}
+
+/// Parameters for parallel tempering with [`temper`].
+///
+/// [`temper`]: fn.temper.html
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TemperingParams {
+    /// The temperature of each replica, from coldest to hottest.
+    pub temperatures: Vec<f64>,
+    /// The number of steps each replica takes.
+    pub n_steps: usize,
+    /// The number of steps between attempted exchanges of adjacent replicas.
+    pub exchange_interval: usize,
+}
+impl TemperingParams {
+    /// Parameters with `n_replicas` temperatures spaced geometrically from `coldest` to
+    /// `hottest`.
+    pub fn geometric(
+        coldest: f64,
+        hottest: f64,
+        n_replicas: usize,
+        n_steps: usize,
+        exchange_interval: usize,
+    ) -> TemperingParams {
+        let ratio = if n_replicas > 1 {
+            (hottest / coldest).powf(1.0 / (n_replicas - 1) as f64)
+        } else {
+            1.0
+        };
+        TemperingParams {
+            temperatures: (0..n_replicas)
+                .map(|i| coldest * ratio.powi(i as i32))
+                .collect(),
+            n_steps,
+            exchange_interval,
+        }
+    }
+}
+
+/// The outcome of [`temper`].
+///
+/// [`temper`]: fn.temper.html
+#[derive(Debug, Clone)]
+pub struct Tempered<E> {
+    /// The final expression and fitness of each replica, from coldest to hottest.
+    pub replicas: Vec<(E, f64)>,
+    /// The fittest expression found by any replica.
+    pub best: (E, f64),
+    /// The number of accepted exchanges between each replica and the next hotter one.
+    pub n_exchanges: Vec<usize>,
+}
+
+/// Minimizes the fitness given by a [`Task`]'s [`oracle`] by simulated annealing, proposing
+/// moves with [`GP::mutate`]. Returns the fittest expression found and its fitness.
+///
+/// The search starts from `initial` if given, and otherwise from an expression created by
+/// [`GP::genesis`]. Returns `None` if there is no `initial` expression and genesis creates none,
+/// as may happen for a request type which is hard to sample. A proposal that is no less fit than
+/// the current expression is always accepted, and otherwise is accepted with probability
+/// \\(\exp(-\Delta / T)\\).
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate polytype;
+/// extern crate programinduction;
+/// extern crate rand;
+/// use programinduction::pcfg::{self, Grammar, Rule};
+/// use programinduction::{anneal, AnnealParams, Schedule, Task};
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// fn evaluator(name: &str, inps: &[i32]) -> Result<i32, ()> {
+///     match name {
+///         "0" => Ok(0),
+///         "1" => Ok(1),
+///         "plus" => Ok(inps[0] + inps[1]),
+///         _ => unreachable!(),
+///     }
+/// }
+///
+/// fn main() {
+///     let g = Grammar::new(
+///         tp!(EXPR),
+///         vec![
+///             Rule::new("0", tp!(EXPR), 1.0),
+///             Rule::new("1", tp!(EXPR), 1.0),
+///             Rule::new("plus", tp!(@arrow[tp!(EXPR), tp!(EXPR), tp!(EXPR)]), 1.0),
+///         ],
+///     );
+///     let task = Task {
+///         oracle: Box::new(|g: &Grammar, expr| match g.eval(expr, &evaluator) {
+///             Ok(n) => (n - 4).abs() as f64,
+///             Err(_) => std::f64::INFINITY,
+///         }),
+///         tp: ptp!(EXPR),
+///         observation: (),
+///     };
+///     let params = pcfg::GeneticParams::default();
+///     let annealparams = AnnealParams {
+///         schedule: Schedule::Exponential {
+///             initial: 2.0,
+///             rate: 0.99,
+///         },
+///         n_steps: 1000,
+///     };
+///     let rng = &mut SmallRng::from_seed([1u8; 16]);
+///
+///     let (expr, fitness) = anneal(&g, &params, rng, &annealparams, &task, None).unwrap();
+///     assert_eq!(fitness, 0.0);
+///     assert_eq!(g.eval(&expr, &evaluator), Ok(4));
+/// }
+/// ```
+///
+/// [`Task`]: struct.Task.html
+/// [`oracle`]: struct.Task.html#structfield.oracle
+/// [`GP::mutate`]: trait.GP.html#tymethod.mutate
+/// [`GP::genesis`]: trait.GP.html#tymethod.genesis
+pub fn anneal<G: GP, R: Rng>(
+    gp: &G,
+    params: &G::Params,
+    rng: &mut R,
+    annealparams: &AnnealParams,
+    task: &Task<G, G::Expression, G::Observation>,
+    initial: Option<G::Expression>,
+) -> Option<(G::Expression, f64)> {
+    let expr = match initial {
+        Some(expr) => expr,
+        None => gp.genesis(params, rng, 1, &task.tp).pop()?,
+    };
+    let fitness = (task.oracle)(gp, &expr);
+    let mut current = (expr, fitness);
+    let mut best = current.clone();
+    for step in 0..annealparams.n_steps {
+        let temperature = annealparams
+            .schedule
+            .temperature(step, annealparams.n_steps);
+        if let Some(proposal) = propose(gp, params, rng, task, &current.0) {
+            let fitness = (task.oracle)(gp, &proposal);
+            if accept(rng, current.1, fitness, temperature) {
+                current = (proposal, fitness);
+                if current.1 < best.1 {
+                    best = current.clone();
+                }
+            }
+        }
+    }
+    Some(best)
+}
+
+/// Minimizes the fitness given by a [`Task`]'s [`oracle`] by parallel tempering (replica
+/// exchange), proposing moves with [`GP::mutate`].
+///
+/// Each replica is annealed at a fixed temperature from [`temperatures`], and the fitness of
+/// every replica's proposal is evaluated in parallel. Every [`exchange_interval`] steps, each
+/// pair of adjacent replicas exchange expressions with the Metropolis probability
+/// \\(\min(1, \exp((1/T_i - 1/T_j)(f_i - f_j)))\\). Replicas start from `initial` if given (in
+/// order from coldest to hottest), with [`GP::genesis`] creating the rest. Returns `None` if
+/// genesis creates too few expressions for every temperature to have a replica.
+///
+/// [`Task`]: struct.Task.html
+/// [`oracle`]: struct.Task.html#structfield.oracle
+/// [`GP::mutate`]: trait.GP.html#tymethod.mutate
+/// [`GP::genesis`]: trait.GP.html#tymethod.genesis
+/// [`temperatures`]: struct.TemperingParams.html#structfield.temperatures
+/// [`exchange_interval`]: struct.TemperingParams.html#structfield.exchange_interval
+pub fn temper<G: GP, R: Rng>(
+    gp: &G,
+    params: &G::Params,
+    rng: &mut R,
+    temperingparams: &TemperingParams,
+    task: &Task<G, G::Expression, G::Observation>,
+    initial: Option<Vec<G::Expression>>,
+) -> Option<Tempered<G::Expression>> {
+    let temperatures = &temperingparams.temperatures;
+    assert!(
+        !temperatures.is_empty(),
+        "parallel tempering requires at least one temperature"
+    );
+    let mut exprs = initial.unwrap_or_default();
+    exprs.truncate(temperatures.len());
+    if exprs.len() < temperatures.len() {
+        let n = temperatures.len() - exprs.len();
+        exprs.append(&mut gp.genesis(params, rng, n, &task.tp));
+        if exprs.len() < temperatures.len() {
+            return None;
+        }
+    }
+    let mut replicas: Vec<_> = exprs
+        .into_par_iter()
+        .map(|expr| {
+            let fitness = (task.oracle)(gp, &expr);
+            (expr, fitness)
+        })
+        .collect();
+    let mut best = fittest(&replicas).clone();
+    let mut n_exchanges = vec![0; temperatures.len() - 1];
+    for step in 0..temperingparams.n_steps {
+        let proposals: Vec<_> = replicas
+            .iter()
+            .map(|(expr, _)| propose(gp, params, rng, task, expr))
+            .collect();
+        let scored: Vec<_> = proposals
+            .into_par_iter()
+            .map(|proposal| {
+                proposal.map(|expr| {
+                    let fitness = (task.oracle)(gp, &expr);
+                    (expr, fitness)
+                })
+            })
+            .collect();
+        for (i, proposal) in scored.into_iter().enumerate() {
+            if let Some(proposal) = proposal {
+                if accept(rng, replicas[i].1, proposal.1, temperatures[i]) {
+                    replicas[i] = proposal;
+                }
+            }
+        }
+        if fittest(&replicas).1 < best.1 {
+            best = fittest(&replicas).clone();
+        }
+        let interval = temperingparams.exchange_interval;
+        if interval > 0 && step % interval == interval - 1 {
+            for i in 0..n_exchanges.len() {
+                let (f_cold, f_hot) = (replicas[i].1, replicas[i + 1].1);
+                let log_ratio =
+                    (1.0 / temperatures[i] - 1.0 / temperatures[i + 1]) * (f_cold - f_hot);
+                if log_ratio >= 0.0 || rng.gen::<f64>().ln() < log_ratio {
+                    replicas.swap(i, i + 1);
+                    n_exchanges[i] += 1;
+                }
+            }
+        }
+    }
+    Some(Tempered {
+        replicas,
+        best,
+        n_exchanges,
+    })
+}
+
+/// Mutates `expr`, choosing uniformly among the offspring.
+fn propose<G: GP, R: Rng>(
+    gp: &G,
+    params: &G::Params,
+    rng: &mut R,
+    task: &Task<G, G::Expression, G::Observation>,
+    expr: &G::Expression,
+) -> Option<G::Expression> {
+    let mut offspring = gp.mutate(params, rng, expr, &task.observation);
+    if offspring.is_empty() {
+        None
+    } else {
+        let i = rng.gen_range(0, offspring.len());
+        Some(offspring.swap_remove(i))
+    }
+}
+
+/// The Metropolis criterion for minimizing fitness at some temperature.
+fn accept<R: Rng>(rng: &mut R, current: f64, proposed: f64, temperature: f64) -> bool {
+    proposed <= current || rng.gen::<f64>() < (-(proposed - current) / temperature).exp()
+}
+
+fn fittest<E>(replicas: &[(E, f64)]) -> &(E, f64) {
+    replicas
+        .iter()
+        .min_by(|(_, x), (_, y)| x.partial_cmp(y).expect("found NaN"))
+        .expect("no replicas")
+}
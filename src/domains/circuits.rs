@@ -27,7 +27,7 @@ use rand::distributions::{Distribution, WeightedIndex};
 use std::f64;
 use std::iter;
 
-use lambda::{Evaluator as EvaluatorT, Expression, Language};
+use lambda::{EvalLimits, Evaluator as EvaluatorT, Expression, Language};
 use Task;
 
 /// The circuit representation, a [`lambda::Language`], only defines the binary `nand` operation.
@@ -93,6 +93,7 @@ impl EvaluatorT for Evaluator {
             _ => unreachable!(),
         }
     }
+    fn limit_error(&self) -> Self::Error {}
 }
 
 /// Randomly sample a number of circuits into [`Task`]s.
@@ -175,17 +176,21 @@ pub fn make_tasks_advanced(
             let oracle_outputs = outputs.clone();
             let evaluator = ::std::sync::Arc::new(Evaluator);
             let oracle = Box::new(move |dsl: &Language, expr: &Expression| -> f64 {
-                let success = iter::repeat(vec![false, true])
+                let compiled = dsl.compile(expr);
+                let limits = EvalLimits::default();
+                let inputs: Vec<_> = iter::repeat(vec![false, true])
                     .take(n_inputs)
                     .multi_cartesian_product()
+                    .collect();
+                let success = compiled
+                    .eval_batch_with_limits(
+                        &evaluator,
+                        inputs.iter().map(|inps| &inps[..]),
+                        &limits,
+                    )
+                    .into_iter()
                     .zip(&oracle_outputs)
-                    .all(|(inps, out)| {
-                        if let Ok(o) = dsl.eval_arc(expr, &evaluator, &inps) {
-                            o == *out
-                        } else {
-                            false
-                        }
-                    });
+                    .all(|(o, out)| if let Ok(o) = o { o == *out } else { false });
                 if success {
                     0f64
                 } else {
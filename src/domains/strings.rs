@@ -22,8 +22,9 @@
 use itertools::Itertools;
 use std::f64;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-use lambda::{Evaluator as EvaluatorT, Expression, Language, LiftedFunction};
+use lambda::{EvalLimits, Evaluator as EvaluatorT, Expression, Language, LiftedFunction};
 use Task;
 
 /// The string editing [`lambda::Language`] defines the following operations:
@@ -147,6 +148,18 @@ impl PartialEq for Space {
         }
     }
 }
+/// Consistent with `PartialEq`, under which a function is unequal to everything.
+impl Hash for Space {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Num(x) => (0, x).hash(state),
+            Char(x) => (1, x).hash(state),
+            Str(ref x) => (2, x).hash(state),
+            List(ref xs) => (3, xs).hash(state),
+            Func(_) => 4.hash(state),
+        }
+    }
+}
 /// An [`Evaluator`] for the strings domain.
 ///
 /// # Examples
@@ -289,6 +302,7 @@ impl EvaluatorT for Evaluator {
     fn lift(&self, f: LiftedFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
         Ok(Func(f))
     }
+    fn limit_error(&self) -> Self::Error {}
 }
 
 /// Randomly generate string editing [`Task`]s.
@@ -309,13 +323,22 @@ pub fn make_tasks(
             let evaluator = ::std::sync::Arc::new(Evaluator);
             let oracle_examples = examples.clone();
             let oracle = Box::new(move |dsl: &Language, expr: &Expression| -> f64 {
-                let success = oracle_examples.iter().all(|&(ref inps, ref out)| {
-                    if let Ok(o) = dsl.eval_arc(expr, &evaluator, inps) {
-                        o == *out
-                    } else {
-                        false
-                    }
-                });
+                let compiled = dsl.compile(expr);
+                let inputs = oracle_examples.iter().map(|(inps, _)| &inps[..]);
+                let limits = EvalLimits::default();
+                let success = compiled
+                    .eval_batch_with_limits(&evaluator, inputs, &limits)
+                    .into_iter()
+                    .zip(&oracle_examples)
+                    .all(
+                        |(o, (_, out))| {
+                            if let Ok(o) = o {
+                                o == *out
+                            } else {
+                                false
+                            }
+                        },
+                    );
                 if success {
                     0f64
                 } else {
@@ -9,7 +9,7 @@ use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Duration;
 
-use Task;
+use {SketchedTask, Task};
 
 /// Parameters for the EC algorithm.
 ///
@@ -31,8 +31,9 @@ pub struct ECParams {
 ///
 /// For details on the EC algorithm, see the module-level documentation [here].
 ///
-/// Implementors of `EC` need only provide an [`enumerate`] and [`compress`] methods. By doing so,
-/// we provide the [`ec`], [`ec_with_recognition`], and [`explore`] methods.
+/// Implementors of `EC` need only provide the [`enumerate`], [`enumerate_sketch`], and [`compress`]
+/// methods. By doing so, we provide the [`ec`], [`ec_with_recognition`], [`explore`], and
+/// [`explore_sketched`] methods.
 ///
 /// Typically, you will interact with this trait via existing implementations, such as with
 /// [`lambda::Language`] or [`pcfg::Grammar`].
@@ -75,10 +76,12 @@ pub struct ECParams {
 ///
 /// [here]: index.html#bayesian-program-learning-with-the-ec-algorithm
 /// [`enumerate`]: #tymethod.enumerate
+/// [`enumerate_sketch`]: #tymethod.enumerate_sketch
 /// [`compress`]: #tymethod.compress
 /// [`ec`]: #method.ec
 /// [`ec_with_recognition`]: #method.ec_with_recognition
 /// [`explore`]: #method.explore
+/// [`explore_sketched`]: #method.explore_sketched
 /// [`lambda::Language`]: lambda/struct.Language.html
 /// [`pcfg::Grammar`]: pcfg/struct.Grammar.html
 pub trait EC: Send + Sync + Sized {
@@ -98,6 +101,23 @@ pub trait EC: Send + Sync + Sized {
     fn enumerate<F>(&self, tp: TypeSchema, termination_condition: F)
     where
         F: Fn(Self::Expression, f64) -> bool + Send + Sync;
+    /// Like [`enumerate`], but only for completions of a sketch: an [`Expression`] which may
+    /// contain holes. This is used by [`explore_sketched`].
+    ///
+    /// A sketch without holes is its own only completion, if it has the requested type. A
+    /// representation whose expressions cannot contain holes should therefore yield just the
+    /// sketch, rather than everything [`enumerate`] would.
+    ///
+    /// [`enumerate`]: #tymethod.enumerate
+    /// [`Expression`]: #associatedtype.Expression
+    /// [`explore_sketched`]: #method.explore_sketched
+    fn enumerate_sketch<F>(
+        &self,
+        sketch: &Self::Expression,
+        tp: TypeSchema,
+        termination_condition: F,
+    ) where
+        F: Fn(Self::Expression, f64) -> bool + Send + Sync;
     /// Update the representation based on findings of expressions that solve [`Task`]s.
     ///
     /// The `frontiers` argument, and similar return value, must always be of the same size as
@@ -255,7 +275,9 @@ pub trait EC: Send + Sync + Sized {
         {
             let mutex = Arc::new(Mutex::new(&mut results));
             tps.into_par_iter()
-                .flat_map(|(tp, tasks)| enumerate_solutions(self, ec_params, tp.clone(), tasks))
+                .flat_map(|(tp, tasks)| {
+                    enumerate_solutions(self, ec_params, tp.clone(), None, tasks)
+                })
                 .for_each(move |(i, frontier)| {
                     let mut results = mutex.lock().unwrap();
                     results[i] = frontier
@@ -278,7 +300,31 @@ pub trait EC: Send + Sync + Sized {
             .zip(representations)
             .enumerate()
             .map(|(i, (t, repr))| {
-                enumerate_solutions(repr, ec_params, t.tp.clone(), vec![(i, t)])
+                enumerate_solutions(repr, ec_params, t.tp.clone(), None, vec![(i, t)])
+                    .pop()
+                    .unwrap()
+                    .1
+            })
+            .collect()
+    }
+
+    /// Like [`explore`], but each task's solutions are found among the completions of its sketch
+    /// using [`enumerate_sketch`]. Sketched tasks are made with [`Task::with_sketch`].
+    ///
+    /// [`explore`]: #method.explore
+    /// [`enumerate_sketch`]: #tymethod.enumerate_sketch
+    /// [`Task::with_sketch`]: struct.Task.html#method.with_sketch
+    fn explore_sketched<O: Sync>(
+        &self,
+        ec_params: &ECParams,
+        tasks: &[SketchedTask<Self, Self::Expression, O>],
+    ) -> Vec<ECFrontier<Self>> {
+        tasks
+            .par_iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let tp = t.task.tp.clone();
+                enumerate_solutions(self, ec_params, tp, Some(&t.sketch), vec![(i, &t.task)])
                     .pop()
                     .unwrap()
                     .1
@@ -294,10 +340,12 @@ pub trait EC: Send + Sync + Sized {
 ///
 /// Each task will be associated with at most `params.frontier_limit` many such expressions,
 /// and enumeration is stopped when `params.search_limit` valid expressions have been checked.
+/// If a sketch is given, only its completions are enumerated.
 fn enumerate_solutions<L, X, O: Sync>(
     repr: &L,
     params: &ECParams,
     tp: TypeSchema,
+    sketch: Option<&X>,
     tasks: Vec<(usize, &Task<L, X, O>)>,
 ) -> Vec<(usize, ECFrontier<L>)>
 where
@@ -377,7 +425,10 @@ where
         }
     };
 
-    repr.enumerate(tp, termination_condition);
+    match sketch {
+        Some(sketch) => repr.enumerate_sketch(sketch, tp, termination_condition),
+        None => repr.enumerate(tp, termination_condition),
+    }
     if let Ok(l) = Arc::try_unwrap(frontiers) {
         let frontiers = l.into_inner().expect("enumeration frontiers poisoned");
         frontiers.into_iter().map(|(j, _, f)| (j, f)).collect()
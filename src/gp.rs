@@ -1,9 +1,15 @@
 //! Representations capable of Genetic Programming.
 
-use itertools::Itertools;
+use itertools::{repeat_n, Itertools};
 use polytype::TypeSchema;
 use rand::{distributions::Distribution, distributions::WeightedIndex, seq::IteratorRandom, Rng};
+use rayon::prelude::*;
 use std::cmp::Ordering;
+use std::error::Error;
+use std::f64;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::ptr;
 use utils::{logsumexp, weighted_sample};
 
 use Task;
@@ -54,34 +60,30 @@ pub enum GPSelection {
 }
 
 impl GPSelection {
+    /// The fitness of existing individuals is reevaluated in parallel where necessary. Because
+    /// results are collected in order, selection remains deterministic for a given `rng`.
     pub(crate) fn update_population<'a, R: Rng, X: Clone + Send + Sync>(
         &self,
         population: &mut Vec<(X, f64)>,
-        children: Vec<X>,
+        mut scored_children: Vec<(X, f64)>,
         oracle: Box<dyn Fn(&X) -> f64 + Send + Sync + 'a>,
         rng: &mut R,
     ) {
-        let mut scored_children = children
-            .into_iter()
-            .map(|child| {
-                let fitness = oracle(&child);
-                (child, fitness)
-            })
-            .collect_vec();
         match self {
             GPSelection::Drift(alpha) => {
-                for (p, old_fitness) in population.iter_mut() {
+                population.par_iter_mut().for_each(|(p, old_fitness)| {
                     let new_fitness = oracle(p);
                     *old_fitness = *alpha * *old_fitness + (1.0 - alpha) * new_fitness;
-                }
+                });
                 let pop_size = population.len();
                 population.extend(scored_children);
                 *population = sample_without_replacement(population, pop_size, rng);
             }
-            GPSelection::Resample => {
+            GPSelection::Resample if !scored_children.is_empty() => {
                 let pop_size = population.len();
                 *population = sample_with_replacement(&mut scored_children, pop_size, rng);
             }
+            GPSelection::Resample => (),
             GPSelection::Deterministic => {
                 for child in scored_children {
                     sorted_place(child, population);
@@ -101,12 +103,52 @@ impl GPSelection {
                     *population = options;
                     options = rest;
                 }
-                population.append(&mut sample_pop(options, sample_size));
+                population.append(&mut sample_pop(options, sample_size, rng));
             }
         }
     }
 }
 
+/// Parameters for an age-layered population structure (ALPS), used with [`GP::init_alps`] and
+/// [`GP::evolve_alps`] or [`GP::evolve_alps_with_state`].
+///
+/// The population is split into `n_layers` layers, each of size [`population_size`] and each
+/// evolved according to the usual [`GPParams`]. An individual's age is the number of generations
+/// since its oldest genetic material was introduced by [`GP::genesis`]: offspring inherit the
+/// greatest age of their parents, and everyone ages by one with each generation. Layer `i` only
+/// holds individuals younger than `age_gap * (i + 1)`, except for the last layer, which has no
+/// limit. Individuals that become too old for their layer compete for a place in the next one.
+///
+/// Parents are selected from their own layer and the layer below it. Every `age_gap`
+/// generations, the youngest layer is replaced by fresh individuals from [`GP::genesis`], and its
+/// previous occupants and their children compete for a place in the next layer (or are discarded,
+/// if there is only one layer).
+///
+/// [`GP::init_alps`]: trait.GP.html#method.init_alps
+/// [`GP::evolve_alps`]: trait.GP.html#method.evolve_alps
+/// [`GP::evolve_alps_with_state`]: trait.GP.html#method.evolve_alps_with_state
+/// [`GP::genesis`]: trait.GP.html#tymethod.genesis
+/// [`population_size`]: struct.GPParams.html#structfield.population_size
+/// [`GPParams`]: struct.GPParams.html
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GPAlps {
+    /// The number of layers. There is always at least one layer, even if this is `0`.
+    pub n_layers: usize,
+    /// The number of generations between injections of fresh individuals into the youngest layer,
+    /// which is also the range of ages held by each layer but the last. If `0`, nothing is
+    /// injected and every individual moves up to the last layer.
+    pub age_gap: usize,
+}
+impl GPAlps {
+    fn max_age(&self, layer: usize) -> Option<usize> {
+        if layer + 1 < self.n_layers {
+            Some(self.age_gap * (layer + 1))
+        } else {
+            None
+        }
+    }
+}
+
 /// Parameters for genetic programming.
 #[derive(Deserialize, Serialize)]
 pub struct GPParams {
@@ -127,6 +169,623 @@ pub struct GPParams {
     pub n_delta: usize,
 }
 
+/// Parameters for controlling _bloat_, the growth of programs during evolution without
+/// corresponding improvement in fitness, used with [`GP::evolve_with_state`] via
+/// [`GPState::bloat`]. The default has no controls.
+///
+/// Sizes and depths are measured by [`GP::size`] and [`GP::depth`]. Their default
+/// implementations give every program a size and depth of `1`, so for a representation that does
+/// not override them, `max_size` and `max_depth` of at least `1` never reject an offspring,
+/// lexicographic parsimony never breaks a tie, and the Tarpeian method never kills a child.
+/// [`pcfg::Grammar`], [`lambda::Language`], and [`trs::Lexicon`] override both.
+///
+/// [`GP::evolve_with_state`]: trait.GP.html#method.evolve_with_state
+/// [`GPState::bloat`]: struct.GPState.html#structfield.bloat
+/// [`GP::size`]: trait.GP.html#method.size
+/// [`GP::depth`]: trait.GP.html#method.depth
+/// [`pcfg::Grammar`]: pcfg/struct.Grammar.html
+/// [`lambda::Language`]: lambda/struct.Language.html
+/// [`trs::Lexicon`]: trs/struct.Lexicon.html
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GPBloat {
+    /// Offspring larger than `max_size` are replaced by a copy of their (first) parent.
+    pub max_size: Option<usize>,
+    /// Offspring deeper than `max_depth` are replaced by a copy of their (first) parent.
+    pub max_depth: Option<usize>,
+    /// Lexicographic parsimony pressure: when contestants in a tournament have equal fitness, the
+    /// smaller one wins. This tournament takes the place of [`GP::tournament`].
+    ///
+    /// [`GP::tournament`]: trait.GP.html#method.tournament
+    pub lexicographic_parsimony: bool,
+    /// The Tarpeian method: `Some(p)` means that each child larger than the average size of the
+    /// population is, with probability `p`, assigned infinite (i.e. the worst) fitness without
+    /// being evaluated.
+    pub tarpeian: Option<f64>,
+}
+impl GPBloat {
+    fn admits<G: GP>(&self, gp: &G, expr: &G::Expression) -> bool {
+        match (self.max_size, self.max_depth) {
+            (Some(n), _) if gp.size(expr) > n => false,
+            (_, Some(n)) if gp.depth(expr) > n => false,
+            _ => true,
+        }
+    }
+    fn limit<G: GP>(&self, gp: &G, parent: &G::Expression, offspring: &mut [G::Expression]) {
+        for child in offspring.iter_mut() {
+            if !self.admits(gp, child) {
+                *child = parent.clone();
+            }
+        }
+    }
+    /// Selects an individual from the population by [`GP::tournament`], or by a tournament in
+    /// which the fittest contestant wins with ties broken by size if lexicographic parsimony is
+    /// enabled.
+    ///
+    /// [`GP::tournament`]: trait.GP.html#method.tournament
+    fn select<'a, G: GP, R: Rng>(
+        &self,
+        gp: &G,
+        rng: &mut R,
+        tournament_size: usize,
+        population: &'a [(G::Expression, f64)],
+    ) -> &'a G::Expression {
+        if !self.lexicographic_parsimony || tournament_size == 1 {
+            return gp.tournament(rng, tournament_size, population);
+        }
+        (0..population.len())
+            .choose_multiple(rng, tournament_size)
+            .into_iter()
+            .map(|i| &population[i])
+            .min_by(
+                |(x, fx), (y, fy)| match fx.partial_cmp(fy).expect("found NaN") {
+                    Ordering::Equal => gp.size(x).cmp(&gp.size(y)),
+                    ord => ord,
+                },
+            )
+            .map(|(expr, _)| expr)
+            .expect("tournament cannot select winner from no contestants")
+    }
+    /// Which children are killed by the Tarpeian method.
+    fn tarpeian<G: GP, R: Rng>(
+        &self,
+        gp: &G,
+        population: &[(G::Expression, f64)],
+        children: &[G::Expression],
+        rng: &mut R,
+    ) -> Vec<bool> {
+        match self.tarpeian {
+            Some(p) if !population.is_empty() => {
+                let total: usize = population.iter().map(|(expr, _)| gp.size(expr)).sum();
+                let mean = total as f64 / population.len() as f64;
+                children
+                    .iter()
+                    .map(|child| gp.size(child) as f64 > mean && rng.gen_bool(p))
+                    .collect()
+            }
+            _ => vec![false; children.len()],
+        }
+    }
+}
+
+/// Diversity-preserving adjustments to selection, used with [`GP::evolve_with_state`].
+///
+/// Selection among the population and its offspring is performed on adjusted scores, but the
+/// population itself retains (and is sorted by) unadjusted fitness.
+///
+/// [`GP::evolve_with_state`]: trait.GP.html#method.evolve_with_state
+#[allow(clippy::type_complexity)]
+pub enum GPDiversity<'a, E> {
+    /// Fitness sharing: an individual's fitness is penalized according to its _niche count_
+    /// `n`, the sum of `1 - (d / radius)^alpha` over individuals within distance `d < radius` of
+    /// it (including itself). The adjusted score is `fitness + |fitness| * (n - 1)`, which for
+    /// non-negative fitness is its product with the niche count, so that crowding makes an
+    /// individual look less fit whatever the sign of its fitness. If `distance` is `None`,
+    /// [`GP::distance`] is used.
+    ///
+    /// [`GP::distance`]: trait.GP.html#method.distance
+    FitnessSharing {
+        radius: f64,
+        alpha: f64,
+        distance: Option<Box<dyn Fn(&E, &E) -> f64 + Send + Sync + 'a>>,
+    },
+    /// Novelty search: an individual's novelty is the mean Euclidean distance from its behavior
+    /// to the `k` nearest behaviors among the population, offspring, and archive. Distances
+    /// involving a behavior with a `NaN` component are ignored. Offspring with novelty above
+    /// `threshold` are added to the archive. The adjusted score is
+    /// `(1 - weight) * fitness - weight * novelty`, so a `weight` of `1` ignores fitness entirely.
+    Novelty {
+        behavior: Box<dyn Fn(&E) -> Vec<f64> + Send + Sync + 'a>,
+        k: usize,
+        threshold: f64,
+        weight: f64,
+    },
+}
+
+/// Adaptive operator selection by probability matching, used with [`GP::evolve_with_state`].
+///
+/// The operators are crossover followed by each of the representation's mutation operators (see
+/// [`n_mutation_operators`]). An operator's reward is an exponential moving average, with rate
+/// `learning_rate`, of the relative fitness improvement of its offspring over their best parent.
+/// Operators are selected with probability `p_min + (1 - n * p_min) * reward / total_reward`,
+/// where `n` is the number of operators. Until any operator has been rewarded, operators are
+/// selected uniformly.
+///
+/// [`GP::evolve_with_state`]: trait.GP.html#method.evolve_with_state
+/// [`n_mutation_operators`]: trait.GP.html#method.n_mutation_operators
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GPAdaptive {
+    /// The minimum probability of selecting any operator, between `0` and `1 / n`. Larger values
+    /// are treated as `1 / n`, which selects operators uniformly, and smaller values as `0`.
+    pub p_min: f64,
+    pub learning_rate: f64,
+    /// The reward for each operator, with crossover first. Empty until the first generation.
+    pub rewards: Vec<f64>,
+}
+impl GPAdaptive {
+    pub fn new(p_min: f64, learning_rate: f64) -> Self {
+        GPAdaptive {
+            p_min,
+            learning_rate,
+            rewards: Vec::new(),
+        }
+    }
+    /// The probability of selecting each operator, with crossover first.
+    pub fn probabilities(&self) -> Vec<f64> {
+        let n = self.rewards.len() as f64;
+        let total: f64 = self.rewards.iter().sum();
+        let p_min = self.p_min.max(0.0).min(1.0 / n);
+        self.rewards
+            .iter()
+            .map(|reward| {
+                if total > 0.0 {
+                    p_min + (1.0 - n * p_min) * reward / total
+                } else {
+                    1.0 / n
+                }
+            })
+            .collect()
+    }
+    fn initialize(&mut self, n_mutation_operators: usize) {
+        if self.rewards.is_empty() {
+            self.rewards = vec![0.0; 1 + n_mutation_operators];
+        }
+    }
+    fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        WeightedIndex::new(self.probabilities())
+            .expect("invalid operator probabilities")
+            .sample(rng)
+    }
+    fn credit<E>(&mut self, lineages: &[Lineage], scored_children: &[(E, f64)]) {
+        let mut totals = vec![(0.0, 0usize); self.rewards.len()];
+        for (lineage, &(_, fitness)) in lineages.iter().zip(scored_children) {
+            let total = &mut totals[lineage.operator];
+            total.0 += improvement(lineage.parent_fitness, fitness);
+            total.1 += 1;
+        }
+        for (reward, (sum, count)) in self.rewards.iter_mut().zip(totals) {
+            if count > 0 {
+                *reward += self.learning_rate * (sum / count as f64 - *reward);
+            }
+        }
+    }
+}
+
+/// The relative improvement of a child's fitness over its parent's, between `0` and `1`.
+fn improvement(parent: f64, child: f64) -> f64 {
+    if child >= parent || child.is_nan() {
+        0.0
+    } else if parent.is_infinite() {
+        1.0
+    } else {
+        ((parent - child) / parent.abs()).min(1.0)
+    }
+}
+
+/// Semantic constraints on offspring, used with [`GP::evolve_with_state`].
+///
+/// An expression's _semantics_ is its vector of outputs on a task's examples, typically computed
+/// with [`pcfg::Grammar::eval`] or [`lambda::Language::eval`] and encoded as numbers (a failed
+/// evaluation may be encoded as `NaN`, which is never equivalent to or between anything). Offspring
+/// which violate the constraints are discarded and their operator is applied again to newly
+/// selected parents, up to `max_attempts` times, after which the last offspring are kept
+/// regardless.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate polytype;
+/// extern crate programinduction;
+/// extern crate rand;
+/// use programinduction::pcfg::{self, Grammar, Rule};
+/// use programinduction::{GPParams, GPSelection, GPSemantics, GPState, Task, GP};
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// fn evaluator(name: &str, inps: &[i32]) -> Result<i32, ()> {
+///     match name {
+///         "0" => Ok(0),
+///         "1" => Ok(1),
+///         "plus" => Ok(inps[0] + inps[1]),
+///         _ => unreachable!(),
+///     }
+/// }
+///
+/// fn main() {
+///     let g = Grammar::new(
+///         tp!(EXPR),
+///         vec![
+///             Rule::new("0", tp!(EXPR), 1.0),
+///             Rule::new("1", tp!(EXPR), 1.0),
+///             Rule::new("plus", tp!(@arrow[tp!(EXPR), tp!(EXPR), tp!(EXPR)]), 1.0),
+///         ],
+///     );
+///     let task = Task {
+///         oracle: Box::new(|g: &Grammar, expr| match g.eval(expr, &evaluator) {
+///             Ok(n) => (n - 4).abs() as f64,
+///             Err(_) => std::f64::INFINITY,
+///         }),
+///         tp: ptp!(EXPR),
+///         observation: (),
+///     };
+///     let gpparams = GPParams {
+///         selection: GPSelection::Deterministic,
+///         population_size: 10,
+///         tournament_size: 5,
+///         mutation_prob: 0.5,
+///         n_delta: 4,
+///     };
+///     let params = pcfg::GeneticParams::default();
+///     let rng = &mut SmallRng::from_seed([1u8; 16]);
+///
+///     let mut state = GPState {
+///         semantics: Some(GPSemantics::new(Box::new(|expr| {
+///             vec![g.eval(expr, &evaluator).map_or(std::f64::NAN, f64::from)]
+///         }))),
+///         ..Default::default()
+///     };
+///     let mut pop = g.init(&params, rng, &gpparams, &task, None);
+///     for _ in 0..50 {
+///         g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+///     }
+///     assert_eq!(pop.len(), 10);
+/// }
+/// ```
+///
+/// [`GP::evolve_with_state`]: trait.GP.html#method.evolve_with_state
+/// [`pcfg::Grammar::eval`]: pcfg/struct.Grammar.html#method.eval
+/// [`lambda::Language::eval`]: lambda/struct.Language.html#method.eval
+#[allow(clippy::type_complexity)]
+pub struct GPSemantics<'a, E> {
+    /// The semantics of an expression.
+    pub semantics: Box<dyn Fn(&E) -> Vec<f64> + Send + Sync + 'a>,
+    /// Whether to discard offspring semantically equivalent to any of their parents.
+    pub reject_equivalent: bool,
+    /// Whether crossover is semantic geometric crossover, which discards offspring whose output
+    /// on some example does not lie between the outputs of the two parents.
+    pub geometric_crossover: bool,
+    /// The absolute difference within which outputs are considered equal.
+    pub tolerance: f64,
+    /// The number of times an operator is applied before its offspring are kept regardless.
+    pub max_attempts: usize,
+}
+impl<'a, E> GPSemantics<'a, E> {
+    /// Semantic constraints with both equivalence rejection and geometric crossover enabled, a
+    /// `tolerance` of `1e-9`, and ten attempts per operator.
+    #[allow(clippy::type_complexity)]
+    pub fn new(semantics: Box<dyn Fn(&E) -> Vec<f64> + Send + Sync + 'a>) -> Self {
+        GPSemantics {
+            semantics,
+            reject_equivalent: true,
+            geometric_crossover: true,
+            tolerance: 1e-9,
+            max_attempts: 10,
+        }
+    }
+    /// Whether `child`, bred from `parents` by crossover or mutation, satisfies the constraints.
+    fn admits(&self, parents: &[Vec<f64>], child: &E, is_crossover: bool) -> bool {
+        let child = (self.semantics)(child);
+        let tolerance = self.tolerance;
+        if self.reject_equivalent
+            && parents.iter().any(|parent| {
+                parent.len() == child.len()
+                    && parent
+                        .iter()
+                        .zip(&child)
+                        .all(|(x, y)| (x - y).abs() <= tolerance)
+            })
+        {
+            return false;
+        }
+        if self.geometric_crossover && is_crossover && parents.len() == 2 {
+            let (p1, p2) = (&parents[0], &parents[1]);
+            if p1.len() != child.len() || p2.len() != child.len() {
+                return false;
+            }
+            return child
+                .iter()
+                .zip(p1.iter().zip(p2))
+                .all(|(&c, (&x, &y))| x.min(y) - tolerance <= c && c <= x.max(y) + tolerance);
+        }
+        true
+    }
+}
+
+/// State carried across generations by [`GP::evolve_with_state`].
+///
+/// [`GP::evolve_with_state`]: trait.GP.html#method.evolve_with_state
+pub struct GPState<'a, E> {
+    /// Diversity-preserving selection. If `None`, selection is based on fitness alone.
+    pub diversity: Option<GPDiversity<'a, E>>,
+    /// Individuals found novel under [`GPDiversity::Novelty`], along with their behaviors.
+    ///
+    /// [`GPDiversity::Novelty`]: enum.GPDiversity.html#variant.Novelty
+    pub archive: Vec<(E, Vec<f64>)>,
+    /// Adaptive operator selection. If `None`, operators are selected according to the static
+    /// [`mutation_prob`] and the representation's own parameters.
+    ///
+    /// [`mutation_prob`]: struct.GPParams.html#structfield.mutation_prob
+    pub adaptive: Option<GPAdaptive>,
+    /// Semantic constraints on offspring. If `None`, offspring are constrained only by
+    /// [`GP::validate_offspring`].
+    ///
+    /// [`GP::validate_offspring`]: trait.GP.html#method.validate_offspring
+    pub semantics: Option<GPSemantics<'a, E>>,
+    /// Controls on the growth of programs. By default, there are none.
+    pub bloat: GPBloat,
+    /// The number of generations evolved so far.
+    pub generation: usize,
+}
+impl<'a, E> Default for GPState<'a, E> {
+    fn default() -> Self {
+        GPState {
+            diversity: None,
+            archive: Vec::new(),
+            adaptive: None,
+            semantics: None,
+            bloat: GPBloat::default(),
+            generation: 0,
+        }
+    }
+}
+impl<'a, E: Clone + Send + Sync> GPState<'a, E> {
+    /// Adjusts the scores of `individuals`, the last `n_children` of which are new offspring.
+    fn adjust<G: GP<Expression = E>>(
+        &mut self,
+        gp: &G,
+        individuals: &[(E, f64)],
+        n_children: usize,
+    ) -> Vec<f64> {
+        match self.diversity {
+            None => individuals.iter().map(|&(_, f)| f).collect(),
+            Some(GPDiversity::FitnessSharing {
+                radius,
+                alpha,
+                ref distance,
+            }) => individuals
+                .par_iter()
+                .map(|(x, f)| {
+                    let niche_count: f64 = individuals
+                        .iter()
+                        .map(|(y, _)| match *distance {
+                            Some(ref distance) => distance(x, y),
+                            None => gp.distance(x, y),
+                        })
+                        .filter(|&d| d < radius)
+                        .map(|d| 1.0 - (d / radius).powf(alpha))
+                        .sum();
+                    f + f.abs() * (niche_count.max(1.0) - 1.0)
+                })
+                .collect(),
+            Some(GPDiversity::Novelty {
+                ref behavior,
+                k,
+                threshold,
+                weight,
+            }) => {
+                let behaviors: Vec<Vec<f64>> =
+                    individuals.par_iter().map(|(x, _)| behavior(x)).collect();
+                let novelties: Vec<f64> = (0..individuals.len())
+                    .into_par_iter()
+                    .map(|i| {
+                        let others = behaviors
+                            .iter()
+                            .enumerate()
+                            .filter(|&(j, _)| j != i)
+                            .map(|(_, b)| b)
+                            .chain(self.archive.iter().map(|(_, b)| b));
+                        let distances = others
+                            .map(|b| euclidean(&behaviors[i], b))
+                            .filter(|d| !d.is_nan())
+                            .sorted_by(|x, y| x.partial_cmp(y).expect("found NaN"))
+                            .take(k)
+                            .collect_vec();
+                        if distances.is_empty() {
+                            0.0
+                        } else {
+                            distances.iter().sum::<f64>() / distances.len() as f64
+                        }
+                    })
+                    .collect();
+                let n_parents = individuals.len() - n_children;
+                for (i, b) in behaviors.into_iter().enumerate().skip(n_parents) {
+                    if novelties[i] > threshold {
+                        self.archive.push((individuals[i].0.clone(), b));
+                    }
+                }
+                individuals
+                    .iter()
+                    .zip(novelties)
+                    .map(|(&(_, f), novelty)| {
+                        let f = if weight < 1.0 {
+                            (1.0 - weight) * f
+                        } else {
+                            0.0
+                        };
+                        f - weight * novelty
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn euclidean(x: &[f64], y: &[f64]) -> f64 {
+    x.iter()
+        .zip(y)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// A summary of the fitness of a population. Only finite fitness values are summarized, with
+/// the remainder counted in `n_nonfinite`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FitnessStats {
+    /// The smallest (best) fitness.
+    pub best: f64,
+    /// The median fitness.
+    pub median: f64,
+    /// The mean fitness.
+    pub mean: f64,
+    /// The standard deviation of fitness.
+    pub std_dev: f64,
+    /// The largest (worst) fitness.
+    pub worst: f64,
+    /// The number of individuals with infinite or NaN fitness.
+    pub n_nonfinite: usize,
+}
+impl FitnessStats {
+    fn new<E>(population: &[(E, f64)]) -> Option<FitnessStats> {
+        let fitnesses: Vec<f64> = population
+            .iter()
+            .map(|(_, fitness)| *fitness)
+            .filter(|fitness| fitness.is_finite())
+            .sorted_by(|x, y| x.partial_cmp(y).expect("found NaN"))
+            .collect();
+        if fitnesses.is_empty() {
+            return None;
+        }
+        let n = fitnesses.len();
+        let mean = fitnesses.iter().sum::<f64>() / n as f64;
+        let variance = fitnesses.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / n as f64;
+        let median = if n % 2 == 1 {
+            fitnesses[n / 2]
+        } else {
+            (fitnesses[n / 2 - 1] + fitnesses[n / 2]) / 2.0
+        };
+        Some(FitnessStats {
+            best: fitnesses[0],
+            median,
+            mean,
+            std_dev: variance.sqrt(),
+            worst: fitnesses[n - 1],
+            n_nonfinite: population.len() - n,
+        })
+    }
+}
+
+/// A record of a single generation, as returned by [`GP::evolve_with_state`] and
+/// [`GP::evolve_alps_with_state`]. A sequence of these gives a learning curve, and can be
+/// serialized for logging.
+///
+/// [`GP::evolve_with_state`]: trait.GP.html#method.evolve_with_state
+/// [`GP::evolve_alps_with_state`]: trait.GP.html#method.evolve_alps_with_state
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GenerationStats {
+    /// The number of generations evolved so far, including this one.
+    pub generation: usize,
+    /// The fitness of the population after selection, or `None` if no individual has finite
+    /// fitness.
+    pub fitness: Option<FitnessStats>,
+    /// The size of the population after selection.
+    pub population_size: usize,
+    /// The number of distinct expressions in the population after selection.
+    pub n_unique: usize,
+    /// The mean [`size`] of expressions in the population after selection.
+    ///
+    /// [`size`]: trait.GP.html#method.size
+    pub mean_size: f64,
+    /// The number of children created by mutation.
+    pub n_mutations: usize,
+    /// The number of children created by crossover.
+    pub n_crossovers: usize,
+    /// The number of offspring discarded by [`validate_offspring`] or as semantically equivalent
+    /// to their parents (see [`GPSemantics`]).
+    ///
+    /// [`GPSemantics`]: struct.GPSemantics.html
+    /// [`validate_offspring`]: trait.GP.html#method.validate_offspring
+    pub n_discarded: usize,
+    /// The probability of selecting each operator, if selection is adaptive. See
+    /// [`GPAdaptive::probabilities`].
+    ///
+    /// [`GPAdaptive::probabilities`]: struct.GPAdaptive.html#method.probabilities
+    pub operator_probabilities: Option<Vec<f64>>,
+}
+impl GenerationStats {
+    fn new<G: GP>(
+        gp: &G,
+        state: &GPState<G::Expression>,
+        population: &[(G::Expression, f64)],
+        lineages: &[Lineage],
+        n_discarded: usize,
+    ) -> GenerationStats {
+        let mut unique: Vec<&G::Expression> = Vec::with_capacity(population.len());
+        for (expr, _) in population {
+            if !unique.contains(&expr) {
+                unique.push(expr);
+            }
+        }
+        let mean_size = if population.is_empty() {
+            0.0
+        } else {
+            population
+                .iter()
+                .map(|(expr, _)| gp.size(expr))
+                .sum::<usize>() as f64
+                / population.len() as f64
+        };
+        let n_crossovers = lineages
+            .iter()
+            .filter(|lineage| lineage.operator == 0)
+            .count();
+        GenerationStats {
+            generation: state.generation,
+            fitness: FitnessStats::new(population),
+            population_size: population.len(),
+            n_unique: unique.len(),
+            mean_size,
+            n_mutations: lineages.len() - n_crossovers,
+            n_crossovers,
+            n_discarded,
+            operator_probabilities: state.adaptive.as_ref().map(GPAdaptive::probabilities),
+        }
+    }
+}
+
+/// An age-layered population, created by [`GP::init_alps`] and evolved by [`GP::evolve_alps`] or
+/// [`GP::evolve_alps_with_state`].
+///
+/// [`GP::init_alps`]: trait.GP.html#method.init_alps
+/// [`GP::evolve_alps`]: trait.GP.html#method.evolve_alps
+/// [`GP::evolve_alps_with_state`]: trait.GP.html#method.evolve_alps_with_state
+#[derive(Debug, Clone)]
+pub struct GPLayers<E> {
+    /// Layers from youngest to oldest. Each layer is a list of programs with their ages, along
+    /// with their scores, sorted by score.
+    pub layers: Vec<Vec<((E, usize), f64)>>,
+    /// The number of generations evolved so far.
+    pub generation: usize,
+}
+impl<E> GPLayers<E> {
+    /// The most-fit individual across all layers, with its age and score.
+    pub fn best(&self) -> Option<&((E, usize), f64)> {
+        self.layers
+            .iter()
+            .filter_map(|layer| layer.first())
+            .min_by(|x, y| x.1.partial_cmp(&y.1).expect("found NaN"))
+    }
+}
+
 /// A kind of representation suitable for **genetic programming**.
 ///
 /// Implementors of `GP` must provide methods for [`genesis`], [`mutate`], [`crossover`]. A
@@ -199,7 +858,7 @@ pub struct GPParams {
 ///     let generations = 1000;
 ///     let rng = &mut SmallRng::from_seed([1u8; 16]);
 ///
-///     let mut pop = g.init(&params, rng, &gpparams, &task);
+///     let mut pop = g.init(&params, rng, &gpparams, &task, None);
 ///     for _ in 0..generations {
 ///         g.evolve(&params, rng, &gpparams, &task, &mut pop)
 ///     }
@@ -222,13 +881,18 @@ pub struct GPParams {
 /// [`pcfg::Grammar`]: pcfg/struct.Grammar.html
 pub trait GP: Send + Sync + Sized {
     /// An Expression is a sentence in the representation. **Tasks are solved by Expressions**.
-    type Expression: Clone + Send + Sync;
+    type Expression: Clone + PartialEq + Send + Sync;
     /// Extra parameters for a representation go here.
     type Params;
     // task-specific information, e.g. an input/output pair, goes here.
     type Observation: Clone + Send + Sync;
 
     /// Create an initial population for a particular requesting type.
+    ///
+    /// This may return fewer than `pop_size` expressions if the representation cannot produce
+    /// enough of the requested type, e.g. within a limited number of attempts. Selection keeps
+    /// the size of the population it is given, so evolution then continues with the smaller
+    /// population.
     fn genesis<R: Rng>(
         &self,
         params: &Self::Params,
@@ -246,6 +910,32 @@ pub trait GP: Send + Sync + Sized {
         obs: &Self::Observation,
     ) -> Vec<Self::Expression>;
 
+    /// The number of distinct mutation operators of the representation, used for adaptive
+    /// operator selection with [`GPAdaptive`]. By default, [`mutate`] is treated as a single
+    /// operator.
+    ///
+    /// [`GPAdaptive`]: struct.GPAdaptive.html
+    /// [`mutate`]: #tymethod.mutate
+    fn n_mutation_operators(&self, _params: &Self::Params) -> usize {
+        1
+    }
+
+    /// Mutate a single program using a particular operator, indexed from zero up to
+    /// [`n_mutation_operators`]. By default, this is [`mutate`].
+    ///
+    /// [`n_mutation_operators`]: #method.n_mutation_operators
+    /// [`mutate`]: #tymethod.mutate
+    fn mutate_with_operator<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        prog: &Self::Expression,
+        obs: &Self::Observation,
+        _operator: usize,
+    ) -> Vec<Self::Expression> {
+        self.mutate(params, rng, prog, obs)
+    }
+
     /// Perform crossover between two programs. There must be at least one child.
     fn crossover<R: Rng>(
         &self,
@@ -256,7 +946,37 @@ pub trait GP: Send + Sync + Sized {
         obs: &Self::Observation,
     ) -> Vec<Self::Expression>;
 
-    /// A tournament selects an individual from a population.
+    /// The size of a program, such as the number of nodes in its syntax tree. Used for
+    /// [`GPBloat`]. By default, every program has size `1`.
+    ///
+    /// [`GPBloat`]: struct.GPBloat.html
+    fn size(&self, _expr: &Self::Expression) -> usize {
+        1
+    }
+
+    /// The depth of a program's syntax tree. Used for [`GPBloat`]. By default, every program has
+    /// depth `1`.
+    ///
+    /// [`GPBloat`]: struct.GPBloat.html
+    fn depth(&self, _expr: &Self::Expression) -> usize {
+        1
+    }
+
+    /// A distance between programs, used for [`GPDiversity::FitnessSharing`] when no other
+    /// distance is given. By default, this is the difference in [`size`].
+    ///
+    /// [`GPDiversity::FitnessSharing`]: enum.GPDiversity.html#variant.FitnessSharing
+    /// [`size`]: #method.size
+    fn distance(&self, expr1: &Self::Expression, expr2: &Self::Expression) -> f64 {
+        (self.size(expr1) as f64 - self.size(expr2) as f64).abs()
+    }
+
+    /// A tournament selects an individual from a population. Of `tournament_size` contestants
+    /// drawn uniformly at random, the fittest (the one with the smallest score) wins.
+    ///
+    /// This is a change in behavior: previously the contestant with the _largest_ score, i.e. the
+    /// least fit, won. Evolution with the default tournament, even from the same seed, therefore
+    /// yields different populations than before. To keep the old selection, override this method.
     fn tournament<'a, R: Rng>(
         &self,
         rng: &mut R,
@@ -270,7 +990,7 @@ pub trait GP: Send + Sync + Sized {
                 .choose_multiple(rng, tournament_size)
                 .into_iter()
                 .map(|i| &population[i])
-                .max_by(|&&(_, ref x), &&(_, ref y)| x.partial_cmp(y).expect("found NaN"))
+                .min_by(|&&(_, ref x), &&(_, ref y)| x.partial_cmp(y).expect("found NaN"))
                 .map(|&(ref expr, _)| expr)
                 .expect("tournament cannot select winner from no contestants")
         }
@@ -278,20 +998,37 @@ pub trait GP: Send + Sync + Sized {
 
     /// Initializes a population, which is a list of programs and their scores sorted by score.
     /// The most-fit individual is the first element in the population.
+    ///
+    /// If a `seed` population is given, such as one restored with
+    /// [`GPCheckpoint::load_population`], up to [`population_size`] of its expressions are
+    /// included and [`genesis`] supplies the rest. Fitness of the initial population is evaluated
+    /// in parallel.
+    ///
+    /// [`GPCheckpoint::load_population`]: trait.GPCheckpoint.html#method.load_population
+    /// [`population_size`]: struct.GPParams.html#structfield.population_size
+    /// [`genesis`]: #tymethod.genesis
     fn init<R: Rng, O: Sync>(
         &self,
         params: &Self::Params,
         rng: &mut R,
         gpparams: &GPParams,
         task: &Task<Self, Self::Expression, O>,
+        seed: Option<Vec<Self::Expression>>,
     ) -> Vec<(Self::Expression, f64)> {
-        let exprs = self.genesis(params, rng, gpparams.population_size, &task.tp);
+        let mut exprs = seed.unwrap_or_default();
+        exprs.truncate(gpparams.population_size);
+        if exprs.len() < gpparams.population_size {
+            let n = gpparams.population_size - exprs.len();
+            exprs.append(&mut self.genesis(params, rng, n, &task.tp));
+        }
         exprs
-            .into_iter()
+            .into_par_iter()
             .map(|expr| {
                 let l = (task.oracle)(self, &expr);
                 (expr, l)
             })
+            .collect::<Vec<_>>()
+            .into_iter()
             .sorted_by(|&(_, ref x), &(_, ref y)| x.partial_cmp(y).expect("found NaN"))
             .collect()
     }
@@ -313,10 +1050,15 @@ pub trait GP: Send + Sync + Sized {
 
     /// Evolves a population. This will repeatedly run a Bernoulli trial with parameter
     /// [`mutation_prob`] and perform mutation or crossover depending on the outcome until
-    /// [`n_delta`] expressions are determined.
+    /// [`n_delta`] expressions are determined. The fitness of those expressions is evaluated in
+    /// parallel.
+    ///
+    /// Use [`evolve_with_state`] for bloat control, other extensions to evolution, and statistics
+    /// for each generation.
     ///
     /// [`mutation_prob`]: struct.GPParams.html#mutation_prob
     /// [`n_delta`]: struct.GPParams.html#n_delta
+    /// [`evolve_with_state`]: #method.evolve_with_state
     fn evolve<R: Rng>(
         &self,
         params: &Self::Params,
@@ -325,33 +1067,548 @@ pub trait GP: Send + Sync + Sized {
         task: &Task<Self, Self::Expression, Self::Observation>,
         population: &mut Vec<(Self::Expression, f64)>,
     ) {
-        let mut children = Vec::with_capacity(gpparams.n_delta);
-        while children.len() < gpparams.n_delta {
-            let mut offspring = if rng.gen_bool(gpparams.mutation_prob) {
-                let parent = self.tournament(rng, gpparams.tournament_size, population);
-                self.mutate(params, rng, parent, &task.observation)
-            } else {
-                let parent1 = self.tournament(rng, gpparams.tournament_size, population);
-                let parent2 = self.tournament(rng, gpparams.tournament_size, population);
-                self.crossover(params, rng, parent1, parent2, &task.observation)
-            };
-            self.validate_offspring(params, population, &children, &mut offspring);
-            children.append(&mut offspring);
+        evolve_generation(
+            self,
+            params,
+            rng,
+            gpparams,
+            task,
+            population,
+            &mut GPState::default(),
+        );
+    }
+
+    /// Like [`evolve`], but with a [`GPState`] that persists across generations. This allows for
+    /// diversity-preserving selection with [`GPDiversity`] and bloat control with [`GPBloat`].
+    /// Returns [`GenerationStats`] describing the generation.
+    ///
+    /// [`evolve`]: #method.evolve
+    /// [`GPState`]: struct.GPState.html
+    /// [`GPDiversity`]: enum.GPDiversity.html
+    /// [`GPBloat`]: struct.GPBloat.html
+    /// [`GenerationStats`]: struct.GenerationStats.html
+    fn evolve_with_state<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        gpparams: &GPParams,
+        task: &Task<Self, Self::Expression, Self::Observation>,
+        population: &mut Vec<(Self::Expression, f64)>,
+        state: &mut GPState<Self::Expression>,
+    ) -> GenerationStats {
+        let (lineages, n_discarded) =
+            evolve_generation(self, params, rng, gpparams, task, population, state);
+        GenerationStats::new(self, state, population, &lineages, n_discarded)
+    }
+
+    /// Initializes an age-layered population according to [`GPAlps`]. Only the youngest layer is
+    /// populated, with individuals of age zero.
+    ///
+    /// [`GPAlps`]: struct.GPAlps.html
+    fn init_alps<R: Rng, O: Sync>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        gpparams: &GPParams,
+        alps: &GPAlps,
+        task: &Task<Self, Self::Expression, O>,
+    ) -> GPLayers<Self::Expression> {
+        let mut layers = vec![Vec::new(); alps.n_layers.max(1)];
+        layers[0] = self
+            .init(params, rng, gpparams, task, None)
+            .into_iter()
+            .map(|(expr, fitness)| ((expr, 0), fitness))
+            .collect();
+        GPLayers {
+            layers,
+            generation: 0,
         }
-        children.truncate(gpparams.n_delta);
+    }
+
+    /// Evolves an age-layered population according to [`GPAlps`]. Each layer is evolved like a
+    /// population in [`evolve`], producing [`n_delta`] children.
+    ///
+    /// Use [`evolve_alps_with_state`] for bloat control, other extensions to evolution, and
+    /// statistics for each generation.
+    ///
+    /// [`GPAlps`]: struct.GPAlps.html
+    /// [`evolve`]: #method.evolve
+    /// [`n_delta`]: struct.GPParams.html#n_delta
+    /// [`evolve_alps_with_state`]: #method.evolve_alps_with_state
+    fn evolve_alps<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        gpparams: &GPParams,
+        alps: &GPAlps,
+        task: &Task<Self, Self::Expression, Self::Observation>,
+        layers: &mut GPLayers<Self::Expression>,
+    ) {
+        self.evolve_alps_with_state(
+            params,
+            rng,
+            gpparams,
+            alps,
+            task,
+            layers,
+            &mut GPState::default(),
+        );
+    }
+
+    /// Like [`evolve_alps`], but with a [`GPState`] that persists across generations, as in
+    /// [`evolve_with_state`]. Each layer is bred with the state's adaptive operator selection,
+    /// semantic constraints, and bloat control. Its diversity-preserving selection is not used,
+    /// since layering by age already maintains diversity. Returns [`GenerationStats`] describing
+    /// the generation across all layers.
+    ///
+    /// [`evolve_alps`]: #method.evolve_alps
+    /// [`evolve_with_state`]: #method.evolve_with_state
+    /// [`GPState`]: struct.GPState.html
+    /// [`GenerationStats`]: struct.GenerationStats.html
+    #[allow(clippy::too_many_arguments)]
+    fn evolve_alps_with_state<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        gpparams: &GPParams,
+        alps: &GPAlps,
+        task: &Task<Self, Self::Expression, Self::Observation>,
+        layers: &mut GPLayers<Self::Expression>,
+        state: &mut GPState<Self::Expression>,
+    ) -> GenerationStats {
+        if let Some(ref mut adaptive) = state.adaptive {
+            adaptive.initialize(self.n_mutation_operators(params));
+        }
+        layers.generation += 1;
+        // breed each layer from itself and the layer below it.
+        let mut children: Vec<Vec<_>> = Vec::with_capacity(layers.layers.len());
+        let mut all_lineages = Vec::new();
+        let mut n_discarded = 0;
+        for i in 0..layers.layers.len() {
+            let (pool, ages): (Vec<_>, Vec<_>) = layers.layers[i.saturating_sub(1)..=i]
+                .iter()
+                .flatten()
+                .map(|((expr, age), fitness)| ((expr.clone(), *fitness), *age))
+                .unzip();
+            if pool.is_empty() {
+                children.push(Vec::new());
+                continue;
+            }
+            let (bred, lineages, discarded) = breed(
+                self,
+                params,
+                rng,
+                gpparams,
+                task,
+                &pool,
+                &|j| ages[j],
+                state.adaptive.as_ref(),
+                state.semantics.as_ref(),
+                &state.bloat,
+            );
+            let scored = score(self, &state.bloat, task, &pool, bred, rng);
+            if let Some(ref mut adaptive) = state.adaptive {
+                adaptive.credit(&lineages, &scored);
+            }
+            children.push(
+                scored
+                    .into_iter()
+                    .zip(&lineages)
+                    .map(|((expr, fitness), lineage)| ((expr, lineage.age), fitness))
+                    .collect(),
+            );
+            all_lineages.extend(lineages);
+            n_discarded += discarded;
+        }
+        for ((_, age), _) in layers.layers.iter_mut().flatten() {
+            *age += 1;
+        }
+        for ((_, age), _) in children.iter_mut().flatten() {
+            *age += 1;
+        }
+        // inject fresh individuals into the youngest layer. Its previous occupants and children
+        // are displaced to compete for a place in the next layer.
+        let mut displaced = Vec::new();
+        if alps.age_gap > 0 && layers.generation % alps.age_gap == 0 {
+            let fresh = self
+                .init_alps(params, rng, gpparams, alps, task)
+                .layers
+                .swap_remove(0);
+            displaced = mem::replace(&mut layers.layers[0], fresh);
+            displaced.append(&mut children[0]);
+        }
+        // survivors stay in their layer if they are young enough, otherwise they move up.
+        let oracle = |x: &(Self::Expression, usize)| (task.oracle)(self, &x.0);
+        let mut migrants = Vec::new();
+        for (i, (layer, children)) in layers.layers.iter_mut().zip(children).enumerate() {
+            let mut candidates = mem::take(&mut migrants);
+            candidates.extend(children);
+            if i == 1 {
+                candidates.append(&mut displaced);
+            }
+            if let Some(max_age) = alps.max_age(i) {
+                let (young, old): (Vec<_>, Vec<_>) =
+                    layer.drain(..).partition(|&((_, age), _)| age < max_age);
+                *layer = young;
+                migrants.extend(old);
+                let (young, old): (Vec<_>, Vec<_>) = candidates
+                    .into_iter()
+                    .partition(|&((_, age), _)| age < max_age);
+                candidates = young;
+                migrants.extend(old);
+            }
+            candidates.sort_by(|x, y| x.1.partial_cmp(&y.1).expect("found NaN"));
+            let n_free = gpparams
+                .population_size
+                .saturating_sub(layer.len())
+                .min(candidates.len());
+            let rest = candidates.split_off(n_free);
+            layer.append(&mut candidates);
+            layer.sort_by(|x, y| x.1.partial_cmp(&y.1).expect("found NaN"));
+            if !rest.is_empty() {
+                gpparams
+                    .selection
+                    .update_population(layer, rest, Box::new(oracle), rng);
+            }
+        }
+        state.generation += 1;
+        let population: Vec<_> = layers
+            .layers
+            .iter()
+            .flatten()
+            .map(|((expr, _), fitness)| (expr.clone(), *fitness))
+            .collect();
+        GenerationStats::new(self, state, &population, &all_lineages, n_discarded)
+    }
+}
+
+/// A [`GP`] representation whose expressions can be written as, and read back from, a single line
+/// of text. This allows populations to be saved to disk with [`save_population`] and restored
+/// with [`load_population`], so that a run can be resumed or a new run seeded (via [`GP::init`])
+/// with individuals from a previous task.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate polytype;
+/// extern crate programinduction;
+/// use programinduction::pcfg::{Grammar, Rule};
+/// use programinduction::GPCheckpoint;
+///
+/// # fn main() {
+/// let g = Grammar::new(
+///     tp!(EXPR),
+///     vec![
+///         Rule::new("0", tp!(EXPR), 1.0),
+///         Rule::new("plus", tp!(@arrow[tp!(EXPR), tp!(EXPR), tp!(EXPR)]), 1.0),
+///     ],
+/// );
+/// let population = vec![
+///     (g.parse("0").unwrap(), 1.0),
+///     (g.parse("plus(0,0)").unwrap(), std::f64::INFINITY),
+/// ];
+///
+/// let mut saved = Vec::new();
+/// g.save_population(&population, &mut saved).unwrap();
+/// let loaded = g.load_population(&saved[..]).unwrap();
+///
+/// assert_eq!(loaded, population);
+/// # }
+/// ```
+///
+/// [`GP`]: trait.GP.html
+/// [`GP::init`]: trait.GP.html#method.init
+/// [`save_population`]: #method.save_population
+/// [`load_population`]: #method.load_population
+pub trait GPCheckpoint: GP {
+    /// The error produced when an expression cannot be parsed.
+    type ParseError: Error + Send + Sync + 'static;
+
+    /// Writes an expression as a single line of text.
+    fn display_expression(&self, expr: &Self::Expression) -> String;
+
+    /// Reads an expression written by [`display_expression`].
+    ///
+    /// Parsing may extend the representation with what the expression refers to. For example, a
+    /// TRS [`Lexicon`] gains the variables of the parsed rules.
+    ///
+    /// [`display_expression`]: #tymethod.display_expression
+    /// [`Lexicon`]: trs/struct.Lexicon.html
+    fn parse_expression(&self, s: &str) -> Result<Self::Expression, Self::ParseError>;
+
+    /// Writes a population, one individual per line as its fitness and expression separated by a
+    /// tab.
+    fn save_population<W: Write>(
+        &self,
+        population: &[(Self::Expression, f64)],
+        mut writer: W,
+    ) -> io::Result<()> {
+        for (expr, fitness) in population {
+            writeln!(writer, "{}\t{}", fitness, self.display_expression(expr))?;
+        }
+        Ok(())
+    }
+
+    /// Reads a population written by [`save_population`]. Blank lines are ignored.
+    ///
+    /// [`save_population`]: #method.save_population
+    fn load_population<B: BufRead>(&self, reader: B) -> io::Result<Vec<(Self::Expression, f64)>> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut population = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.splitn(2, '\t');
+            let fitness = fields.next().unwrap_or_default();
+            let fitness = fitness
+                .parse::<f64>()
+                .map_err(|_| invalid(format!("invalid fitness {:?}", fitness)))?;
+            let expr = fields
+                .next()
+                .ok_or_else(|| invalid(format!("missing expression in {:?}", line)))?;
+            let expr = self
+                .parse_expression(expr)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            population.push((expr, fitness));
+        }
+        Ok(population)
+    }
+}
+
+/// How a child came to be.
+#[derive(Debug, Clone, Copy)]
+struct Lineage {
+    /// The greatest age among the child's parents.
+    age: usize,
+    /// The operator which produced the child: `0` for crossover, or `1 + i` for the `i`th
+    /// mutation operator.
+    operator: usize,
+    /// The best fitness among the child's parents.
+    parent_fitness: f64,
+}
+
+/// A generation of [`GP::evolve_with_state`], returning the lineages of the children and the
+/// number of offspring discarded.
+///
+/// [`GP::evolve_with_state`]: trait.GP.html#method.evolve_with_state
+fn evolve_generation<G: GP, R: Rng>(
+    gp: &G,
+    params: &G::Params,
+    rng: &mut R,
+    gpparams: &GPParams,
+    task: &Task<G, G::Expression, G::Observation>,
+    population: &mut Vec<(G::Expression, f64)>,
+    state: &mut GPState<G::Expression>,
+) -> (Vec<Lineage>, usize) {
+    if let Some(ref mut adaptive) = state.adaptive {
+        adaptive.initialize(gp.n_mutation_operators(params));
+    }
+    let adaptive = state.adaptive.as_ref();
+    let (children, lineages, n_discarded) = breed(
+        gp,
+        params,
+        rng,
+        gpparams,
+        task,
+        population,
+        &|_| 0,
+        adaptive,
+        state.semantics.as_ref(),
+        &state.bloat,
+    );
+    let scored_children = score(gp, &state.bloat, task, population, children, rng);
+    if let Some(ref mut adaptive) = state.adaptive {
+        adaptive.credit(&lineages, &scored_children);
+    }
+    if state.diversity.is_none() {
         gpparams.selection.update_population(
             population,
+            scored_children,
+            Box::new(|child| (task.oracle)(gp, child)),
+            rng,
+        );
+    } else {
+        // drift the unadjusted fitness of existing individuals before adjusting it, so that it is
+        // not left stale by selection below.
+        if let GPSelection::Drift(alpha) = gpparams.selection {
+            population.par_iter_mut().for_each(|(expr, fitness)| {
+                *fitness = alpha * *fitness + (1.0 - alpha) * (task.oracle)(gp, expr);
+            });
+        }
+        // select on adjusted scores, carrying the unadjusted fitness along with each
+        // individual. The adjusted score is carried too, so that drift leaves it unchanged.
+        let n_children = scored_children.len();
+        let mut individuals = Vec::with_capacity(population.len() + n_children);
+        individuals.append(population);
+        individuals.extend(scored_children);
+        let adjusted = state.adjust(gp, &individuals, n_children);
+        let mut ranked: Vec<_> = individuals
+            .into_iter()
+            .zip(adjusted)
+            .map(|(individual, score)| ((individual, score), score))
+            .collect();
+        let children = ranked.split_off(ranked.len() - n_children);
+        ranked.sort_by(|a, b| a.1.partial_cmp(&b.1).expect("found NaN"));
+        gpparams.selection.update_population(
+            &mut ranked,
             children,
-            Box::new(|child| (task.oracle)(self, child)),
+            Box::new(|&(_, score)| score),
             rng,
         );
+        *population = ranked
+            .into_iter()
+            .map(|((individual, _), _)| individual)
+            .sorted_by(|(_, x), (_, y)| x.partial_cmp(y).expect("found NaN"))
+            .collect();
+    }
+    state.generation += 1;
+    (lineages, n_discarded)
+}
+
+/// Creates [`n_delta`] children from the population by mutation and crossover (or none, if the
+/// population is empty), along with their lineage and the number of offspring discarded by
+/// validation. `age` maps an index of the
+/// population to an age. Operators are selected by `adaptive`, if given, and offspring are subject
+/// to the limits of `bloat`.
+///
+/// [`n_delta`]: struct.GPParams.html#n_delta
+#[allow(clippy::too_many_arguments)]
+fn breed<G: GP, R: Rng>(
+    gp: &G,
+    params: &G::Params,
+    rng: &mut R,
+    gpparams: &GPParams,
+    task: &Task<G, G::Expression, G::Observation>,
+    population: &[(G::Expression, f64)],
+    age: &dyn Fn(usize) -> usize,
+    adaptive: Option<&GPAdaptive>,
+    semantics: Option<&GPSemantics<G::Expression>>,
+    bloat: &GPBloat,
+) -> (Vec<G::Expression>, Vec<Lineage>, usize) {
+    let lineage_of = |parent: &G::Expression, operator: usize| match population
+        .iter()
+        .position(|(expr, _)| ptr::eq(expr, parent))
+    {
+        Some(i) => Lineage {
+            age: age(i),
+            operator,
+            parent_fitness: population[i].1,
+        },
+        None => Lineage {
+            age: 0,
+            operator,
+            parent_fitness: f64::INFINITY,
+        },
+    };
+    if population.is_empty() {
+        return (Vec::new(), Vec::new(), 0);
     }
+    let mut children = Vec::with_capacity(gpparams.n_delta);
+    let mut lineages = Vec::with_capacity(gpparams.n_delta);
+    let mut n_discarded = 0;
+    while children.len() < gpparams.n_delta {
+        let operator = adaptive.map(|adaptive| adaptive.sample(rng));
+        let is_mutation = match operator {
+            Some(operator) => operator > 0,
+            None => rng.gen_bool(gpparams.mutation_prob),
+        };
+        let mut attempts = 1;
+        let (mut offspring, lineage) = loop {
+            let (offspring, lineage, parents) = if is_mutation {
+                let parent = bloat.select(gp, rng, gpparams.tournament_size, population);
+                let mut offspring = match operator {
+                    Some(operator) => gp.mutate_with_operator(
+                        params,
+                        rng,
+                        parent,
+                        &task.observation,
+                        operator - 1,
+                    ),
+                    None => gp.mutate(params, rng, parent, &task.observation),
+                };
+                bloat.limit(gp, parent, &mut offspring);
+                (
+                    offspring,
+                    lineage_of(parent, operator.unwrap_or(1)),
+                    vec![parent],
+                )
+            } else {
+                let parent1 = bloat.select(gp, rng, gpparams.tournament_size, population);
+                let parent2 = bloat.select(gp, rng, gpparams.tournament_size, population);
+                let mut offspring = gp.crossover(params, rng, parent1, parent2, &task.observation);
+                bloat.limit(gp, parent1, &mut offspring);
+                let lineage1 = lineage_of(parent1, 0);
+                let lineage2 = lineage_of(parent2, 0);
+                let lineage = Lineage {
+                    age: lineage1.age.max(lineage2.age),
+                    operator: 0,
+                    parent_fitness: lineage1.parent_fitness.min(lineage2.parent_fitness),
+                };
+                (offspring, lineage, vec![parent1, parent2])
+            };
+            match semantics {
+                Some(semantics) if attempts < semantics.max_attempts => {
+                    let parents: Vec<_> = parents.into_iter().map(&semantics.semantics).collect();
+                    let n_offspring = offspring.len();
+                    let offspring: Vec<_> = offspring
+                        .into_iter()
+                        .filter(|child| semantics.admits(&parents, child, !is_mutation))
+                        .collect();
+                    n_discarded += n_offspring - offspring.len();
+                    if !offspring.is_empty() {
+                        break (offspring, lineage);
+                    }
+                    attempts += 1;
+                }
+                _ => break (offspring, lineage),
+            }
+        };
+        let n_offspring = offspring.len();
+        gp.validate_offspring(params, population, &children, &mut offspring);
+        n_discarded += n_offspring - offspring.len();
+        lineages.extend(repeat_n(lineage, offspring.len()));
+        children.append(&mut offspring);
+    }
+    children.truncate(gpparams.n_delta);
+    lineages.truncate(gpparams.n_delta);
+    (children, lineages, n_discarded)
+}
+
+/// Evaluates the fitness of children in parallel, subject to the Tarpeian method of `bloat`.
+fn score<G: GP, R: Rng>(
+    gp: &G,
+    bloat: &GPBloat,
+    task: &Task<G, G::Expression, G::Observation>,
+    population: &[(G::Expression, f64)],
+    children: Vec<G::Expression>,
+    rng: &mut R,
+) -> Vec<(G::Expression, f64)> {
+    let killed = bloat.tarpeian(gp, population, &children, rng);
+    children
+        .into_par_iter()
+        .zip(killed)
+        .map(|(child, killed)| {
+            let fitness = if killed {
+                f64::INFINITY
+            } else {
+                (task.oracle)(gp, &child)
+            };
+            (child, fitness)
+        })
+        .collect()
 }
 
 /// Given a `Vec` of item-score pairs sorted by score, and some `sample_size`,
 /// return a score-sorted sample selected in inverse proportion to its overall
 /// score.
-fn sample_pop<T: Clone>(options: Vec<(T, f64)>, sample_size: usize) -> Vec<(T, f64)> {
+fn sample_pop<R: Rng, T: Clone>(
+    options: Vec<(T, f64)>,
+    sample_size: usize,
+    rng: &mut R,
+) -> Vec<(T, f64)> {
     // TODO: Is this necessary. Could we just sample a weighted permutation
     // rather than do all the combinatorics?
     // https://softwareengineering.stackexchange.com/questions/233541
@@ -367,7 +1624,7 @@ fn sample_pop<T: Clone>(options: Vec<(T, f64)>, sample_size: usize) -> Vec<(T, f
         .iter()
         .map(|x| (x - sum_scores).exp())
         .collect::<Vec<_>>();
-    let idx = weighted_sample(&idxs, &scores);
+    let idx = weighted_sample(&idxs, &scores, rng);
     options
         .into_iter()
         .combinations(sample_size)
@@ -0,0 +1,312 @@
+//! Bottom-up, example-driven enumeration of lambda calculus expressions.
+
+use polytype::{Context, Type, TypeSchema};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::eval::eval_with_limits;
+use super::{CompiledExpression, EvalLimits, Evaluator, Expression, Language};
+
+/// Parameters for bottom-up synthesis with [`Language::enumerate_bottom_up`] and
+/// [`Language::explore_bottom_up`].
+///
+/// [`Language::enumerate_bottom_up`]: struct.Language.html#method.enumerate_bottom_up
+/// [`Language::explore_bottom_up`]: struct.Language.html#method.explore_bottom_up
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BottomUpParams {
+    /// The maximum size, in number of primitives, inventions, and variables, of a program.
+    pub max_size: usize,
+    /// The maximum number of observationally distinct programs kept in the bank. Programs are no
+    /// longer grown once the bank is this large.
+    pub max_bank_size: usize,
+    /// Limits on evaluating each program on the inputs. The timeout is further cut short by the
+    /// search deadline, if there is one.
+    pub eval_limits: EvalLimits,
+}
+impl Default for BottomUpParams {
+    /// max_size: 8, max_bank_size: 100_000, eval_limits: no limits
+    fn default() -> Self {
+        BottomUpParams {
+            max_size: 8,
+            max_bank_size: 100_000,
+            eval_limits: EvalLimits::default(),
+        }
+    }
+}
+
+/// A program body, whose free variables are the inputs, with its values on every input.
+struct Entry<V> {
+    expr: Expression,
+    tp: Type,
+    values: Vec<V>,
+}
+
+fn hash_values<V: Hash>(values: &[V]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    values.hash(&mut hasher);
+    hasher.finish()
+}
+
+struct BottomUp<'a, E: Evaluator + 'a> {
+    dsl: &'a Language,
+    evaluator: &'a Arc<E>,
+    inputs: &'a [Vec<E::Space>],
+    /// Limits on evaluation, whose timeout is cut short by the deadline of the search.
+    limits: &'a EvalLimits,
+    deadline: Option<Instant>,
+    entries: Vec<Entry<E::Space>>,
+    /// Indices into `entries` for each program size.
+    by_size: Vec<Vec<usize>>,
+    /// Indices into `entries` for each type and hash of values, for finding observationally
+    /// equivalent programs.
+    by_values: HashMap<(Type, u64), Vec<usize>>,
+}
+impl<'a, E> BottomUp<'a, E>
+where
+    E: Evaluator,
+    E::Space: Hash,
+{
+    /// Adds the program to the bank unless an observationally equivalent one is already there.
+    fn insert(&mut self, size: usize, entry: Entry<E::Space>) -> bool {
+        let entries = &self.entries;
+        let key = (entry.tp.clone(), hash_values(&entry.values));
+        let idxs = self.by_values.entry(key).or_default();
+        if idxs.iter().any(|&i| entries[i].values == entry.values) {
+            return false;
+        }
+        idxs.push(self.entries.len());
+        while self.by_size.len() <= size {
+            self.by_size.push(Vec::new());
+        }
+        self.by_size[size].push(self.entries.len());
+        self.entries.push(entry);
+        true
+    }
+    /// The evaluation limits, with the timeout cut short by the deadline.
+    fn limits(&self) -> EvalLimits {
+        let mut limits = self.limits.clone();
+        if let Some(deadline) = self.deadline {
+            let left = deadline.saturating_duration_since(Instant::now());
+            limits.timeout = Some(limits.timeout.map_or(left, |timeout| timeout.min(left)));
+        }
+        limits
+    }
+    /// Evaluates a constant, which is the same on every input.
+    fn constant(&self, expr: &Expression) -> Option<Vec<E::Space>> {
+        let value = eval_with_limits(self.dsl, expr, self.evaluator, &[], &self.limits()).ok()?;
+        Some(vec![value; self.inputs.len()])
+    }
+    /// Evaluates `f` on each input's argument values.
+    fn apply(&self, f: &CompiledExpression<E::Space>, args: &[usize]) -> Option<Vec<E::Space>> {
+        let arg_values: Vec<Vec<_>> = (0..self.inputs.len())
+            .map(|i| {
+                args.iter()
+                    .map(|&arg| self.entries[arg].values[i].clone())
+                    .collect()
+            })
+            .collect();
+        let inputs = arg_values.iter().map(|arg_values| &arg_values[..]);
+        f.eval_batch_with_limits(self.evaluator, inputs, &self.limits())
+            .into_iter()
+            .map(Result::ok)
+            .collect()
+    }
+    /// Calls `cb` with every well-typed application of `f` to programs from the bank with sizes
+    /// summing to `size`, without symmetry violations, until it returns `false`.
+    ///
+    /// Applications are generated as they are needed, so `cb` may add programs to the bank. Only
+    /// programs smaller than `size` are arguments, and those are left alone.
+    fn applications<C>(
+        &mut self,
+        f: &Expression,
+        arg_tps: &[Type],
+        ret_tp: &Type,
+        ctx: &Context,
+        size: usize,
+        cb: &mut C,
+    ) -> bool
+    where
+        C: FnMut(&mut Self, &[usize], Type) -> bool,
+    {
+        self.applications_internal(f, arg_tps, ret_tp, ctx, size, &mut Vec::new(), cb)
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn applications_internal<C>(
+        &mut self,
+        f: &Expression,
+        arg_tps: &[Type],
+        ret_tp: &Type,
+        ctx: &Context,
+        size: usize,
+        args: &mut Vec<usize>,
+        cb: &mut C,
+    ) -> bool
+    where
+        C: FnMut(&mut Self, &[usize], Type) -> bool,
+    {
+        let pos = args.len();
+        if pos == arg_tps.len() {
+            return size != 0 || cb(self, args, ret_tp.apply(ctx));
+        }
+        let n_rest = arg_tps.len() - pos - 1;
+        if size < n_rest + 1 {
+            return true;
+        }
+        let arg_tp = arg_tps[pos].apply(ctx);
+        let sizes = if n_rest == 0 {
+            size..=size
+        } else {
+            1..=size - n_rest
+        };
+        for arg_size in sizes {
+            let n = self.by_size.get(arg_size).map_or(0, Vec::len);
+            for j in 0..n {
+                let arg = self.by_size[arg_size][j];
+                if self.dsl.violates_symmetry(f, pos, &self.entries[arg].expr) {
+                    continue;
+                }
+                let mut ctx = ctx.clone();
+                if ctx.unify(&arg_tp, &self.entries[arg].tp).is_err() {
+                    continue;
+                }
+                args.push(arg);
+                let size = size - arg_size;
+                let keep_going =
+                    self.applications_internal(f, arg_tps, ret_tp, &ctx, size, args, cb);
+                args.pop();
+                if !keep_going {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Enumerates observationally distinct programs for the request in order of size, calling
+/// `termination_condition` with each program and its log-prior until it returns `true`, or until
+/// the deadline passes.
+pub fn run<E, F>(
+    dsl: &Language,
+    params: &BottomUpParams,
+    evaluator: &Arc<E>,
+    request: &TypeSchema,
+    inputs: &[Vec<E::Space>],
+    deadline: Option<Instant>,
+    mut termination_condition: F,
+) where
+    E: Evaluator,
+    E::Space: Hash,
+    F: FnMut(Expression, f64) -> bool,
+{
+    let mut ctx = Context::default();
+    let req = request.instantiate(&mut ctx);
+    let req_args: Vec<Type> = req
+        .args()
+        .map(|args| args.into_iter().cloned().collect())
+        .unwrap_or_default();
+    let req_ret = req.returns().unwrap_or(&req).clone();
+    let n_args = req_args.len();
+    let timed_out = || match deadline {
+        Some(deadline) => Instant::now() >= deadline,
+        None => false,
+    };
+
+    let mut bank = BottomUp {
+        dsl,
+        evaluator,
+        inputs,
+        limits: &params.eval_limits,
+        deadline,
+        entries: Vec::new(),
+        by_size: Vec::new(),
+        by_values: HashMap::new(),
+    };
+    // primitives and inventions which take at least one argument, with instantiated types
+    let mut functions = Vec::new();
+    let mut found = Vec::new();
+
+    // programs of size one: inputs and constants
+    for i in 0..n_args {
+        let tp = req_args[n_args - 1 - i].clone();
+        if tp.as_arrow().is_none() {
+            let values = inputs.iter().map(|inps| inps[n_args - 1 - i].clone());
+            let entry = Entry {
+                expr: Expression::Index(i),
+                tp,
+                values: values.collect(),
+            };
+            if bank.insert(1, entry) {
+                found.push(bank.entries.len() - 1);
+            }
+        }
+    }
+    let heads = (0..dsl.primitives.len())
+        .map(|i| (Expression::Primitive(i), &dsl.primitives[i].1))
+        .chain((0..dsl.invented.len()).map(|i| (Expression::Invented(i), &dsl.invented[i].1)));
+    for (expr, schema) in heads {
+        let tp = schema.instantiate(&mut ctx);
+        if let Some(arg_tps) = tp.args() {
+            if arg_tps.iter().all(|tp| tp.as_arrow().is_none()) {
+                let arg_tps: Vec<Type> = arg_tps.into_iter().cloned().collect();
+                let ret_tp = tp.returns().unwrap().clone();
+                let compiled = dsl.compile(&expr);
+                functions.push((expr, compiled, ret_tp, arg_tps));
+            }
+        } else if tp.vars().is_empty() {
+            if let Some(values) = bank.constant(&expr) {
+                if bank.insert(1, Entry { expr, tp, values }) {
+                    found.push(bank.entries.len() - 1);
+                }
+            }
+        }
+    }
+
+    let mut size = 1;
+    loop {
+        for i in found.drain(..) {
+            let entry = &bank.entries[i];
+            if ctx.clone().unify(&entry.tp, &req_ret).is_ok() {
+                let expr = (0..n_args).fold(entry.expr.clone(), |body, _| {
+                    Expression::Abstraction(Box::new(body))
+                });
+                let logprior = dsl.likelihood(request, &expr);
+                if termination_condition(expr, logprior) {
+                    return;
+                }
+            }
+        }
+        size += 1;
+        if size > params.max_size || bank.entries.len() >= params.max_bank_size {
+            return;
+        }
+        for (f, compiled, ret_tp, arg_tps) in &functions {
+            let cb = &mut |bank: &mut BottomUp<E>, args: &[usize], tp: Type| {
+                if timed_out() || bank.entries.len() >= params.max_bank_size {
+                    return false;
+                }
+                if tp.vars().is_empty() && tp.as_arrow().is_none() {
+                    if let Some(values) = bank.apply(compiled, args) {
+                        let expr = args.iter().fold(f.clone(), |f, &arg| {
+                            let arg = bank.entries[arg].expr.clone();
+                            Expression::Application(Box::new(f), Box::new(arg))
+                        });
+                        if bank.insert(size, Entry { expr, tp, values }) {
+                            found.push(bank.entries.len() - 1);
+                        }
+                    }
+                }
+                true
+            };
+            if !bank.applications(f, arg_tps, ret_tp, &ctx, size - 1, cb) {
+                break;
+            }
+        }
+        if timed_out() {
+            return;
+        }
+    }
+}
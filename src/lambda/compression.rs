@@ -1174,7 +1174,7 @@ mod proposals {
                 subtrees(*body, counts);
                 counts.entry(expr).or_insert(0);
             }
-            Expression::Index(_) => (),
+            Expression::Index(_) | Expression::Hole => (),
             Expression::Primitive(num) => {
                 counts.entry(Expression::Primitive(num)).or_insert(0);
             }
@@ -1209,7 +1209,7 @@ mod proposals {
 /// Counts of prims, free, bound
 pub fn expression_count_kinds(expr: &Expression, abstraction_depth: usize) -> (u64, u64, u64) {
     match *expr {
-        Expression::Primitive(_) | Expression::Invented(_) => (1, 0, 0),
+        Expression::Primitive(_) | Expression::Invented(_) | Expression::Hole => (1, 0, 0),
         Expression::Index(i) => {
             if i < abstraction_depth {
                 (0, 0, 1)
@@ -0,0 +1,707 @@
+//! Production probabilities conditioned on the parent function and argument position.
+
+use crossbeam_channel::bounded;
+use polytype::{Context, Type, TypeSchema};
+use rayon::prelude::*;
+use rayon::spawn;
+use std::collections::VecDeque;
+use std::f64;
+use std::iter;
+use std::rc::Rc;
+
+use super::enumerator::{budget_interval, fill_sketch, sketch_holes, SketchHole, MAX_DEPTH};
+use super::{CompressionParams, Expression, Language, LinkedList, RescoredFrontier};
+use {ECFrontier, Task, EC};
+
+/// Production log-probabilities for a single context of a [`ContextualLanguage`].
+///
+/// [`ContextualLanguage`]: struct.ContextualLanguage.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct Productions {
+    /// The log-probability of using a variable, which is shared among the variables in scope.
+    pub variable_logprob: f64,
+    /// The log-probability of each primitive followed by each invention, in the order of the
+    /// underlying [`Language`].
+    ///
+    /// [`Language`]: struct.Language.html
+    pub logprobs: Vec<f64>,
+}
+impl Productions {
+    /// The unigram log-probabilities of a [`Language`].
+    ///
+    /// [`Language`]: struct.Language.html
+    pub fn of(dsl: &Language) -> Self {
+        let logprobs = dsl
+            .primitives
+            .iter()
+            .map(|p| p.2)
+            .chain(dsl.invented.iter().map(|inv| inv.2))
+            .collect();
+        Productions {
+            variable_logprob: dsl.variable_logprob,
+            logprobs,
+        }
+    }
+}
+
+/// (representation) A ContextualLanguage is a [`Language`] whose production log-probabilities
+/// are conditioned on the parent of each production and the argument position it fills.
+///
+/// The body of an abstraction shares the context of the abstraction itself, so the context of
+/// every production is determined by the function at the head of the nearest enclosing
+/// application. Productions at the root of an expression use [`no_parent`], arguments to a
+/// variable use [`variable_parent`], and arguments to a primitive or invention use the
+/// corresponding entry of [`library`].
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate polytype;
+/// # extern crate programinduction;
+/// # fn main() {
+/// use programinduction::lambda::{ContextualLanguage, Language};
+///
+/// let dsl = Language::uniform(vec![
+///     ("0", ptp!(int)),
+///     ("1", ptp!(int)),
+///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+/// ]);
+/// let mut cl = ContextualLanguage::new(dsl);
+/// // the first argument to + is much more likely to be 1
+/// cl.library[2][0].logprobs = vec![-5.0, 0.0, -5.0];
+///
+/// let exprs: Vec<String> = cl
+///     .enumerate(ptp!(int))
+///     .take(4)
+///     .map(|(expr, _log_prior)| cl.dsl.display(&expr))
+///     .collect();
+/// assert_eq!(exprs, vec!["0", "1", "(+ 1 0)", "(+ 1 1)"]);
+/// # }
+/// ```
+///
+/// [`Language`]: struct.Language.html
+/// [`no_parent`]: #structfield.no_parent
+/// [`variable_parent`]: #structfield.variable_parent
+/// [`library`]: #structfield.library
+#[derive(Debug, Clone)]
+pub struct ContextualLanguage {
+    /// The underlying language, which supplies primitives, inventions, and symmetry violations.
+    /// Its own production log-probabilities are not used.
+    pub dsl: Language,
+    /// Productions at the root of an expression.
+    pub no_parent: Productions,
+    /// Productions for arguments to a variable.
+    pub variable_parent: Productions,
+    /// Productions for each argument of each primitive, followed by each invention.
+    pub library: Vec<Vec<Productions>>,
+}
+impl ContextualLanguage {
+    /// Every context starts with the unigram log-probabilities of the language.
+    pub fn new(dsl: Language) -> Self {
+        let unigram = Productions::of(&dsl);
+        let library = dsl
+            .primitives
+            .iter()
+            .map(|p| &p.1)
+            .chain(dsl.invented.iter().map(|inv| &inv.1))
+            .map(|schema| vec![unigram.clone(); arity(schema)])
+            .collect();
+        ContextualLanguage {
+            dsl,
+            no_parent: unigram.clone(),
+            variable_parent: unigram,
+            library,
+        }
+    }
+
+    /// Enumerate expressions for a request type (including its probability and appropriately
+    /// instantiated `Type`), in order of their contextual log-prior. Like
+    /// [`Language::enumerate`], enumeration respects the underlying language's symmetry
+    /// violations.
+    ///
+    /// [`Language::enumerate`]: struct.Language.html#method.enumerate
+    pub fn enumerate(&self, tp: TypeSchema) -> Box<dyn Iterator<Item = (Expression, f64)>> {
+        let (tx, rx) = bounded(1);
+        let cl = self.clone();
+        spawn(move || {
+            let tx = tx.clone();
+            let termination_condition = |expr, logprior| tx.send((expr, logprior)).is_err();
+            cl.run(tp, termination_condition)
+        });
+        Box::new(rx.into_iter())
+    }
+
+    /// Get the contextual log-likelihood of an expression normalized with other expressions of
+    /// the given type. Like [`Language::likelihood`], the expression must be in eta-long form.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// # use programinduction::lambda::{ContextualLanguage, Language};
+    /// let dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let req = ptp!(@arrow[tp!(int), tp!(int), tp!(int)]);
+    /// let expr = dsl.parse("(λ (λ (+ $0 $1)))").unwrap();
+    ///
+    /// // without any context, this is the same as the underlying language
+    /// let mut cl = ContextualLanguage::new(dsl.clone());
+    /// assert_eq!(cl.likelihood(&req, &expr), dsl.likelihood(&req, &expr));
+    ///
+    /// cl.library[2][0].variable_logprob = 10.0;
+    /// assert!(cl.likelihood(&req, &expr) > dsl.likelihood(&req, &expr));
+    /// # }
+    /// ```
+    ///
+    /// [`Language::likelihood`]: struct.Language.html#method.likelihood
+    pub fn likelihood(&self, request: &TypeSchema, expr: &Expression) -> f64 {
+        let mut ctx = Context::default();
+        let env = Rc::new(LinkedList::default());
+        let tp = request.clone().instantiate_owned(&mut ctx);
+        self.likelihood_internal(&tp, &ctx, &env, None, expr, &mut None)
+            .0
+    }
+
+    /// Computes the joint minimum description length over all frontiers.
+    pub fn joint_mdl(&self, frontiers: &[RescoredFrontier]) -> f64 {
+        frontiers
+            .par_iter()
+            .map(|(t, f)| {
+                f.iter()
+                    .map(|e| e.2 + self.likelihood(t, &e.0))
+                    .fold(f64::NEG_INFINITY, f64::max)
+            })
+            .sum::<f64>()
+    }
+
+    /// Re-estimates the production log-probabilities of every context from the expected number
+    /// of uses in the frontiers, where each expression of a frontier is weighted by its posterior.
+    /// The `pseudocounts` are added to the observed counts of every production in every context.
+    /// The joint minimum description length under the new log-probabilities is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// # use programinduction::lambda::{ContextualLanguage, Language};
+    /// let dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let mut cl = ContextualLanguage::new(dsl);
+    /// let frontiers = vec![
+    ///     (ptp!(int), vec![(cl.dsl.parse("(+ 1 0)").unwrap(), 0.0, 0.0)]),
+    ///     (ptp!(int), vec![(cl.dsl.parse("(+ 1 (+ 1 0))").unwrap(), 0.0, 0.0)]),
+    /// ];
+    /// cl.inside_outside(&frontiers, 1);
+    ///
+    /// // the first argument to + was always 1, and the second argument never was
+    /// let first_arg = &cl.library[2][0].logprobs;
+    /// let second_arg = &cl.library[2][1].logprobs;
+    /// assert!(first_arg[1] > first_arg[0]);
+    /// assert!(second_arg[1] < second_arg[0]);
+    /// # }
+    /// ```
+    pub fn inside_outside(&mut self, frontiers: &[RescoredFrontier], pseudocounts: u64) -> f64 {
+        let u = {
+            let cl = &*self;
+            frontiers
+                .par_iter()
+                .map(|(tp, f)| {
+                    let lu: Vec<_> = f
+                        .iter()
+                        .map(|(expr, _logprior, loglikelihood)| {
+                            let (logprior, u) = cl.uses(tp, expr);
+                            (logprior + loglikelihood, u)
+                        })
+                        .collect();
+                    let largest = lu.iter().fold(f64::NEG_INFINITY, |acc, &(l, _)| acc.max(l));
+                    let mut total = Uses::new(cl);
+                    if largest.is_finite() {
+                        let z = largest
+                            + lu.iter()
+                                .map(|&(l, _)| (l - largest).exp())
+                                .sum::<f64>()
+                                .ln();
+                        for (l, mut u) in lu {
+                            u.scale((l - z).exp());
+                            total.merge(u);
+                        }
+                    }
+                    total
+                })
+                .reduce(
+                    || Uses::new(cl),
+                    |mut u, nu| {
+                        u.merge(nu);
+                        u
+                    },
+                )
+        };
+        let pseudocounts = pseudocounts as f64;
+        self.no_parent = u.no_parent.estimate(pseudocounts);
+        self.variable_parent = u.variable_parent.estimate(pseudocounts);
+        self.library = u
+            .library
+            .into_iter()
+            .map(|args| args.into_iter().map(|c| c.estimate(pseudocounts)).collect())
+            .collect();
+        self.joint_mdl(frontiers)
+    }
+
+    fn productions(&self, parent: Option<(&Expression, usize)>) -> &Productions {
+        match parent {
+            None => &self.no_parent,
+            Some((&Expression::Primitive(num), i)) => &self.library[num][i],
+            Some((&Expression::Invented(num), i)) => {
+                &self.library[self.dsl.primitives.len() + num][i]
+            }
+            Some(_) => &self.variable_parent,
+        }
+    }
+
+    fn candidates(
+        &self,
+        parent: Option<(&Expression, usize)>,
+        request: &Type,
+        ctx: &Context,
+        env: &VecDeque<Type>,
+    ) -> Vec<(f64, Expression, Type, Context)> {
+        let productions = self.productions(parent);
+        let logprobs = productions.logprobs.iter().cloned();
+        self.dsl
+            .candidates_with(logprobs, productions.variable_logprob, request, ctx, env)
+    }
+
+    fn run<F>(&self, request: TypeSchema, termination_condition: F)
+    where
+        F: Fn(Expression, f64) -> bool,
+    {
+        let mut ctx = Context::default();
+        let tp = request.instantiate_owned(&mut ctx);
+        let env = Rc::new(LinkedList::default());
+        let cb = &mut |expr, logprior, _| !termination_condition(expr, logprior);
+        (0..).map(budget_interval).all(|budget| {
+            if cfg!(feature = "verbose") {
+                eprintln!(
+                    "ENUMERATION: starting contextual budget {:?} for request {}",
+                    budget, &tp
+                );
+            }
+            self.enumerate_internal(&ctx, &tp, &env, None, budget, 0, cb)
+        });
+        if cfg!(feature = "verbose") {
+            eprintln!("ENUMERATION: finished for request {}", &tp);
+        }
+    }
+
+    /// Like `enumerator::run_sketch`, where each hole is enumerated in the context of the function
+    /// it is an argument of.
+    fn run_sketch<F>(&self, sketch: &Expression, request: TypeSchema, termination_condition: F)
+    where
+        F: Fn(Expression, f64) -> bool,
+    {
+        let (ctx, tp, holes) = match sketch_holes(&self.dsl, sketch, request) {
+            Some(x) => x,
+            None => return, // ill-typed sketch has no completions
+        };
+        if holes.is_empty() {
+            termination_condition(sketch.clone(), 0.0);
+            return;
+        }
+        let cb = &mut |fills: Vec<Expression>, logprior| {
+            let expr = fill_sketch(sketch, &mut fills.into_iter());
+            !termination_condition(expr, logprior)
+        };
+        (0..).map(budget_interval).all(|budget| {
+            if cfg!(feature = "verbose") {
+                eprintln!(
+                    "ENUMERATION: starting contextual budget {:?} for sketch {} with request {}",
+                    budget,
+                    self.dsl.display(sketch),
+                    &tp
+                );
+            }
+            self.enumerate_holes(&ctx, &holes, &[], budget, 0.0, cb)
+        });
+        if cfg!(feature = "verbose") {
+            eprintln!(
+                "ENUMERATION: finished for sketch {}",
+                self.dsl.display(sketch)
+            );
+        }
+    }
+
+    /// Like `enumerator::enumerate_holes`, where each hole is enumerated in the context of the
+    /// function it is an argument of.
+    fn enumerate_holes(
+        &self,
+        ctx: &Context,
+        holes: &[SketchHole],
+        fills: &[Expression],
+        budget: (f64, f64),
+        offset: f64,
+        cb: &mut dyn FnMut(Vec<Expression>, f64) -> bool,
+    ) -> bool {
+        if budget.1 <= 0f64 {
+            true
+        } else if let Some((hole, holes)) = holes.split_first() {
+            let tp = hole.tp.apply(ctx);
+            let parent = hole.arg_of.as_ref().map(|&(ref f, i)| (f, i));
+            let cb_hole = &mut |expr, ll, ctx: Context| {
+                if let Some((f, i)) = parent {
+                    if self.dsl.violates_symmetry(f, i, &expr) {
+                        return true;
+                    }
+                }
+                let mut fills = fills.to_vec();
+                fills.push(expr);
+                let budget = (budget.0 + ll, budget.1 + ll);
+                self.enumerate_holes(&ctx, holes, &fills, budget, offset + ll, cb)
+            };
+            self.enumerate_internal(ctx, &tp, &hole.env, parent, (0f64, budget.1), 0, cb_hole)
+        } else if budget.0 < 0f64 {
+            cb(fills.to_vec(), offset)
+        } else {
+            true
+        }
+    }
+
+    /// Like `enumerator::enumerate`, but with the parent and argument position of the
+    /// production.
+    #[allow(clippy::too_many_arguments)]
+    fn enumerate_internal(
+        &self,
+        ctx: &Context,
+        request: &Type,
+        env: &Rc<LinkedList<Type>>,
+        parent: Option<(&Expression, usize)>,
+        budget: (f64, f64),
+        depth: u32,
+        cb: &mut dyn FnMut(Expression, f64, Context) -> bool,
+    ) -> bool {
+        if budget.1 <= 0f64 || depth > MAX_DEPTH {
+            true
+        } else if let Some((arg, ret)) = request.as_arrow() {
+            let env = LinkedList::prepend(env, arg.clone());
+            let cb = &mut |body, ll, ctx| cb(Expression::Abstraction(Box::new(body)), ll, ctx);
+            self.enumerate_internal(ctx, ret, &env, parent, budget, depth, cb)
+        } else {
+            self.candidates(parent, request, ctx, &env.as_vecdeque())
+                .into_iter()
+                .filter(|&(ll, _, _, _)| -ll <= budget.1)
+                .all(|(p, expr, tp, ctx)| {
+                    let arg_tps: VecDeque<Type> = tp
+                        .args()
+                        .map(|args| args.into_iter().cloned().collect())
+                        .unwrap_or_else(VecDeque::new);
+                    let budget = (budget.0 + p, budget.1 + p);
+                    let depth = depth + 1;
+                    let idx = (0, &expr);
+                    self.enumerate_many(&ctx, env, &expr, idx, arg_tps, budget, p, depth, cb)
+                })
+        }
+    }
+
+    /// Like `enumerator::enumerate_many`, where each argument is enumerated in the context of
+    /// `idx`.
+    #[allow(clippy::too_many_arguments)]
+    fn enumerate_many(
+        &self,
+        ctx: &Context,
+        env: &Rc<LinkedList<Type>>,
+        f: &Expression,
+        idx: (usize, &Expression),
+        mut arg_tps: VecDeque<Type>,
+        budget: (f64, f64),
+        offset: f64,
+        depth: u32,
+        cb: &mut dyn FnMut(Expression, f64, Context) -> bool,
+    ) -> bool {
+        if budget.1 <= 0f64 {
+            true
+        } else if let Some(mut arg_tp) = arg_tps.pop_front() {
+            arg_tp.apply_mut(ctx);
+            let cb_arg = &mut |arg, ll, ctx| {
+                if self.dsl.violates_symmetry(idx.1, idx.0, &arg) {
+                    return true;
+                }
+                let idx = (idx.0 + 1, idx.1);
+                let f = Expression::Application(Box::new(f.clone()), Box::new(arg));
+                let arg_tps = arg_tps.clone();
+                let budget = (budget.0 + ll, budget.1 + ll);
+                let offset = offset + ll;
+                self.enumerate_many(&ctx, env, &f, idx, arg_tps, budget, offset, depth, cb)
+            };
+            let parent = Some((idx.1, idx.0));
+            let budget = (0f64, budget.1);
+            self.enumerate_internal(ctx, &arg_tp, env, parent, budget, depth, cb_arg)
+        } else if budget.0 < 0f64 {
+            cb(f.clone(), offset, ctx.clone())
+        } else {
+            true
+        }
+    }
+
+    fn uses(&self, request: &TypeSchema, expr: &Expression) -> (f64, Uses) {
+        let mut ctx = Context::default();
+        let env = Rc::new(LinkedList::default());
+        let tp = request.clone().instantiate_owned(&mut ctx);
+        let mut uses = Some(Uses::new(self));
+        let l = self
+            .likelihood_internal(&tp, &ctx, &env, None, expr, &mut uses)
+            .0;
+        (l, uses.unwrap())
+    }
+
+    /// Like `enumerator::likelihood_internal`, but in the context of `parent`. Production counts
+    /// are recorded in `uses` if it is given.
+    fn likelihood_internal(
+        &self,
+        request: &Type,
+        ctx: &Context,
+        env: &Rc<LinkedList<Type>>,
+        parent: Option<(&Expression, usize)>,
+        mut expr: &Expression,
+        uses: &mut Option<Uses>,
+    ) -> (f64, Context) {
+        if let Some((arg, ret)) = request.as_arrow() {
+            let env = LinkedList::prepend(env, arg.clone());
+            if let Expression::Abstraction(ref body) = *expr {
+                self.likelihood_internal(ret, ctx, &env, parent, body, uses)
+            } else {
+                (f64::NEG_INFINITY, ctx.clone()) // invalid expression
+            }
+        } else {
+            let mut xs: Vec<&Expression> = vec![];
+            while let Expression::Application(ref l, ref r) = *expr {
+                expr = l;
+                xs.push(r);
+            }
+            xs.reverse();
+            let candidates = self.candidates(parent, request, ctx, &env.as_vecdeque());
+            if let Some(ref mut u) = *uses {
+                u.record(self, parent, &candidates, expr);
+            }
+            match candidates
+                .into_iter()
+                .find(|(_, c_expr, _, _)| expr == c_expr)
+            {
+                Some((f_l, _, f_tp, ctx)) => {
+                    let arg_tps = f_tp.args().unwrap_or_else(VecDeque::new);
+                    xs.into_iter().zip(arg_tps).enumerate().fold(
+                        (f_l, ctx),
+                        |(l, ctx), (i, (x, x_tp))| {
+                            let parent = Some((expr, i));
+                            let (x_l, ctx) =
+                                self.likelihood_internal(x_tp, &ctx, env, parent, x, uses);
+                            (l + x_l, ctx)
+                        },
+                    )
+                }
+                None => (f64::NEG_INFINITY, ctx.clone()),
+            }
+        }
+    }
+}
+impl EC for ContextualLanguage {
+    type Expression = Expression;
+    type Params = CompressionParams;
+    fn enumerate<F>(&self, tp: TypeSchema, termination_condition: F)
+    where
+        F: Fn(Expression, f64) -> bool + Send + Sync,
+    {
+        self.run(tp, termination_condition)
+    }
+    fn enumerate_sketch<F>(&self, sketch: &Expression, tp: TypeSchema, termination_condition: F)
+    where
+        F: Fn(Expression, f64) -> bool + Send + Sync,
+    {
+        self.run_sketch(sketch, tp, termination_condition)
+    }
+    /// Compresses the underlying [`Language`] with [`Language::compress`], then re-estimates the
+    /// contextual production log-probabilities from the rewritten frontiers with
+    /// [`inside_outside`].
+    ///
+    /// [`Language`]: struct.Language.html
+    /// [`Language::compress`]: struct.Language.html#method.compress
+    /// [`inside_outside`]: #method.inside_outside
+    fn compress<O: Sync>(
+        &self,
+        params: &Self::Params,
+        tasks: &[Task<Self, Self::Expression, O>],
+        frontiers: Vec<ECFrontier<Self>>,
+    ) -> (Self, Vec<ECFrontier<Self>>) {
+        // compression only depends on the request type of each task
+        let noop_tasks: Vec<_> = tasks.iter().map(|t| Task::noop(t.tp.clone())).collect();
+        let frontiers = frontiers.into_iter().map(|f| ECFrontier(f.0)).collect();
+        let (dsl, frontiers) = self.dsl.compress(params, &noop_tasks, frontiers);
+        let mut cl = ContextualLanguage::new(dsl);
+        let rescored_frontiers: Vec<RescoredFrontier> = tasks
+            .iter()
+            .zip(&frontiers)
+            .filter(|&(_, f)| !f.is_empty())
+            .map(|(t, f)| (t.tp.clone(), f.0.clone()))
+            .collect();
+        cl.inside_outside(&rescored_frontiers, params.pseudocounts);
+        let frontiers = tasks
+            .iter()
+            .zip(frontiers)
+            .map(|(t, f)| {
+                let xs = f.0.into_iter().map(|(expr, _, loglikelihood)| {
+                    let logprior = cl.likelihood(&t.tp, &expr);
+                    (expr, logprior, loglikelihood)
+                });
+                ECFrontier(xs.collect())
+            })
+            .collect();
+        (cl, frontiers)
+    }
+}
+
+fn arity(schema: &TypeSchema) -> usize {
+    let tp = schema.clone().instantiate_owned(&mut Context::default());
+    tp.args().map(|args| args.len()).unwrap_or(0)
+}
+
+/// Expected production counts for a single context.
+#[derive(Debug, Clone)]
+struct Counts {
+    actual_vars: f64,
+    possible_vars: f64,
+    actual: Vec<f64>,
+    possible: Vec<f64>,
+}
+impl Counts {
+    fn new(n: usize) -> Counts {
+        Counts {
+            actual_vars: 0f64,
+            possible_vars: 0f64,
+            actual: vec![0f64; n],
+            possible: vec![0f64; n],
+        }
+    }
+    fn scale(&mut self, s: f64) {
+        self.actual_vars *= s;
+        self.possible_vars *= s;
+        self.actual.iter_mut().for_each(|x| *x *= s);
+        self.possible.iter_mut().for_each(|x| *x *= s);
+    }
+    fn merge(&mut self, other: Counts) {
+        self.actual_vars += other.actual_vars;
+        self.possible_vars += other.possible_vars;
+        self.actual
+            .iter_mut()
+            .zip(other.actual)
+            .for_each(|(a, b)| *a += b);
+        self.possible
+            .iter_mut()
+            .zip(other.possible)
+            .for_each(|(a, b)| *a += b);
+    }
+    /// Productions which were never possible in this context are given log-probability zero,
+    /// which leaves them to normalization.
+    fn estimate(&self, pseudocounts: f64) -> Productions {
+        let estimate = |actual: f64, possible: f64| {
+            if possible == 0f64 {
+                0f64
+            } else {
+                (actual + pseudocounts).ln() - possible.ln()
+            }
+        };
+        Productions {
+            variable_logprob: estimate(self.actual_vars, self.possible_vars),
+            logprobs: self
+                .actual
+                .iter()
+                .zip(&self.possible)
+                .map(|(&actual, &possible)| estimate(actual, possible))
+                .collect(),
+        }
+    }
+}
+
+/// Expected production counts for every context of a [`ContextualLanguage`].
+///
+/// [`ContextualLanguage`]: struct.ContextualLanguage.html
+#[derive(Debug, Clone)]
+struct Uses {
+    no_parent: Counts,
+    variable_parent: Counts,
+    library: Vec<Vec<Counts>>,
+}
+impl Uses {
+    fn new(cl: &ContextualLanguage) -> Uses {
+        let n = cl.no_parent.logprobs.len();
+        Uses {
+            no_parent: Counts::new(n),
+            variable_parent: Counts::new(n),
+            library: cl
+                .library
+                .iter()
+                .map(|args| vec![Counts::new(n); args.len()])
+                .collect(),
+        }
+    }
+    fn all_counts(self) -> impl Iterator<Item = Counts> {
+        iter::once(self.no_parent)
+            .chain(iter::once(self.variable_parent))
+            .chain(self.library.into_iter().flat_map(|args| args.into_iter()))
+    }
+    fn all_counts_mut(&mut self) -> impl Iterator<Item = &mut Counts> {
+        iter::once(&mut self.no_parent)
+            .chain(iter::once(&mut self.variable_parent))
+            .chain(self.library.iter_mut().flat_map(|args| args.iter_mut()))
+    }
+    fn scale(&mut self, s: f64) {
+        self.all_counts_mut().for_each(|c| c.scale(s))
+    }
+    fn merge(&mut self, other: Uses) {
+        self.all_counts_mut()
+            .zip(other.all_counts())
+            .for_each(|(c, other)| c.merge(other))
+    }
+    /// Records the possible productions from `candidates` and the actual production `expr` in
+    /// the context of `parent`.
+    fn record(
+        &mut self,
+        cl: &ContextualLanguage,
+        parent: Option<(&Expression, usize)>,
+        candidates: &[(f64, Expression, Type, Context)],
+        expr: &Expression,
+    ) {
+        let n_primitives = cl.dsl.primitives.len();
+        let counts = match parent {
+            None => &mut self.no_parent,
+            Some((&Expression::Primitive(num), i)) => &mut self.library[num][i],
+            Some((&Expression::Invented(num), i)) => &mut self.library[n_primitives + num][i],
+            Some(_) => &mut self.variable_parent,
+        };
+        let mut vars_possible = false;
+        for (_, c_expr, _, _) in candidates {
+            match *c_expr {
+                Expression::Primitive(num) => counts.possible[num] += 1f64,
+                Expression::Invented(num) => counts.possible[n_primitives + num] += 1f64,
+                Expression::Index(_) => vars_possible = true,
+                _ => unreachable!(),
+            }
+        }
+        if vars_possible {
+            counts.possible_vars += 1f64;
+        }
+        match *expr {
+            Expression::Primitive(num) => counts.actual[num] += 1f64,
+            Expression::Invented(num) => counts.actual[n_primitives + num] += 1f64,
+            Expression::Index(_) => counts.actual_vars += 1f64,
+            _ => (),
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use polytype::{Context, Type, TypeSchema};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
 use std::collections::VecDeque;
 use std::f64;
 use std::rc::Rc;
 
 use super::{Expression, Language, LinkedList};
 
-const MAX_DEPTH: u32 = 8192;
+pub const MAX_DEPTH: u32 = 8192;
+const MAX_SYMMETRY_ATTEMPTS: usize = 8;
 
-fn budget_interval(n: u32) -> (f64, f64) {
+pub fn budget_interval(n: u32) -> (f64, f64) {
     match n / 6 {
         0 => {
             let offset = f64::from(n) * 2.0;
@@ -61,6 +64,167 @@ where
     }
 }
 
+/// Enumerates completions of a sketch, filling its holes in order of their joint prior.
+pub fn run_sketch<F>(
+    dsl: &Language,
+    sketch: &Expression,
+    request: TypeSchema,
+    termination_condition: F,
+) where
+    F: Fn(Expression, f64) -> bool + Send + Sync,
+{
+    let (ctx, tp, holes) = match sketch_holes(dsl, sketch, request) {
+        Some(x) => x,
+        None => return, // ill-typed sketch has no completions
+    };
+    if holes.is_empty() {
+        termination_condition(sketch.clone(), 0.0);
+        return;
+    }
+    let cb = &mut |fills: Vec<Expression>, logprior| {
+        let expr = fill_sketch(sketch, &mut fills.into_iter());
+        !termination_condition(expr, logprior)
+    };
+    (0..).map(budget_interval).all(|budget| {
+        if cfg!(feature = "verbose") {
+            eprintln!(
+                "ENUMERATION: starting budget {:?} for sketch {} with request {}",
+                budget,
+                dsl.display(sketch),
+                &tp
+            );
+        }
+        enumerate_holes(dsl, &ctx, &holes, &[], budget, 0.0, cb)
+    });
+    if cfg!(feature = "verbose") {
+        eprintln!("ENUMERATION: finished for sketch {}", dsl.display(sketch));
+    }
+}
+
+/// A hole in a sketch, with the function and argument position it fills if it is an argument.
+pub struct SketchHole {
+    pub tp: Type,
+    pub env: Rc<LinkedList<Type>>,
+    pub arg_of: Option<(Expression, usize)>,
+}
+
+/// The holes of a sketch in pre-order, with the context and instantiated request in which they
+/// are typed, or `None` if the sketch does not have the requested type.
+pub fn sketch_holes(
+    dsl: &Language,
+    sketch: &Expression,
+    request: TypeSchema,
+) -> Option<(Context, Type, Vec<SketchHole>)> {
+    let mut ctx = Context::default();
+    let tp = request.instantiate_owned(&mut ctx);
+    let env = Rc::new(LinkedList::default());
+    let mut holes = Vec::new();
+    let sketch_tp = infer_sketch(dsl, &mut ctx, &env, sketch, None, &mut holes)?;
+    ctx.unify(&sketch_tp, &tp).ok()?;
+    Some((ctx, tp, holes))
+}
+
+/// Infers the type of a closed sketch, collecting its holes in pre-order.
+fn infer_sketch(
+    dsl: &Language,
+    ctx: &mut Context,
+    env: &Rc<LinkedList<Type>>,
+    expr: &Expression,
+    arg_of: Option<(Expression, usize)>,
+    holes: &mut Vec<SketchHole>,
+) -> Option<Type> {
+    match *expr {
+        Expression::Hole => {
+            let tp = ctx.new_variable();
+            holes.push(SketchHole {
+                tp: tp.clone(),
+                env: env.clone(),
+                arg_of,
+            });
+            Some(tp)
+        }
+        Expression::Application(..) => {
+            let mut f = expr;
+            let mut xs = Vec::new();
+            while let Expression::Application(ref ff, ref x) = *f {
+                xs.push(&**x);
+                f = ff;
+            }
+            xs.reverse();
+            let mut f_tp = infer_sketch(dsl, ctx, env, f, None, holes)?;
+            for (i, x) in xs.into_iter().enumerate() {
+                let x_tp = infer_sketch(dsl, ctx, env, x, Some((f.clone(), i)), holes)?;
+                let ret_tp = ctx.new_variable();
+                ctx.unify(&f_tp, &Type::arrow(x_tp, ret_tp.clone())).ok()?;
+                f_tp = ret_tp.apply(ctx);
+            }
+            Some(f_tp)
+        }
+        Expression::Abstraction(ref body) => {
+            let arg_tp = ctx.new_variable();
+            let env = LinkedList::prepend(env, arg_tp.clone());
+            let ret_tp = infer_sketch(dsl, ctx, &env, body, None, holes)?;
+            Some(Type::arrow(arg_tp, ret_tp).apply(ctx))
+        }
+        Expression::Index(i) if i < env.len() => Some(env[i].apply(ctx)),
+        Expression::Index(_) => None,
+        Expression::Primitive(num) => dsl.primitives.get(num).map(|p| p.1.instantiate(ctx)),
+        Expression::Invented(num) => dsl.invented.get(num).map(|inv| inv.1.instantiate(ctx)),
+    }
+}
+
+/// Replaces the holes of a sketch, in pre-order, with the given expressions.
+pub fn fill_sketch<I: Iterator<Item = Expression>>(
+    sketch: &Expression,
+    fills: &mut I,
+) -> Expression {
+    match *sketch {
+        Expression::Hole => fills.next().expect("sketch has more holes than fills"),
+        Expression::Application(ref f, ref x) => {
+            let f = fill_sketch(f, fills);
+            let x = fill_sketch(x, fills);
+            Expression::Application(Box::new(f), Box::new(x))
+        }
+        Expression::Abstraction(ref body) => {
+            Expression::Abstraction(Box::new(fill_sketch(body, fills)))
+        }
+        _ => sketch.clone(),
+    }
+}
+
+/// Like `enumerate_many`, but for the holes of a sketch, each with its own environment.
+fn enumerate_holes(
+    dsl: &Language,
+    ctx: &Context,
+    holes: &[SketchHole],
+    fills: &[Expression],
+    budget: (f64, f64),
+    offset: f64,
+    cb: &mut dyn FnMut(Vec<Expression>, f64) -> bool,
+) -> bool {
+    if budget.1 <= 0f64 {
+        true
+    } else if let Some((hole, holes)) = holes.split_first() {
+        let tp = hole.tp.apply(ctx);
+        let cb_hole = &mut |expr, ll, ctx: Context| {
+            if let Some((ref f, i)) = hole.arg_of {
+                if dsl.violates_symmetry(f, i, &expr) {
+                    return true;
+                }
+            }
+            let mut fills = fills.to_vec();
+            fills.push(expr);
+            let budget = (budget.0 + ll, budget.1 + ll);
+            enumerate_holes(dsl, &ctx, holes, &fills, budget, offset + ll, cb)
+        };
+        enumerate(dsl, ctx, &tp, &hole.env, (0f64, budget.1), 0, cb_hole)
+    } else if budget.0 < 0f64 {
+        cb(fills.to_vec(), offset)
+    } else {
+        true
+    }
+}
+
 pub fn likelihood<'a>(dsl: &'a Language, request: &TypeSchema, expr: &Expression) -> f64 {
     let mut ctx = Context::default();
     let env = Rc::new(LinkedList::default());
@@ -119,6 +283,73 @@ fn likelihood_internal<'a>(
     }
 }
 
+/// Samples an expression for the request according to the language's probabilities, where
+/// applications are nested at most `max_depth` deep. Abstractions do not count towards depth.
+///
+/// The context is only updated if sampling succeeds, in which case it holds the constraints of the
+/// sampled expression. Returns `None` if no expression could be found within the depth limit.
+pub fn sample<R: Rng>(
+    dsl: &Language,
+    ctx: &mut Context,
+    request: &Type,
+    env: &Rc<LinkedList<Type>>,
+    max_depth: u32,
+    rng: &mut R,
+) -> Option<Expression> {
+    if let Some((arg, ret)) = request.as_arrow() {
+        let env = LinkedList::prepend(env, arg.clone());
+        return sample(dsl, ctx, ret, &env, max_depth, rng)
+            .map(|body| Expression::Abstraction(Box::new(body)));
+    }
+    if max_depth == 0 {
+        return None;
+    }
+    let mut cands = dsl.candidates(request, ctx, &env.as_vecdeque());
+    if max_depth == 1 {
+        cands.retain(|(_, _, tp, _)| tp.as_arrow().is_none());
+    }
+    while !cands.is_empty() {
+        let i = WeightedIndex::new(cands.iter().map(|&(p, _, _, _)| p.exp()))
+            .ok()?
+            .sample(rng);
+        let (_, f, tp, mut f_ctx) = cands.swap_remove(i);
+        if let Some(expr) = sample_args(dsl, &mut f_ctx, f, &tp, env, max_depth - 1, rng) {
+            *ctx = f_ctx;
+            return Some(expr);
+        }
+    }
+    None
+}
+fn sample_args<R: Rng>(
+    dsl: &Language,
+    ctx: &mut Context,
+    f: Expression,
+    tp: &Type,
+    env: &Rc<LinkedList<Type>>,
+    max_depth: u32,
+    rng: &mut R,
+) -> Option<Expression> {
+    let mut expr = f.clone();
+    if let Some(arg_tps) = tp.args() {
+        for (i, arg_tp) in arg_tps.into_iter().enumerate() {
+            let arg_tp = arg_tp.apply(ctx);
+            let mut sampled = None;
+            for _ in 0..MAX_SYMMETRY_ATTEMPTS {
+                let mut arg_ctx = ctx.clone();
+                let arg = sample(dsl, &mut arg_ctx, &arg_tp, env, max_depth, rng)?;
+                if !dsl.violates_symmetry(&f, i, &arg) {
+                    sampled = Some((arg, arg_ctx));
+                    break;
+                }
+            }
+            let (arg, arg_ctx) = sampled?;
+            *ctx = arg_ctx;
+            expr = Expression::Application(Box::new(expr), Box::new(arg));
+        }
+    }
+    Some(expr)
+}
+
 /// returns whether the caller should continue enumerating (i.e. whether the termination condition
 /// from `cb` has been met)
 fn enumerate(
@@ -402,6 +633,7 @@ mod bfs {
                 }
                 Expression::Index(i) => HoleExpression::Index(i),
                 Expression::Invented(n) => HoleExpression::Invented(n),
+                Expression::Hole => unreachable!(/* holes are only created with a type */),
             }
         }
     }
@@ -1,8 +1,13 @@
 //! Evaluation happens by calling primitives provided by an evaluator.
 use polytype::TypeSchema;
+use serde::{Serialize, Serializer};
 use std::collections::VecDeque;
+use std::error::Error;
 use std::fmt;
-use std::sync::Arc;
+use std::mem;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use lambda::{Expression, Language};
 
@@ -16,7 +21,11 @@ where
     V: Clone + PartialEq + Send + Sync,
     E: Evaluator<Space = V>,
 {
-    ReducedExpression::new(dsl, expr).eval_inps(evaluator, inps)
+    let limiter = Arc::new(Limiter::new(&EvalLimits::default()));
+    let result = ReducedExpression::new(dsl, expr).eval_inps(evaluator, inps, &limiter);
+    limiter
+        .finish(result)
+        .map_err(|err| err.into_evaluator_error(|| evaluator.limit_error()))
 }
 
 pub fn lazy_eval<V, E>(
@@ -29,7 +38,400 @@ where
     V: Clone + PartialEq + Send + Sync,
     E: LazyEvaluator<Space = V>,
 {
-    ReducedExpression::new(dsl, expr).lazy_eval_inps(evaluator, inps)
+    let limiter = Arc::new(Limiter::new(&EvalLimits::default()));
+    let result = ReducedExpression::new(dsl, expr).lazy_eval_inps(evaluator, inps, &limiter);
+    limiter
+        .finish(result)
+        .map_err(|err| err.into_evaluator_error(|| evaluator.limit_error()))
+}
+
+pub fn eval_with_limits<V, E>(
+    dsl: &Language,
+    expr: &Expression,
+    evaluator: &Arc<E>,
+    inps: &[V],
+    limits: &EvalLimits,
+) -> Result<V, EvalError<E::Error>>
+where
+    V: Clone + PartialEq + Send + Sync,
+    E: Evaluator<Space = V>,
+{
+    let limiter = Arc::new(Limiter::new(limits));
+    limiter.finish(ReducedExpression::new(dsl, expr).eval_inps(evaluator, inps, &limiter))
+}
+
+pub fn lazy_eval_with_limits<V, E>(
+    dsl: &Language,
+    expr: &Expression,
+    evaluator: &Arc<E>,
+    inps: &[V],
+    limits: &EvalLimits,
+) -> Result<V, EvalError<E::Error>>
+where
+    V: Clone + PartialEq + Send + Sync,
+    E: LazyEvaluator<Space = V>,
+{
+    let limiter = Arc::new(Limiter::new(limits));
+    limiter.finish(ReducedExpression::new(dsl, expr).lazy_eval_inps(evaluator, inps, &limiter))
+}
+
+pub fn eval_with_trace<V, E>(
+    dsl: &Language,
+    expr: &Expression,
+    evaluator: &Arc<E>,
+    inps: &[V],
+) -> (Result<V, E::Error>, EvalTrace<V>)
+where
+    V: Clone + PartialEq + Send + Sync,
+    E: Evaluator<Space = V>,
+{
+    let limiter = Arc::new(Limiter::new(&EvalLimits::default()).traced());
+    let result = ReducedExpression::new(dsl, expr).eval_inps(evaluator, inps, &limiter);
+    let result = limiter
+        .finish(result)
+        .map_err(|err| err.into_evaluator_error(|| evaluator.limit_error()));
+    (result, limiter.take_trace())
+}
+
+pub fn lazy_eval_with_trace<V, E>(
+    dsl: &Language,
+    expr: &Expression,
+    evaluator: &Arc<E>,
+    inps: &[V],
+) -> (Result<V, E::Error>, EvalTrace<V>)
+where
+    V: Clone + PartialEq + Send + Sync,
+    E: LazyEvaluator<Space = V>,
+{
+    let limiter = Arc::new(Limiter::new(&EvalLimits::default()).traced());
+    let result = ReducedExpression::new(dsl, expr).lazy_eval_inps(evaluator, inps, &limiter);
+    let result = limiter
+        .finish(result)
+        .map_err(|err| err.into_evaluator_error(|| evaluator.limit_error()));
+    (result, limiter.take_trace())
+}
+
+pub fn compile<V>(dsl: &Language, expr: &Expression) -> CompiledExpression<V>
+where
+    V: Clone + PartialEq + Send + Sync,
+{
+    CompiledExpression::new(ReducedExpression::new(dsl, expr))
+}
+
+/// Limits on the resources used by [`Language::eval_with_limits`] and
+/// [`Language::lazy_eval_with_limits`]. Every limit is optional, and by default there are none.
+///
+/// [`Language::eval_with_limits`]: struct.Language.html#method.eval_with_limits
+/// [`Language::lazy_eval_with_limits`]: struct.Language.html#method.lazy_eval_with_limits
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct EvalLimits {
+    /// The maximum number of reduction steps, including those made by lifted functions.
+    pub fuel: Option<usize>,
+    /// The maximum depth of nested reductions, which prevents stack overflows.
+    pub max_depth: Option<usize>,
+    /// The maximum wall-clock time spent evaluating.
+    pub timeout: Option<Duration>,
+}
+
+/// An error from [`Language::eval_with_limits`] or [`Language::lazy_eval_with_limits`]: either
+/// an error from the evaluator, an exhausted [`EvalLimits`] limit, or a [`Hole`] which was
+/// reached because the expression is an incomplete sketch.
+///
+/// [`Language::eval_with_limits`]: struct.Language.html#method.eval_with_limits
+/// [`Language::lazy_eval_with_limits`]: struct.Language.html#method.lazy_eval_with_limits
+/// [`EvalLimits`]: struct.EvalLimits.html
+/// [`Hole`]: enum.Expression.html#variant.Hole
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError<E> {
+    Evaluator(E),
+    OutOfFuel,
+    MaxDepthExceeded,
+    Timeout,
+    Hole,
+}
+impl<E> EvalError<E> {
+    /// The code stored by a `Limiter` once evaluation was stopped with this error, or `RUNNING`
+    /// if it came from the evaluator.
+    fn stop_code(&self) -> u8 {
+        match *self {
+            EvalError::Evaluator(_) => RUNNING,
+            EvalError::OutOfFuel => 1,
+            EvalError::MaxDepthExceeded => 2,
+            EvalError::Timeout => 3,
+            EvalError::Hole => 4,
+        }
+    }
+    /// The error which stopped evaluation, given its `stop_code`.
+    fn from_stop_code(code: u8) -> Option<EvalError<E>> {
+        match code {
+            1 => Some(EvalError::OutOfFuel),
+            2 => Some(EvalError::MaxDepthExceeded),
+            3 => Some(EvalError::Timeout),
+            4 => Some(EvalError::Hole),
+            _ => None,
+        }
+    }
+    /// The error from the evaluator, or the evaluator's [`limit_error`] if evaluation was
+    /// stopped otherwise.
+    ///
+    /// [`limit_error`]: trait.Evaluator.html#tymethod.limit_error
+    fn into_evaluator_error<F>(self, limit_error: F) -> E
+    where
+        F: FnOnce() -> E,
+    {
+        match self {
+            EvalError::Evaluator(err) => err,
+            _ => limit_error(),
+        }
+    }
+}
+impl<E: fmt::Display> fmt::Display for EvalError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            EvalError::Evaluator(ref err) => write!(f, "evaluator failed: {}", err),
+            EvalError::OutOfFuel => write!(f, "evaluation ran out of fuel"),
+            EvalError::MaxDepthExceeded => write!(f, "evaluation exceeded the maximum depth"),
+            EvalError::Timeout => write!(f, "evaluation timed out"),
+            EvalError::Hole => write!(f, "evaluation reached a hole in a sketch"),
+        }
+    }
+}
+impl<E: Error> Error for EvalError<E> {
+    fn description(&self) -> &str {
+        "could not evaluate expression"
+    }
+}
+
+/// A record of the calls made while evaluating an expression, from [`Language::eval_with_trace`]
+/// or [`Language::lazy_eval_with_trace`].
+///
+/// A trace is rendered as text with its `Display` implementation, and as JSON by serializing it
+/// (e.g. with `serde_json`). Both show values using their `Debug` implementation.
+///
+/// [`Language::eval_with_trace`]: struct.Language.html#method.eval_with_trace
+/// [`Language::lazy_eval_with_trace`]: struct.Language.html#method.lazy_eval_with_trace
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(bound = "V: fmt::Debug")]
+pub struct EvalTrace<V> {
+    /// Every call in the order it was made, so each is followed by the calls nested within it.
+    pub events: Vec<TraceEvent<V>>,
+}
+impl<V: fmt::Debug> fmt::Display for EvalTrace<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+/// A call recorded in an [`EvalTrace`]. A result of `None` means the call failed.
+///
+/// [`EvalTrace`]: struct.EvalTrace.html
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", bound = "V: fmt::Debug")]
+pub enum TraceEvent<V> {
+    /// A call to the evaluator for a primitive. With lazy evaluation, an argument that was not
+    /// already a value is `None`, and its evaluation is recorded as a call to a lifted function.
+    Primitive {
+        depth: usize,
+        name: String,
+        #[serde(serialize_with = "serialize_lazy_args")]
+        args: Vec<Option<V>>,
+        #[serde(serialize_with = "serialize_result")]
+        result: Option<V>,
+    },
+    /// A call to a [`LiftedFunction`] or [`LiftedLazyFunction`].
+    ///
+    /// [`LiftedFunction`]: struct.LiftedFunction.html
+    /// [`LiftedLazyFunction`]: struct.LiftedLazyFunction.html
+    Lifted {
+        depth: usize,
+        #[serde(serialize_with = "serialize_args")]
+        args: Vec<V>,
+        #[serde(serialize_with = "serialize_result")]
+        result: Option<V>,
+    },
+}
+impl<V: fmt::Debug> fmt::Display for TraceEvent<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let (depth, name, args, result): (_, &str, Vec<String>, _) = match *self {
+            TraceEvent::Primitive {
+                depth,
+                ref name,
+                ref args,
+                ref result,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|arg| match *arg {
+                        Some(ref arg) => format!("{:?}", arg),
+                        None => String::from("_"),
+                    })
+                    .collect();
+                (depth, name, args, result)
+            }
+            TraceEvent::Lifted {
+                depth,
+                ref args,
+                ref result,
+            } => {
+                let args = args.iter().map(|arg| format!("{:?}", arg)).collect();
+                (depth, "λ", args, result)
+            }
+        };
+        write!(f, "{:indent$}", "", indent = 2 * depth)?;
+        if args.is_empty() {
+            write!(f, "{}", name)?;
+        } else {
+            write!(f, "({} {})", name, args.join(" "))?;
+        }
+        match *result {
+            Some(ref result) => write!(f, " = {:?}", result),
+            None => write!(f, " failed"),
+        }
+    }
+}
+
+/// Serializes a value in a trace using its `Debug` implementation.
+struct Debugged<'a, T: 'a>(&'a T);
+impl<'a, T: fmt::Debug> Serialize for Debugged<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{:?}", self.0))
+    }
+}
+fn serialize_args<V: fmt::Debug, S: Serializer>(
+    xs: &[V],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(xs.iter().map(Debugged))
+}
+fn serialize_lazy_args<V: fmt::Debug, S: Serializer>(
+    xs: &[Option<V>],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(xs.iter().map(|x| x.as_ref().map(Debugged)))
+}
+fn serialize_result<V: fmt::Debug, S: Serializer>(
+    x: &Option<V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    x.as_ref().map(Debugged).serialize(serializer)
+}
+
+/// The stop code of a `Limiter` whose evaluation has not been stopped.
+const RUNNING: u8 = 0;
+
+/// Tracks the resources used by an evaluation, which are shared with the functions it lifts, and
+/// records a trace of its calls if one was requested.
+struct Limiter<V> {
+    fuel: Option<usize>,
+    max_depth: Option<usize>,
+    deadline: Option<Instant>,
+    steps: AtomicUsize,
+    depth: AtomicUsize,
+    /// The `stop_code` of the first exhausted limit or hole, which takes precedence over whatever
+    /// the evaluator made of it, or `RUNNING`.
+    stopped: AtomicU8,
+    trace: Option<Tracer<V>>,
+}
+struct Tracer<V> {
+    events: Mutex<Vec<TraceEvent<V>>>,
+    depth: AtomicUsize,
+}
+impl<V: Clone> Limiter<V> {
+    fn new(limits: &EvalLimits) -> Limiter<V> {
+        Limiter {
+            fuel: limits.fuel,
+            max_depth: limits.max_depth,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            steps: AtomicUsize::new(0),
+            depth: AtomicUsize::new(0),
+            stopped: AtomicU8::new(RUNNING),
+            trace: None,
+        }
+    }
+    fn traced(mut self) -> Limiter<V> {
+        self.trace = Some(Tracer {
+            events: Mutex::new(Vec::new()),
+            depth: AtomicUsize::new(0),
+        });
+        self
+    }
+    fn take_trace(&self) -> EvalTrace<V> {
+        let events = match self.trace {
+            Some(ref tracer) => mem::take(&mut *tracer.events.lock().unwrap()),
+            None => Vec::new(),
+        };
+        EvalTrace { events }
+    }
+    /// Records the start of a call, given its depth, if evaluation is being traced. Must be
+    /// followed by `trace_end`.
+    fn trace_start<F>(&self, event: F) -> Option<usize>
+    where
+        F: FnOnce(usize) -> TraceEvent<V>,
+    {
+        self.trace.as_ref().map(|tracer| {
+            let depth = tracer.depth.fetch_add(1, Ordering::Relaxed);
+            let mut events = tracer.events.lock().unwrap();
+            events.push(event(depth));
+            events.len() - 1
+        })
+    }
+    fn trace_end(&self, index: Option<usize>, v: Option<&V>) {
+        if let (Some(tracer), Some(index)) = (self.trace.as_ref(), index) {
+            tracer.depth.fetch_sub(1, Ordering::Relaxed);
+            match tracer.events.lock().unwrap()[index] {
+                TraceEvent::Primitive { ref mut result, .. }
+                | TraceEvent::Lifted { ref mut result, .. } => *result = v.cloned(),
+            }
+        }
+    }
+    /// Accounts for a reduction step nested within the current one. Must be followed by `exit`,
+    /// even if a limit was exhausted. Once evaluation has stopped, no further steps are made.
+    fn enter<R>(&self) -> Result<(), EvalError<R>> {
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(err) = EvalError::from_stop_code(self.stopped.load(Ordering::Relaxed)) {
+            return Err(err);
+        }
+        if let Some(fuel) = self.fuel {
+            if steps > fuel {
+                return Err(self.stop(EvalError::OutOfFuel));
+            }
+        }
+        if let Some(max_depth) = self.max_depth {
+            if depth > max_depth {
+                return Err(self.stop(EvalError::MaxDepthExceeded));
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(self.stop(EvalError::Timeout));
+            }
+        }
+        Ok(())
+    }
+    fn exit(&self) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+    /// Records that evaluation was stopped by an exhausted limit or a hole, so it is reported by
+    /// `finish` even if a lifted function's evaluator did not propagate it.
+    fn stop<R>(&self, err: EvalError<R>) -> EvalError<R> {
+        let _ = self.stopped.compare_exchange(
+            RUNNING,
+            err.stop_code(),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+        err
+    }
+    /// The result of the outermost evaluation, unless evaluation was stopped.
+    fn finish<T, R>(&self, result: Result<T, EvalError<R>>) -> Result<T, EvalError<R>> {
+        match EvalError::from_stop_code(self.stopped.load(Ordering::Relaxed)) {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
 }
 
 /// A specification for evaluating lambda calculus expressions in a domain.
@@ -217,6 +619,9 @@ where
 ///     fn lift(&self, f: LiftedFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
 ///         Ok(Func(f))
 ///     }
+///     fn limit_error(&self) -> Self::Error {
+///         ListError("evaluation stopped")
+///     }
 /// }
 ///
 /// # fn main() {
@@ -245,6 +650,17 @@ pub trait Evaluator: Sized + Sync {
     fn lift(&self, _f: LiftedFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
         Err(())
     }
+    /// The error returned by a [`LiftedFunction`] whose evaluation was stopped, because it
+    /// exhausted an [`EvalLimits`] limit or reached a [`Hole`], and by [`Language::eval`] for an
+    /// expression which reaches a `Hole`. The evaluator should propagate it, and evaluation with
+    /// limits then reports the [`EvalError`] which stopped it, whatever the evaluator returned.
+    ///
+    /// [`LiftedFunction`]: struct.LiftedFunction.html
+    /// [`EvalLimits`]: struct.EvalLimits.html
+    /// [`Hole`]: enum.Expression.html#variant.Hole
+    /// [`Language::eval`]: struct.Language.html#method.eval
+    /// [`EvalError`]: enum.EvalError.html
+    fn limit_error(&self) -> Self::Error;
 }
 
 /// Like [`Evaluator`], but you get to decide whether certain arguments should be evaluated.
@@ -324,6 +740,9 @@ pub trait Evaluator: Sized + Sync {
 ///             _ => unreachable!(),
 ///         }
 ///     }
+///     fn limit_error(&self) -> Self::Error {
+///         ListError("evaluation stopped")
+///     }
 /// }
 ///
 /// # fn main() {
@@ -365,14 +784,23 @@ pub trait LazyEvaluator: Sized + Sync {
     fn lift(&self, _f: LiftedLazyFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
         Err(())
     }
+    /// Like [`Evaluator::limit_error`], for a [`LiftedLazyFunction`], including the thunks passed
+    /// to [`lazy_evaluate`].
+    ///
+    /// [`Evaluator::limit_error`]: trait.Evaluator.html#tymethod.limit_error
+    /// [`LiftedLazyFunction`]: struct.LiftedLazyFunction.html
+    /// [`lazy_evaluate`]: #tymethod.lazy_evaluate
+    fn limit_error(&self) -> Self::Error;
 }
 
 /// An [`Evaluator`] defined solely by a function.
 ///
-/// Use [`of`] to create one. Incapable of dealing with first-class functions.
+/// Use [`of`] to create one. Incapable of dealing with first-class functions. Its
+/// [`limit_error`], for an expression which reaches a hole, is the default value of the error.
 ///
 /// [`Evaluator`]: trait.Evaluator.html
 /// [`of`]: #method.of
+/// [`limit_error`]: trait.Evaluator.html#tymethod.limit_error
 pub struct SimpleEvaluator<V, R, F>(F, ::std::marker::PhantomData<(R, V)>);
 impl<V, R, F> SimpleEvaluator<V, R, F>
 where
@@ -404,7 +832,7 @@ where
 impl<V, R, F> Evaluator for SimpleEvaluator<V, R, F>
 where
     V: Clone + PartialEq + Send + Sync,
-    R: Clone + Sync,
+    R: Clone + Default + Sync,
     F: Fn(&str, &[V]) -> Result<V, R> + Sync,
 {
     type Space = V;
@@ -412,6 +840,9 @@ where
     fn evaluate(&self, primitive: &str, inps: &[Self::Space]) -> Result<Self::Space, Self::Error> {
         (self.0)(primitive, inps)
     }
+    fn limit_error(&self) -> Self::Error {
+        R::default()
+    }
 }
 
 /// A function object for evaluation in domains with first-class functions.
@@ -424,6 +855,7 @@ pub struct LiftedFunction<V: Clone + PartialEq + Send + Sync, E: Evaluator<Space
     Arc<ReducedExpression<V>>,
     Arc<E>,
     Arc<VecDeque<ReducedExpression<V>>>,
+    Arc<Limiter<V>>,
 );
 impl<V, E> LiftedFunction<V, E>
 where
@@ -435,7 +867,17 @@ where
     ///
     /// [`Language`]: struct.Language.html
     pub fn eval(&self, xs: &[V]) -> Result<V, E::Error> {
-        self.0.eval_inps_with_env(&self.1, &self.2, xs)
+        let index = self.3.trace_start(|depth| TraceEvent::Lifted {
+            depth,
+            args: xs.to_vec(),
+            result: None,
+        });
+        let v = self
+            .0
+            .eval_inps_with_env(&self.1, &self.2, xs, &self.3)
+            .map_err(|err| err.into_evaluator_error(|| self.1.limit_error()));
+        self.3.trace_end(index, v.as_ref().ok());
+        v
     }
 }
 impl<V, E> Clone for LiftedFunction<V, E>
@@ -444,7 +886,21 @@ where
     V: Clone + PartialEq + Send + Sync,
 {
     fn clone(&self) -> Self {
-        LiftedFunction(self.0.clone(), self.1.clone(), self.2.clone())
+        LiftedFunction(
+            self.0.clone(),
+            self.1.clone(),
+            self.2.clone(),
+            self.3.clone(),
+        )
+    }
+}
+impl<V, E> fmt::Debug for LiftedFunction<V, E>
+where
+    E: Evaluator<Space = V>,
+    V: Clone + PartialEq + Send + Sync,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LiftedFunction({:?})", self.0)
     }
 }
 impl<V, E> PartialEq for LiftedFunction<V, E>
@@ -474,6 +930,7 @@ pub struct LiftedLazyFunction<V: Clone + PartialEq + Send + Sync, E: LazyEvaluat
     Arc<ReducedExpression<V>>,
     Arc<E>,
     Arc<VecDeque<ReducedExpression<V>>>,
+    Arc<Limiter<V>>,
 );
 impl<V, E> LiftedLazyFunction<V, E>
 where
@@ -485,7 +942,28 @@ where
     ///
     /// [`Language`]: struct.Language.html
     pub fn eval(&self, xs: &[V]) -> Result<V, E::Error> {
-        self.0.lazy_eval_inps_with_env(&self.1, &self.2, xs)
+        let index = match *self.0 {
+            // an argument that was already a value is not worth tracing
+            Value(_) if xs.is_empty() => None,
+            _ => self.3.trace_start(|depth| TraceEvent::Lifted {
+                depth,
+                args: xs.to_vec(),
+                result: None,
+            }),
+        };
+        let v = self
+            .0
+            .lazy_eval_inps_with_env(&self.1, &self.2, xs, &self.3)
+            .map_err(|err| err.into_evaluator_error(|| self.1.limit_error()));
+        self.3.trace_end(index, v.as_ref().ok());
+        v
+    }
+    /// The value of the function if it was already evaluated.
+    fn value(&self) -> Option<V> {
+        match *self.0 {
+            Value(ref v) => Some(v.clone()),
+            _ => None,
+        }
     }
 }
 impl<V, E> Clone for LiftedLazyFunction<V, E>
@@ -494,7 +972,21 @@ where
     V: Clone + PartialEq + Send + Sync,
 {
     fn clone(&self) -> Self {
-        LiftedLazyFunction(self.0.clone(), self.1.clone(), self.2.clone())
+        LiftedLazyFunction(
+            self.0.clone(),
+            self.1.clone(),
+            self.2.clone(),
+            self.3.clone(),
+        )
+    }
+}
+impl<V, E> fmt::Debug for LiftedLazyFunction<V, E>
+where
+    E: LazyEvaluator<Space = V>,
+    V: Clone + PartialEq + Send + Sync,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LiftedLazyFunction({:?})", self.0)
     }
 }
 impl<V, E> PartialEq for LiftedLazyFunction<V, E>
@@ -513,15 +1005,350 @@ where
 {
 }
 
+/// An [`Expression`] compiled for evaluation, so it may be evaluated many times over different
+/// inputs without being analyzed again. Use [`Language::compile`] to create one.
+///
+/// The body of the expression, under the abstractions which bind its inputs, is compiled into a
+/// tree of inputs and primitive calls. Evaluation walks this tree rather than reducing the
+/// expression, and a batch of inputs is evaluated together: the tree is walked once for the whole
+/// batch, and each primitive is called on every input that has not yet failed before evaluation
+/// moves on. Any other part of the expression, such as an abstraction passed to a primitive or a
+/// use of `fix`, is reduced in the same way as [`Language::eval_arc`] does. A step of the
+/// [`EvalLimits`] fuel is a primitive call in the compiled tree, or a reduction step elsewhere.
+///
+/// Lazy evaluation is driven by the [`LazyEvaluator`], so [`lazy_eval`] and the other lazy
+/// methods reduce the prepared expression for each input, in the same way and with the same
+/// results as [`Language::lazy_eval_arc`].
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate polytype;
+/// # extern crate programinduction;
+/// use programinduction::lambda::{Language, SimpleEvaluator};
+/// use std::sync::Arc;
+///
+/// fn evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
+///     match name {
+///         "1" => Ok(1),
+///         "+" => Ok(inps[0] + inps[1]),
+///         _ => unreachable!(),
+///     }
+/// }
+///
+/// # fn main() {
+/// let dsl = Language::uniform(vec![
+///     ("1", ptp!(int)),
+///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+/// ]);
+/// let expr = dsl.parse("(λ (λ (+ (+ 1 $0) $1)))").unwrap();
+/// let compiled = dsl.compile(&expr);
+/// let evaluator = Arc::new(SimpleEvaluator::of(evaluate));
+///
+/// assert_eq!(compiled.eval(&evaluator, &[2, 5]), Ok(8));
+///
+/// let inputs = vec![vec![1, 1], vec![2, 3], vec![5, 8]];
+/// let outputs = compiled.eval_batch(&evaluator, inputs.iter().map(|inps| &inps[..]));
+/// assert_eq!(outputs, vec![Ok(3), Ok(6), Ok(14)]);
+/// # }
+/// ```
+///
+/// [`Expression`]: enum.Expression.html
+/// [`Language::compile`]: struct.Language.html#method.compile
+/// [`Language::eval_arc`]: struct.Language.html#method.eval_arc
+/// [`Language::lazy_eval_arc`]: struct.Language.html#method.lazy_eval_arc
+/// [`EvalLimits`]: struct.EvalLimits.html
+/// [`LazyEvaluator`]: trait.LazyEvaluator.html
+/// [`lazy_eval`]: #method.lazy_eval
+#[derive(Debug, Clone)]
+pub struct CompiledExpression<V: Clone + PartialEq + Send + Sync> {
+    expr: ReducedExpression<V>,
+    /// The number of inputs and the compiled body, unless the body is reduced anyway.
+    body: Option<(usize, Compiled<V>)>,
+}
+impl<V> CompiledExpression<V>
+where
+    V: Clone + PartialEq + Send + Sync,
+{
+    fn new(expr: ReducedExpression<V>) -> Self {
+        let body = {
+            let (n_inputs, body) = match expr {
+                Abstraction(depth, ref body) => (depth, &**body),
+                ref body => (0, body),
+            };
+            match Compiled::new(body, n_inputs) {
+                Compiled::Reduce(_) => None,
+                body => Some((n_inputs, body)),
+            }
+        };
+        CompiledExpression { expr, body }
+    }
+    /// Like [`Language::eval_arc`], for the compiled expression.
+    ///
+    /// [`Language::eval_arc`]: struct.Language.html#method.eval_arc
+    pub fn eval<E>(&self, evaluator: &Arc<E>, inps: &[V]) -> Result<V, E::Error>
+    where
+        E: Evaluator<Space = V>,
+    {
+        self.eval_with_limits(evaluator, inps, &EvalLimits::default())
+            .map_err(|err| err.into_evaluator_error(|| evaluator.limit_error()))
+    }
+    /// Like [`Language::lazy_eval_arc`], for the compiled expression.
+    ///
+    /// [`Language::lazy_eval_arc`]: struct.Language.html#method.lazy_eval_arc
+    pub fn lazy_eval<E>(&self, evaluator: &Arc<E>, inps: &[V]) -> Result<V, E::Error>
+    where
+        E: LazyEvaluator<Space = V>,
+    {
+        self.lazy_eval_with_limits(evaluator, inps, &EvalLimits::default())
+            .map_err(|err| err.into_evaluator_error(|| evaluator.limit_error()))
+    }
+    /// Like [`Language::eval_with_limits`], for the compiled expression.
+    ///
+    /// [`Language::eval_with_limits`]: struct.Language.html#method.eval_with_limits
+    pub fn eval_with_limits<E>(
+        &self,
+        evaluator: &Arc<E>,
+        inps: &[V],
+        limits: &EvalLimits,
+    ) -> Result<V, EvalError<E::Error>>
+    where
+        E: Evaluator<Space = V>,
+    {
+        self.eval_batch_with_limits(evaluator, Some(inps), limits)
+            .pop()
+            .unwrap()
+    }
+    /// Like [`Language::lazy_eval_with_limits`], for the compiled expression.
+    ///
+    /// [`Language::lazy_eval_with_limits`]: struct.Language.html#method.lazy_eval_with_limits
+    pub fn lazy_eval_with_limits<E>(
+        &self,
+        evaluator: &Arc<E>,
+        inps: &[V],
+        limits: &EvalLimits,
+    ) -> Result<V, EvalError<E::Error>>
+    where
+        E: LazyEvaluator<Space = V>,
+    {
+        let limiter = Arc::new(Limiter::new(limits));
+        limiter.finish(self.expr.lazy_eval_inps(evaluator, inps, &limiter))
+    }
+    /// Evaluates the compiled expression on each of a sequence of inputs with [`eval`], all
+    /// together, giving their outputs in the same order.
+    ///
+    /// [`eval`]: #method.eval
+    pub fn eval_batch<'a, E, I>(&self, evaluator: &Arc<E>, inputs: I) -> Vec<Result<V, E::Error>>
+    where
+        E: Evaluator<Space = V>,
+        I: IntoIterator<Item = &'a [V]>,
+        V: 'a,
+    {
+        self.eval_batch_with_limits(evaluator, inputs, &EvalLimits::default())
+            .into_iter()
+            .map(|o| o.map_err(|err| err.into_evaluator_error(|| evaluator.limit_error())))
+            .collect()
+    }
+    /// Like [`eval_batch`], but for lazy evaluation with a [`LazyEvaluator`]. Each input is
+    /// evaluated with [`lazy_eval`] in turn, and outputs are computed as they are consumed, so a
+    /// caller may stop early, e.g. on the first output which fails to match an example.
+    ///
+    /// [`eval_batch`]: #method.eval_batch
+    /// [`LazyEvaluator`]: trait.LazyEvaluator.html
+    /// [`lazy_eval`]: #method.lazy_eval
+    pub fn lazy_eval_batch<'a, E, I>(
+        &'a self,
+        evaluator: &'a Arc<E>,
+        inputs: I,
+    ) -> impl Iterator<Item = Result<V, E::Error>> + 'a
+    where
+        E: LazyEvaluator<Space = V>,
+        I: IntoIterator<Item = &'a [V]>,
+        I::IntoIter: 'a,
+    {
+        inputs
+            .into_iter()
+            .map(move |inps| self.lazy_eval(evaluator, inps))
+    }
+    /// Like [`eval_batch`], but with [`eval_with_limits`]. The limits apply to each input
+    /// separately.
+    ///
+    /// [`eval_batch`]: #method.eval_batch
+    /// [`eval_with_limits`]: #method.eval_with_limits
+    pub fn eval_batch_with_limits<'a, E, I>(
+        &self,
+        evaluator: &Arc<E>,
+        inputs: I,
+        limits: &EvalLimits,
+    ) -> Vec<Result<V, EvalError<E::Error>>>
+    where
+        E: Evaluator<Space = V>,
+        I: IntoIterator<Item = &'a [V]>,
+        V: 'a,
+    {
+        let inputs: Vec<_> = inputs
+            .into_iter()
+            .map(|inps| (inps, Arc::new(Limiter::new(limits))))
+            .collect();
+        match self.body {
+            Some((n_inputs, ref body)) if inputs.iter().all(|(inps, _)| inps.len() == n_inputs) => {
+                let batch: Vec<_> = inputs
+                    .iter()
+                    .map(|(inps, limiter)| (*inps, limiter))
+                    .collect();
+                body.eval(evaluator, &batch)
+                    .into_iter()
+                    .zip(&inputs)
+                    .map(|(o, (_, limiter))| limiter.finish(o))
+                    .collect()
+            }
+            _ => inputs
+                .iter()
+                .map(|(inps, limiter)| {
+                    limiter.finish(self.expr.eval_inps(evaluator, inps, limiter))
+                })
+                .collect(),
+        }
+    }
+    /// Like [`lazy_eval_batch`], but with [`lazy_eval_with_limits`]. The limits apply to each
+    /// input separately.
+    ///
+    /// [`lazy_eval_batch`]: #method.lazy_eval_batch
+    /// [`lazy_eval_with_limits`]: #method.lazy_eval_with_limits
+    pub fn lazy_eval_batch_with_limits<'a, E, I>(
+        &'a self,
+        evaluator: &'a Arc<E>,
+        inputs: I,
+        limits: &'a EvalLimits,
+    ) -> impl Iterator<Item = Result<V, EvalError<E::Error>>> + 'a
+    where
+        E: LazyEvaluator<Space = V>,
+        I: IntoIterator<Item = &'a [V]>,
+        I::IntoIter: 'a,
+    {
+        inputs
+            .into_iter()
+            .map(move |inps| self.lazy_eval_with_limits(evaluator, inps, limits))
+    }
+}
+
+/// A part of a [`CompiledExpression`], which is evaluated for a whole batch of inputs at once.
+///
+/// [`CompiledExpression`]: struct.CompiledExpression.html
+#[derive(Debug, Clone)]
+enum Compiled<V: Clone + PartialEq + Send + Sync> {
+    /// An input, by its position among the inputs.
+    Input(usize),
+    /// A primitive applied to as many arguments as it takes.
+    Call(String, Vec<Compiled<V>>),
+    /// Anything else, which is reduced with the inputs as its environment, and lifted if it is a
+    /// function.
+    Reduce(ReducedExpression<V>),
+}
+impl<V> Compiled<V>
+where
+    V: Clone + PartialEq + Send + Sync,
+{
+    /// Compiles the body of an expression which binds `n_inputs` inputs.
+    fn new(expr: &ReducedExpression<V>, n_inputs: usize) -> Self {
+        match *expr {
+            Index(i) if i < n_inputs => Compiled::Input(n_inputs - 1 - i),
+            Primitive(ref name, 0) => Compiled::Call(name.clone(), vec![]),
+            Application(ref xs) => match xs[0] {
+                Primitive(ref name, arity) if arity == xs.len() - 1 => {
+                    let args = xs[1..].iter().map(|x| Compiled::new(x, n_inputs)).collect();
+                    Compiled::Call(name.clone(), args)
+                }
+                _ => Compiled::Reduce(expr.clone()),
+            },
+            _ => Compiled::Reduce(expr.clone()),
+        }
+    }
+    /// The output for each of a batch of inputs, which have their own limiters.
+    fn eval<E>(
+        &self,
+        evaluator: &Arc<E>,
+        batch: &[(&[V], &Arc<Limiter<V>>)],
+    ) -> Vec<Result<V, EvalError<E::Error>>>
+    where
+        E: Evaluator<Space = V>,
+    {
+        match *self {
+            Compiled::Input(i) => batch.iter().map(|(inps, _)| Ok(inps[i].clone())).collect(),
+            Compiled::Call(ref name, ref args) => {
+                let mut arg_values: Vec<Result<Vec<V>, _>> = batch
+                    .iter()
+                    .map(|(_, limiter)| limiter.enter().map(|()| Vec::with_capacity(args.len())))
+                    .collect();
+                for arg in args {
+                    // inputs which failed on an earlier argument are not evaluated further
+                    let active: Vec<usize> = (0..batch.len())
+                        .filter(|&i| arg_values[i].is_ok())
+                        .collect();
+                    let active_batch: Vec<_> = active.iter().map(|&i| batch[i]).collect();
+                    for (i, v) in active.into_iter().zip(arg.eval(evaluator, &active_batch)) {
+                        match v {
+                            Ok(v) => {
+                                if let Ok(ref mut values) = arg_values[i] {
+                                    values.push(v)
+                                }
+                            }
+                            Err(err) => arg_values[i] = Err(err),
+                        }
+                    }
+                }
+                let outputs = arg_values
+                    .into_iter()
+                    .map(|args| {
+                        args.and_then(|args| {
+                            evaluator
+                                .evaluate(name, &args)
+                                .map_err(EvalError::Evaluator)
+                        })
+                    })
+                    .collect();
+                for (_, limiter) in batch {
+                    limiter.exit();
+                }
+                outputs
+            }
+            Compiled::Reduce(ref expr) => batch
+                .iter()
+                .map(|&(inps, limiter)| {
+                    let env = Arc::new(inps.iter().rev().map(|v| Value(v.clone())).collect());
+                    match expr.reduce(evaluator, &env, limiter)? {
+                        Value(v) => Ok(v),
+                        ref x if x.is_function() => Ok(evaluator
+                            .lift(LiftedFunction(
+                                Arc::new(x.clone()),
+                                evaluator.clone(),
+                                env.clone(),
+                                limiter.clone(),
+                            ))
+                            .expect("evaluator could not lift an abstraction")),
+                        e => panic!("tried to evaluate an irreducible expression: {:?}", e),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
 use self::ReducedExpression::*;
 #[derive(Clone, PartialEq)]
 pub enum ReducedExpression<V: Clone + PartialEq + Send + Sync> {
     Value(V),
-    Primitive(String, TypeSchema),
+    /// store arity for primitives.
+    Primitive(String, usize),
     Application(Vec<ReducedExpression<V>>),
     /// store depth (never zero) for nested abstractions.
     Abstraction(usize, Box<ReducedExpression<V>>),
     Index(usize),
+    /// the built-in fixed-point primitive.
+    Fix,
+    /// a hole in a sketch, which cannot be evaluated.
+    Hole,
 }
 impl<V> fmt::Debug for ReducedExpression<V>
 where
@@ -534,6 +1361,8 @@ where
             Application(ref xs) => write!(f, "Application({:?})", xs),
             Abstraction(depth, ref body) => write!(f, "Abstraction({}, {:?})", depth, body),
             Index(n) => write!(f, "Index({})", n),
+            Fix => write!(f, "Fix"),
+            Hole => write!(f, "Hole"),
         }
     }
 }
@@ -544,42 +1373,59 @@ where
     pub fn new(dsl: &Language, expr: &Expression) -> Self {
         Self::from_expr(dsl, &dsl.strip_invented(expr))
     }
-    pub fn eval_inps_with_env<E>(
+    fn eval_inps_with_env<E>(
         &self,
         evaluator: &Arc<E>,
         env: &Arc<VecDeque<ReducedExpression<V>>>,
         inps: &[V],
-    ) -> Result<V, E::Error>
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<V, EvalError<E::Error>>
     where
         E: Evaluator<Space = V>,
     {
-        let expr = self.clone().with_args(inps);
-        let mut evaluated = expr.eval(evaluator, env)?;
+        match self
+            .clone()
+            .with_args(inps)
+            .reduce(evaluator, env, limiter)?
+        {
+            Value(o) => Ok(o),
+            e => panic!("tried to evaluate an irreducible expression: {:?}", e),
+        }
+    }
+    /// Evaluates the expression until it no longer changes.
+    fn reduce<E>(
+        &self,
+        evaluator: &Arc<E>,
+        env: &Arc<VecDeque<ReducedExpression<V>>>,
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<ReducedExpression<V>, EvalError<E::Error>>
+    where
+        E: Evaluator<Space = V>,
+    {
+        let mut evaluated = self.eval(evaluator, env, limiter)?;
         loop {
-            let next = evaluated.eval(evaluator, env)?;
+            let next = evaluated.eval(evaluator, env, limiter)?;
             if next == evaluated {
                 break;
             }
             evaluated = next;
         }
-        match evaluated {
-            Value(o) => Ok(o),
-            e => panic!("tried to evaluate an irreducible expression: {:?}", e),
-        }
+        Ok(evaluated)
     }
-    pub fn lazy_eval_inps_with_env<E>(
+    fn lazy_eval_inps_with_env<E>(
         &self,
         evaluator: &Arc<E>,
         env: &Arc<VecDeque<ReducedExpression<V>>>,
         inps: &[V],
-    ) -> Result<V, E::Error>
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<V, EvalError<E::Error>>
     where
         E: LazyEvaluator<Space = V>,
     {
         let expr = self.clone().with_args(inps);
-        let mut evaluated = expr.lazy_eval(evaluator, env)?;
+        let mut evaluated = expr.lazy_eval(evaluator, env, limiter)?;
         loop {
-            let next = evaluated.lazy_eval(evaluator, env)?;
+            let next = evaluated.lazy_eval(evaluator, env, limiter)?;
             if next == evaluated {
                 break;
             }
@@ -590,25 +1436,51 @@ where
             e => panic!("tried to evaluate an irreducible expression {:?}", e),
         }
     }
-    pub fn eval_inps<E>(&self, evaluator: &Arc<E>, inps: &[V]) -> Result<V, E::Error>
+    fn eval_inps<E>(
+        &self,
+        evaluator: &Arc<E>,
+        inps: &[V],
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<V, EvalError<E::Error>>
     where
         E: Evaluator<Space = V>,
     {
         let env = Arc::new(VecDeque::new());
-        self.eval_inps_with_env(evaluator, &env, inps)
+        self.eval_inps_with_env(evaluator, &env, inps, limiter)
     }
-    pub fn lazy_eval_inps<E>(&self, evaluator: &Arc<E>, inps: &[V]) -> Result<V, E::Error>
+    fn lazy_eval_inps<E>(
+        &self,
+        evaluator: &Arc<E>,
+        inps: &[V],
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<V, EvalError<E::Error>>
     where
         E: LazyEvaluator<Space = V>,
     {
         let env = Arc::new(VecDeque::new());
-        self.lazy_eval_inps_with_env(evaluator, &env, inps)
+        self.lazy_eval_inps_with_env(evaluator, &env, inps, limiter)
     }
     fn eval<E>(
         &self,
         evaluator: &Arc<E>,
         env: &Arc<VecDeque<ReducedExpression<V>>>,
-    ) -> Result<ReducedExpression<V>, E::Error>
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<ReducedExpression<V>, EvalError<E::Error>>
+    where
+        E: Evaluator<Space = V>,
+    {
+        let result = limiter
+            .enter()
+            .and_then(|()| self.eval_step(evaluator, env, limiter));
+        limiter.exit();
+        result
+    }
+    fn eval_step<E>(
+        &self,
+        evaluator: &Arc<E>,
+        env: &Arc<VecDeque<ReducedExpression<V>>>,
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<ReducedExpression<V>, EvalError<E::Error>>
     where
         E: Evaluator<Space = V>,
     {
@@ -617,13 +1489,12 @@ where
                 let f = &xs[0];
                 let mut xs: Vec<_> = xs[1..]
                     .iter()
-                    .map(|x| x.eval(evaluator, env))
+                    .map(|x| x.eval(evaluator, env, limiter))
                     .collect::<Result<_, _>>()?;
                 match *f {
-                    Primitive(ref name, ref tp) => {
+                    Primitive(ref name, arity) => {
                         // when applying a primitive, check if all arity-many args are concrete
                         // values, try lifting abstractions, and evaluate if possible.
-                        let arity = arity(tp);
                         if arity == 0 {
                             panic!(
                                 "tried to apply a primitive that wasn't a function: {}",
@@ -631,12 +1502,12 @@ where
                             )
                         } else if xs.len() < arity
                             || !xs.iter().take(arity).all(|x| match *x {
-                                Value(_) | Abstraction(_, _) => true, // evaluatable
-                                _ => false,
+                                Value(_) => true,
+                                ref x => x.is_function(), // evaluatable by lifting
                             })
                         {
                             // not enough args or not all evaluatable.
-                            xs.insert(0, f.eval(evaluator, env)?);
+                            xs.insert(0, f.eval(evaluator, env, limiter)?);
                             Ok(Application(xs))
                         } else {
                             let mut args = xs;
@@ -645,7 +1516,7 @@ where
                                 .into_iter()
                                 .map(|x| match x {
                                     Value(v) => v,
-                                    Abstraction(_, _) => {
+                                    x => {
                                         let env = env.clone();
                                         evaluator
                                             .clone()
@@ -653,13 +1524,21 @@ where
                                                 Arc::new(x),
                                                 evaluator.clone(),
                                                 env.clone(),
+                                                limiter.clone(),
                                             ))
                                             .expect("evaluator could not lift an abstraction")
                                     }
-                                    _ => unreachable!(),
                                 })
                                 .collect();
-                            let v = Value(evaluator.evaluate(name, &args)?);
+                            let index = limiter.trace_start(|depth| TraceEvent::Primitive {
+                                depth,
+                                name: name.clone(),
+                                args: args.iter().cloned().map(Some).collect(),
+                                result: None,
+                            });
+                            let v = evaluator.evaluate(name, &args);
+                            limiter.trace_end(index, v.as_ref().ok());
+                            let v = Value(v.map_err(EvalError::Evaluator)?);
                             if xs.is_empty() {
                                 Ok(v)
                             } else {
@@ -682,7 +1561,7 @@ where
                                 depth -= 1;
                             }
                             xs.reverse();
-                            let v = body.eval(evaluator, &Arc::new(env))?;
+                            let v = body.eval(evaluator, &Arc::new(env), limiter)?;
                             if depth > 0 {
                                 Ok(Abstraction(depth, Box::new(v)))
                             } else if xs.is_empty() {
@@ -696,23 +1575,50 @@ where
                             }
                         }
                     }
-                    _ => {
-                        xs.insert(0, f.eval(evaluator, env)?);
-                        Ok(Application(xs))
+                    Fix => {
+                        // unroll (fix f x) into (f (fix f) x)
+                        if xs.len() < 2 {
+                            xs.insert(0, Fix);
+                            Ok(Application(xs))
+                        } else {
+                            let fix_f = Application(vec![Fix, xs[0].clone()]);
+                            xs.insert(1, fix_f);
+                            Application(xs).eval(evaluator, env, limiter)
+                        }
                     }
+                    _ => match f.eval(evaluator, env, limiter)? {
+                        // a variable may be bound to a partial application
+                        Application(mut f) => {
+                            f.extend(xs);
+                            Application(f).eval(evaluator, env, limiter)
+                        }
+                        f => {
+                            xs.insert(0, f);
+                            Ok(Application(xs))
+                        }
+                    },
                 }
             }
-            Primitive(ref name, ref tp) => {
-                if is_arrow(tp) {
-                    Ok(Primitive(name.clone(), tp.clone()))
+            Primitive(ref name, arity) => {
+                if arity > 0 {
+                    Ok(self.clone())
                 } else {
-                    Ok(Value(evaluator.evaluate(name, &[])?))
+                    let index = limiter.trace_start(|depth| TraceEvent::Primitive {
+                        depth,
+                        name: name.clone(),
+                        args: vec![],
+                        result: None,
+                    });
+                    let v = evaluator.evaluate(name, &[]);
+                    limiter.trace_end(index, v.as_ref().ok());
+                    Ok(Value(v.map_err(EvalError::Evaluator)?))
                 }
             }
             Index(i) => match env.get(i) {
                 Some(x) => Ok(x.clone()),
                 None => Ok(Index(i)),
             },
+            Hole => Err(limiter.stop(EvalError::Hole)),
             _ => Ok(self.clone()),
         }
     }
@@ -720,7 +1626,23 @@ where
         &self,
         evaluator: &Arc<E>,
         env: &Arc<VecDeque<ReducedExpression<V>>>,
-    ) -> Result<ReducedExpression<V>, E::Error>
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<ReducedExpression<V>, EvalError<E::Error>>
+    where
+        E: LazyEvaluator<Space = V>,
+    {
+        let result = limiter
+            .enter()
+            .and_then(|()| self.lazy_eval_step(evaluator, env, limiter));
+        limiter.exit();
+        result
+    }
+    fn lazy_eval_step<E>(
+        &self,
+        evaluator: &Arc<E>,
+        env: &Arc<VecDeque<ReducedExpression<V>>>,
+        limiter: &Arc<Limiter<V>>,
+    ) -> Result<ReducedExpression<V>, EvalError<E::Error>>
     where
         E: LazyEvaluator<Space = V>,
     {
@@ -729,9 +1651,8 @@ where
                 let f = &exprs[0];
                 let xs = &exprs[1..];
                 match *f {
-                    Primitive(ref name, ref tp) => {
+                    Primitive(ref name, arity) => {
                         // when applying a primitive, check for arity-many args
-                        let arity = arity(tp);
                         if arity == 0 {
                             panic!(
                                 "tried to apply a primitive that wasn't a function: {}",
@@ -746,7 +1667,7 @@ where
                             let args: Vec<_> = args
                                 .into_iter()
                                 .map(|x| {
-                                    if let Abstraction(_, _) = x {
+                                    if x.is_function() {
                                         Value(
                                             evaluator
                                                 .clone()
@@ -754,6 +1675,7 @@ where
                                                     Arc::new(x),
                                                     evaluator.clone(),
                                                     env.clone(),
+                                                    limiter.clone(),
                                                 ))
                                                 .expect("evaluator could not lift an abstraction"),
                                         )
@@ -762,10 +1684,23 @@ where
                                     }
                                 })
                                 .map(|x| {
-                                    LiftedLazyFunction(Arc::new(x), evaluator.clone(), env.clone())
+                                    LiftedLazyFunction(
+                                        Arc::new(x),
+                                        evaluator.clone(),
+                                        env.clone(),
+                                        limiter.clone(),
+                                    )
                                 })
                                 .collect();
-                            let v = Value(evaluator.lazy_evaluate(name, &args)?);
+                            let index = limiter.trace_start(|depth| TraceEvent::Primitive {
+                                depth,
+                                name: name.clone(),
+                                args: args.iter().map(|x| x.value()).collect(),
+                                result: None,
+                            });
+                            let v = evaluator.lazy_evaluate(name, &args);
+                            limiter.trace_end(index, v.as_ref().ok());
+                            let v = Value(v.map_err(EvalError::Evaluator)?);
                             if xs.is_empty() {
                                 Ok(v)
                             } else {
@@ -791,7 +1726,7 @@ where
                             let env = Arc::new(env);
                             let mut body = (*body).clone();
                             body.substitute_indices(&env, 0);
-                            let v = body.lazy_eval(evaluator, &env)?;
+                            let v = body.lazy_eval(evaluator, &env, limiter)?;
                             if depth > 0 {
                                 Ok(Abstraction(depth, Box::new(v)))
                             } else if xs.is_empty() {
@@ -804,23 +1739,59 @@ where
                             }
                         }
                     }
+                    Fix => {
+                        // unroll (fix f x) into (f (fix f) x)
+                        if xs.len() < 2 {
+                            Ok(Application(exprs.clone()))
+                        } else {
+                            let fix_f = Application(vec![Fix, xs[0].clone()]);
+                            let mut unrolled = vec![xs[0].clone(), fix_f];
+                            unrolled.extend(xs[1..].iter().cloned());
+                            Application(unrolled).lazy_eval(evaluator, env, limiter)
+                        }
+                    }
+                    Application(ref f) => {
+                        // a variable may be substituted with a partial application
+                        let mut f = f.clone();
+                        f.extend(xs.iter().cloned());
+                        Application(f).lazy_eval(evaluator, env, limiter)
+                    }
+                    Hole => Err(limiter.stop(EvalError::Hole)),
                     _ => Ok(Application(exprs.clone())),
                 }
             }
-            Primitive(ref name, ref tp) => {
-                if is_arrow(tp) {
-                    Ok(Primitive(name.clone(), tp.clone()))
+            Primitive(ref name, arity) => {
+                if arity > 0 {
+                    Ok(self.clone())
                 } else {
-                    Ok(Value(evaluator.lazy_evaluate(name, &[])?))
+                    let index = limiter.trace_start(|depth| TraceEvent::Primitive {
+                        depth,
+                        name: name.clone(),
+                        args: vec![],
+                        result: None,
+                    });
+                    let v = evaluator.lazy_evaluate(name, &[]);
+                    limiter.trace_end(index, v.as_ref().ok());
+                    Ok(Value(v.map_err(EvalError::Evaluator)?))
                 }
             }
             Index(i) => match env.get(i) {
                 Some(x) => Ok(x.clone()),
                 None => Ok(Index(i)),
             },
+            Hole => Err(limiter.stop(EvalError::Hole)),
             _ => Ok(self.clone()),
         }
     }
+    /// Whether this is a function that can be lifted: an abstraction or a partially-applied
+    /// `fix`.
+    fn is_function(&self) -> bool {
+        match *self {
+            Abstraction(_, _) => true,
+            Application(ref xs) => xs.len() == 2 && xs[0] == Fix,
+            _ => false,
+        }
+    }
     fn with_args(self, inps: &[V]) -> Self {
         if inps.is_empty() {
             self
@@ -840,8 +1811,9 @@ where
     }
     fn from_expr(dsl: &Language, expr: &Expression) -> Self {
         match *expr {
+            Expression::Primitive(num) if dsl.is_fix(num) => Fix,
             Expression::Primitive(num) => {
-                Primitive(dsl.primitives[num].0.clone(), dsl.primitives[num].1.clone())
+                Primitive(dsl.primitives[num].0.clone(), arity(&dsl.primitives[num].1))
             }
             Expression::Application(ref f, ref x) => {
                 let mut v = vec![Self::from_expr(dsl, x)];
@@ -865,6 +1837,7 @@ where
             }
             Expression::Index(i) => Index(i),
             Expression::Invented(_) => unreachable!(), // invented was stripped
+            Expression::Hole => Hole,
         }
     }
     fn substitute_indices(&mut self, env: &Arc<VecDeque<ReducedExpression<V>>>, offset: usize) {
@@ -899,12 +1872,3 @@ fn arity(mut tp: &TypeSchema) -> usize {
     }
     count
 }
-
-fn is_arrow(mut tp: &TypeSchema) -> bool {
-    loop {
-        match *tp {
-            TypeSchema::Monotype(ref t) => break t.as_arrow().is_some(),
-            TypeSchema::Polytype { ref body, .. } => tp = body,
-        }
-    }
-}
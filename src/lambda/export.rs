@@ -0,0 +1,365 @@
+//! Code generation for running expressions outside of Rust.
+use polytype::{Context, TypeSchema};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use super::{Expression, InferenceError, Language};
+
+/// A language that [`Language::export`] can generate code for.
+///
+/// [`Language::export`]: struct.Language.html#method.export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTarget {
+    Python,
+    Scheme,
+}
+
+/// An error from [`Language::export`].
+///
+/// [`Language::export`]: struct.Language.html#method.export
+#[derive(Debug, Clone)]
+pub enum ExportError {
+    /// A primitive, given by name, which has no template.
+    MissingTemplate(String),
+    /// The expression has a free variable or a hole, so it cannot be made into a function.
+    NotClosed,
+    /// The name of the function, made into an identifier, is also an identifier in a template.
+    NameCollision(String),
+    Inference(InferenceError),
+}
+impl From<InferenceError> for ExportError {
+    fn from(err: InferenceError) -> Self {
+        ExportError::Inference(err)
+    }
+}
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ExportError::MissingTemplate(ref name) => {
+                write!(f, "no template for primitive {}", name)
+            }
+            ExportError::NotClosed => write!(f, "expression has a free variable or hole"),
+            ExportError::NameCollision(ref name) => {
+                write!(f, "name {} is used by a template", name)
+            }
+            ExportError::Inference(ref err) => write!(f, "could not infer type: {}", err),
+        }
+    }
+}
+impl Error for ExportError {
+    fn description(&self) -> &str {
+        "could not export expression"
+    }
+}
+
+pub fn export(
+    dsl: &Language,
+    expr: &Expression,
+    name: &str,
+    target: ExportTarget,
+    templates: &HashMap<String, String>,
+) -> Result<String, ExportError> {
+    let mut used = vec![false; dsl.invented.len()];
+    expr.mark_used(&mut vec![false; dsl.primitives.len()], &mut used);
+    // inventions only refer to earlier inventions
+    for num in (0..dsl.invented.len()).rev() {
+        if used[num] {
+            dsl.invented[num]
+                .0
+                .mark_used(&mut vec![false; dsl.primitives.len()], &mut used);
+        }
+    }
+    // definitions must not be shadowed by, and variables must not be captured by, a template
+    let mut reserved: HashSet<String> = templates
+        .values()
+        .flat_map(|template| target.identifiers(template))
+        .map(String::from)
+        .collect();
+    let name = target.identifier(name);
+    if reserved.contains(&name) {
+        return Err(ExportError::NameCollision(name));
+    }
+    reserved.insert(name.clone());
+    let mut helpers = vec![None; dsl.invented.len()];
+    for num in (0..dsl.invented.len()).filter(|&num| used[num]) {
+        let mut helper = helper_name(dsl, target, num);
+        while reserved.contains(&helper) {
+            helper.push('_');
+        }
+        reserved.insert(helper.clone());
+        helpers[num] = Some(helper);
+    }
+    let generator = Generator {
+        dsl,
+        target,
+        templates,
+        helpers,
+        reserved,
+    };
+
+    let mut definitions = Vec::new();
+    for num in (0..dsl.invented.len()).filter(|&num| used[num]) {
+        let (ref body, ref tp, _) = dsl.invented[num];
+        definitions.push(generator.define(generator.helper(num), body, tp)?);
+    }
+    let tp = dsl.infer(expr)?;
+    definitions.push(generator.define(&name, expr, &tp)?);
+    Ok(definitions.join(target.separator()))
+}
+
+impl ExportTarget {
+    /// Makes a name into a valid identifier.
+    fn identifier(self, name: &str) -> String {
+        let mut identifier: String = name
+            .chars()
+            .map(|c| match self {
+                ExportTarget::Python if !c.is_alphanumeric() => '_',
+                ExportTarget::Scheme if "#;'`,\"|".contains(c) => '_',
+                _ => c,
+            })
+            .collect();
+        if identifier.starts_with(|c: char| c.is_numeric()) {
+            identifier.insert(0, '_')
+        }
+        identifier
+    }
+    /// The identifiers, and other tokens, in code.
+    fn identifiers(self, code: &str) -> Vec<&str> {
+        code.split(|c: char| match self {
+            ExportTarget::Python => !c.is_alphanumeric() && c != '_',
+            ExportTarget::Scheme => c.is_whitespace() || "()[]{}#;'`,\"|".contains(c),
+        })
+        .filter(|token| !token.is_empty())
+        .collect()
+    }
+    fn separator(self) -> &'static str {
+        match self {
+            ExportTarget::Python => "\n",
+            ExportTarget::Scheme => "",
+        }
+    }
+}
+
+fn helper_name(dsl: &Language, target: ExportTarget, num: usize) -> String {
+    match dsl.invented_name(num) {
+        Some(name) => target.identifier(name),
+        None => format!("f{}", num),
+    }
+}
+
+fn arity(schema: &TypeSchema) -> usize {
+    let tp = schema.clone().instantiate_owned(&mut Context::default());
+    tp.args().map(|args| args.len()).unwrap_or(0)
+}
+
+struct Generator<'a> {
+    dsl: &'a Language,
+    target: ExportTarget,
+    templates: &'a HashMap<String, String>,
+    /// Names of the helper definitions, for inventions that are used.
+    helpers: Vec<Option<String>>,
+    /// Names of the generated definitions and identifiers in templates, which variables must not
+    /// use.
+    reserved: HashSet<String>,
+}
+impl<'a> Generator<'a> {
+    fn helper(&self, num: usize) -> &str {
+        self.helpers[num]
+            .as_ref()
+            .expect("invention was not marked as used")
+    }
+    /// A definition of a function, with a parameter for every argument of its type.
+    fn define(
+        &self,
+        name: &str,
+        expr: &Expression,
+        tp: &TypeSchema,
+    ) -> Result<String, ExportError> {
+        let mut params = Vec::new();
+        for _ in 0..arity(tp) {
+            let param = self.fresh_name(&params);
+            params.push(param);
+        }
+        // parameters beyond the expression's abstractions are applied to its body
+        let mut body = expr;
+        let mut scope = Vec::new();
+        while let Expression::Abstraction(ref inner) = *body {
+            if scope.len() == params.len() {
+                break;
+            }
+            scope.push(params[scope.len()].clone());
+            body = inner;
+        }
+        let extra = params[scope.len()..].to_vec();
+        let body = self.apply(body, &mut scope, extra)?;
+        Ok(match self.target {
+            ExportTarget::Python => format!(
+                "def {}({}):\n    return {}\n",
+                name,
+                params.join(", "),
+                body
+            ),
+            ExportTarget::Scheme => {
+                let mut signature = vec![name.to_string()];
+                signature.extend(params);
+                format!("(define ({}) {})\n", signature.join(" "), body)
+            }
+        })
+    }
+    fn gen(&self, expr: &Expression, scope: &mut Vec<String>) -> Result<String, ExportError> {
+        self.apply(expr, scope, Vec::new())
+    }
+    /// Generates the expression applied to arguments that were already generated.
+    fn apply(
+        &self,
+        expr: &Expression,
+        scope: &mut Vec<String>,
+        extra: Vec<String>,
+    ) -> Result<String, ExportError> {
+        let mut head = expr;
+        let mut spine = Vec::new();
+        while let Expression::Application(ref f, ref x) = *head {
+            spine.push(x);
+            head = f;
+        }
+        let mut args = Vec::with_capacity(spine.len() + extra.len());
+        for x in spine.into_iter().rev() {
+            args.push(self.gen(x, scope)?);
+        }
+        args.extend(extra);
+        match *head {
+            Expression::Primitive(num) => {
+                let (ref name, ref tp, _) = self.dsl.primitives[num];
+                let template = self
+                    .templates
+                    .get(name)
+                    .ok_or_else(|| ExportError::MissingTemplate(name.clone()))?;
+                Ok(self.saturate(scope, arity(tp), args, |args| {
+                    self.instantiate(template, args)
+                }))
+            }
+            Expression::Invented(num) => {
+                let name = self.helper(num);
+                Ok(self.saturate(
+                    scope,
+                    arity(&self.dsl.invented[num].1),
+                    args,
+                    |args| match self.target {
+                        ExportTarget::Python => format!("{}({})", name, args.join(", ")),
+                        ExportTarget::Scheme if args.is_empty() => format!("({})", name),
+                        ExportTarget::Scheme => format!("({} {})", name, args.join(" ")),
+                    },
+                ))
+            }
+            Expression::Abstraction(_) => {
+                let mut body = head;
+                let mut params = Vec::new();
+                while let Expression::Abstraction(ref inner) = *body {
+                    let param = self.fresh_name(scope);
+                    scope.push(param.clone());
+                    params.push(param);
+                    body = inner;
+                }
+                let body = self.gen(body, scope);
+                let new_len = scope.len() - params.len();
+                scope.truncate(new_len);
+                let f = self.lambda(&params, &body?);
+                Ok(self.call(f, args))
+            }
+            Expression::Index(i) if i < scope.len() => {
+                let f = scope[scope.len() - 1 - i].clone();
+                Ok(self.call(f, args))
+            }
+            _ => Err(ExportError::NotClosed),
+        }
+    }
+    /// Generates a function of arity-many arguments given the arguments. If there are too many,
+    /// the result is called with the rest. If there are too few, a curried function takes the
+    /// rest.
+    fn saturate<F>(&self, scope: &[String], arity: usize, mut args: Vec<String>, f: F) -> String
+    where
+        F: Fn(&[String]) -> String,
+    {
+        if args.len() >= arity {
+            let rest = args.split_off(arity);
+            self.call(f(&args), rest)
+        } else {
+            let mut names = scope.to_vec();
+            let mut params = Vec::new();
+            while args.len() < arity {
+                let param = self.fresh_name(&names);
+                names.push(param.clone());
+                args.push(param.clone());
+                params.push(param);
+            }
+            self.lambda(&params, &f(&args))
+        }
+    }
+    /// Substitutes each `{i}` in a template with the `i`th argument.
+    fn instantiate(&self, template: &str, args: &[String]) -> String {
+        let mut code = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            code.push_str(&rest[..start]);
+            let arg = rest[start + 1..].find('}').and_then(|end| {
+                rest[start + 1..start + 1 + end]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| args.get(i))
+                    .map(|arg| (arg, start + end + 2))
+            });
+            match arg {
+                Some((arg, next)) => {
+                    code.push_str(arg);
+                    rest = &rest[next..];
+                }
+                None => {
+                    code.push('{');
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        code.push_str(rest);
+        match self.target {
+            ExportTarget::Python if !args.is_empty() => format!("({})", code),
+            _ => code,
+        }
+    }
+    /// A curried function of the parameters.
+    fn lambda(&self, params: &[String], body: &str) -> String {
+        match self.target {
+            ExportTarget::Python => {
+                let params: Vec<_> = params.iter().map(|p| format!("lambda {}: ", p)).collect();
+                format!("({}{})", params.concat(), body)
+            }
+            ExportTarget::Scheme => params.iter().rev().fold(body.to_string(), |body, p| {
+                format!("(lambda ({}) {})", p, body)
+            }),
+        }
+    }
+    /// A curried call of a function with the arguments.
+    fn call(&self, f: String, args: Vec<String>) -> String {
+        args.into_iter().fold(f, |f, arg| match self.target {
+            ExportTarget::Python => format!("{}({})", f, arg),
+            ExportTarget::Scheme => format!("({} {})", f, arg),
+        })
+    }
+    /// A name for a variable which doesn't shadow any of `names` or a definition, and which isn't
+    /// an identifier in any template, so a template's own variables cannot capture it.
+    fn fresh_name(&self, names: &[String]) -> String {
+        const BASES: [&str; 6] = ["x", "y", "z", "u", "v", "w"];
+        (0..)
+            .flat_map(|n| {
+                BASES.iter().map(move |base| {
+                    if n == 0 {
+                        String::from(*base)
+                    } else {
+                        format!("{}{}", base, n)
+                    }
+                })
+            })
+            .find(|name| !names.contains(name) && !self.reserved.contains(name))
+            .unwrap()
+    }
+}
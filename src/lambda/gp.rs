@@ -0,0 +1,359 @@
+use polytype::{Context, Type, TypeSchema};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use super::enumerator;
+use super::{Expression, Language, LinkedList, ParseError};
+use {GPCheckpoint, GP};
+
+/// Parameters for [`Language`] genetic programming ([`GP`]).
+///
+/// Every offspring is well-typed, closed, and free of the language's symmetry violations. When
+/// mutation or crossover fails to produce such an offspring within `max_attempts`, the parent is
+/// reproduced instead. Genesis tries up to `max_attempts` samples for each individual, and one
+/// which fails every attempt is left out, so the initial population may be smaller than requested
+/// (or even empty, if the type is uninhabited within `max_sample_depth`). See [`GP::genesis`].
+///
+/// [`Language`]: struct.Language.html
+/// [`GP`]: ../trait.GP.html
+/// [`GP::genesis`]: ../trait.GP.html#tymethod.genesis
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GeneticParams {
+    /// The progeny factor determines the distribution over subtrees of an expression when a
+    /// subtree is randomly selected. If set to `1`, each subtree has uniform probability of being
+    /// chosen for mutation. If set to `2`, then every parent is half as likely to be chosen than
+    /// any one of its children.
+    pub progeny_factor: f64,
+    /// The maximum depth of applications in expressions sampled for genesis and subtree mutation.
+    pub max_sample_depth: u32,
+    /// The number of times genesis, mutation, or crossover may try to produce a valid expression.
+    pub max_attempts: usize,
+}
+impl Default for GeneticParams {
+    fn default() -> GeneticParams {
+        GeneticParams {
+            progeny_factor: 2f64,
+            max_sample_depth: 4,
+            max_attempts: 20,
+        }
+    }
+}
+
+impl GP for Language {
+    type Expression = Expression;
+    type Params = GeneticParams;
+    type Observation = ();
+
+    fn genesis<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        pop_size: usize,
+        tp: &TypeSchema,
+    ) -> Vec<Self::Expression> {
+        (0..pop_size)
+            .filter_map(|_| {
+                (0..params.max_attempts)
+                    .filter_map(|_| self.sample(tp, params.max_sample_depth, rng))
+                    .next()
+            })
+            .collect()
+    }
+    fn mutate<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        prog: &Self::Expression,
+        _obs: &Self::Observation,
+    ) -> Vec<Self::Expression> {
+        vec![mutate_random_site(self, params, rng, prog)]
+    }
+    fn crossover<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        parent1: &Self::Expression,
+        parent2: &Self::Expression,
+        _obs: &Self::Observation,
+    ) -> Vec<Self::Expression> {
+        vec![
+            crossover_random_site(self, params, rng, parent1, parent2),
+            crossover_random_site(self, params, rng, parent2, parent1),
+        ]
+    }
+    /// The number of primitives, inventions, indices, and abstractions in the expression.
+    fn size(&self, expr: &Self::Expression) -> usize {
+        match *expr {
+            Expression::Application(ref f, ref x) => self.size(f) + self.size(x),
+            Expression::Abstraction(ref body) => 1 + self.size(body),
+            _ => 1,
+        }
+    }
+    /// The nesting of applications in the expression, counting abstractions as free. This matches
+    /// the depth used by [`GeneticParams::max_sample_depth`].
+    ///
+    /// [`GeneticParams::max_sample_depth`]: struct.GeneticParams.html#structfield.max_sample_depth
+    fn depth(&self, expr: &Self::Expression) -> usize {
+        if let Expression::Abstraction(ref body) = *expr {
+            return self.depth(body);
+        }
+        let (_, xs) = uncurry(expr);
+        1 + xs.into_iter().map(|x| self.depth(x)).max().unwrap_or(0)
+    }
+    /// Overlays the two expressions from the root. Any subtrees that differ at their root
+    /// contribute their combined size.
+    fn distance(&self, expr1: &Self::Expression, expr2: &Self::Expression) -> f64 {
+        match (expr1, expr2) {
+            (Expression::Application(f1, x1), Expression::Application(f2, x2)) => {
+                self.distance(f1, f2) + self.distance(x1, x2)
+            }
+            (Expression::Abstraction(body1), Expression::Abstraction(body2)) => {
+                self.distance(body1, body2)
+            }
+            _ if expr1 == expr2 => 0.0,
+            _ => (self.size(expr1) + self.size(expr2)) as f64,
+        }
+    }
+}
+
+impl GPCheckpoint for Language {
+    type ParseError = ParseError;
+    fn display_expression(&self, expr: &Self::Expression) -> String {
+        self.display(expr)
+    }
+    fn parse_expression(&self, s: &str) -> Result<Self::Expression, Self::ParseError> {
+        self.parse(s)
+    }
+}
+
+/// Replaces a random subtree with a newly sampled subtree of the same type.
+fn mutate_random_site<R: Rng>(
+    dsl: &Language,
+    params: &GeneticParams,
+    rng: &mut R,
+    prog: &Expression,
+) -> Expression {
+    let (tp, ctx, sites) = match typed_sites(dsl, prog) {
+        Some(x) => x,
+        None => return prog.clone(),
+    };
+    let dist = site_distribution(params, sites.iter().map(|site| site.depth));
+    for _ in 0..params.max_attempts {
+        let n = dist.sample(rng);
+        let site = &sites[n];
+        let max_depth = params
+            .max_sample_depth
+            .saturating_sub(site.depth as u32)
+            .max(1);
+        let mut ctx = ctx.clone();
+        let site_tp = site.tp.apply(&ctx);
+        if let Some(subtree) =
+            enumerator::sample(dsl, &mut ctx, &site_tp, &site.env, max_depth, rng)
+        {
+            let mut child = prog.clone();
+            replace_site(&mut child, &mut { n }, &subtree);
+            if is_valid_offspring(dsl, &child, &tp) {
+                return child;
+            }
+        }
+    }
+    prog.clone()
+}
+
+/// Replaces a random subtree of `recipient` with a subtree of `donor` whose type unifies.
+fn crossover_random_site<R: Rng>(
+    dsl: &Language,
+    params: &GeneticParams,
+    rng: &mut R,
+    recipient: &Expression,
+    donor: &Expression,
+) -> Expression {
+    let (tp, ctx, sites) = match typed_sites(dsl, recipient) {
+        Some(x) => x,
+        None => return recipient.clone(),
+    };
+    let (donor_ctx, donor_sites) = match typed_sites(dsl, donor) {
+        Some((_, donor_ctx, donor_sites)) => (donor_ctx, donor_sites),
+        None => return recipient.clone(),
+    };
+    let dist = site_distribution(params, sites.iter().map(|site| site.depth));
+    for _ in 0..params.max_attempts {
+        let n = dist.sample(rng);
+        let site = &sites[n];
+        let viables: Vec<_> = donor_sites
+            .iter()
+            .filter(|d| d.expr != site.expr && free_reach(d.expr, 0) <= site.env.len())
+            .filter(|d| {
+                let mut ctx = ctx.clone();
+                let d_tp = d.tp.apply(&donor_ctx).generalize(&[]).instantiate(&mut ctx);
+                ctx.unify(&d_tp, &site.tp).is_ok()
+            })
+            .collect();
+        if viables.is_empty() {
+            continue;
+        }
+        let subtree =
+            viables[site_distribution(params, viables.iter().map(|d| d.depth)).sample(rng)].expr;
+        let mut child = recipient.clone();
+        replace_site(&mut child, &mut { n }, subtree);
+        if is_valid_offspring(dsl, &child, &tp) {
+            return child;
+        }
+    }
+    recipient.clone()
+}
+
+/// A subtree of an expression, along with the type and environment it must be valid under.
+struct Site<'a> {
+    expr: &'a Expression,
+    tp: Type,
+    env: Rc<LinkedList<Type>>,
+    depth: usize,
+}
+
+/// Collects every subtree of an eta-long expression in pre-order, along with the expression's type
+/// and the context in which the sites' types are valid.
+fn typed_sites<'a>(
+    dsl: &Language,
+    expr: &'a Expression,
+) -> Option<(TypeSchema, Context, Vec<Site<'a>>)> {
+    let tp = dsl.infer(expr).ok()?;
+    let mut ctx = Context::default();
+    let request = tp.instantiate(&mut ctx);
+    let env = Rc::new(LinkedList::default());
+    let mut sites = Vec::new();
+    collect_sites(dsl, &mut ctx, &request, &env, expr, 0, &mut sites)?;
+    Some((tp, ctx, sites))
+}
+fn collect_sites<'a>(
+    dsl: &Language,
+    ctx: &mut Context,
+    request: &Type,
+    env: &Rc<LinkedList<Type>>,
+    expr: &'a Expression,
+    depth: usize,
+    sites: &mut Vec<Site<'a>>,
+) -> Option<()> {
+    let request = request.apply(ctx);
+    sites.push(Site {
+        expr,
+        tp: request.clone(),
+        env: env.clone(),
+        depth,
+    });
+    if let Some((arg, ret)) = request.as_arrow() {
+        return if let Expression::Abstraction(ref body) = *expr {
+            let env = LinkedList::prepend(env, arg.clone());
+            collect_sites(dsl, ctx, ret, &env, body, depth + 1, sites)
+        } else {
+            None // not eta-long
+        };
+    }
+    let (f, xs) = uncurry(expr);
+    let f_tp = match *f {
+        Expression::Primitive(i) => dsl.primitives[i].1.instantiate(ctx),
+        Expression::Invented(i) => dsl.invented[i].1.instantiate(ctx),
+        Expression::Index(i) if i < env.len() => env[i].clone(),
+        _ => return None,
+    };
+    let arg_tps: Vec<Type> = xs.iter().map(|_| ctx.new_variable()).collect();
+    let mut tps = arg_tps.clone();
+    tps.push(request.clone());
+    ctx.unify(&f_tp, &Type::from(tps)).ok()?;
+    for (x, x_tp) in xs.into_iter().zip(arg_tps) {
+        collect_sites(dsl, ctx, &x_tp, env, x, depth + 1, sites)?;
+    }
+    Some(())
+}
+
+fn site_distribution<I>(params: &GeneticParams, depths: I) -> WeightedIndex<f64>
+where
+    I: IntoIterator<Item = usize>,
+{
+    WeightedIndex::new(
+        depths
+            .into_iter()
+            .map(|depth| params.progeny_factor.powf(depth as f64)),
+    )
+    .expect("expression has no sites")
+}
+
+/// Replaces the `n`th site, in the order they are collected, with the given replacement.
+fn replace_site(expr: &mut Expression, n: &mut usize, replacement: &Expression) -> bool {
+    if *n == 0 {
+        *expr = replacement.clone();
+        return true;
+    }
+    *n -= 1;
+    if let Expression::Abstraction(ref mut body) = *expr {
+        return replace_site(body, n, replacement);
+    }
+    let mut xs = Vec::new();
+    let mut expr = expr;
+    while let Expression::Application(ref mut f, ref mut x) = *expr {
+        xs.push(&mut **x);
+        expr = f;
+    }
+    xs.into_iter()
+        .rev()
+        .any(|x| replace_site(x, n, replacement))
+}
+
+fn uncurry(mut expr: &Expression) -> (&Expression, Vec<&Expression>) {
+    let mut xs = Vec::new();
+    while let Expression::Application(ref f, ref x) = *expr {
+        xs.push(&**x);
+        expr = f;
+    }
+    xs.reverse();
+    (expr, xs)
+}
+
+fn is_valid_offspring(dsl: &Language, expr: &Expression, tp: &TypeSchema) -> bool {
+    free_reach(expr, 0) == 0
+        && !violates_symmetry(dsl, expr)
+        && dsl
+            .infer(expr)
+            .map(|expr_tp| generalizes(&expr_tp, tp))
+            .unwrap_or(false)
+}
+
+/// How far out does the furthest reaching index go, excluding internal abstractions?
+fn free_reach(expr: &Expression, depth: usize) -> usize {
+    match *expr {
+        Expression::Application(ref f, ref x) => free_reach(f, depth).max(free_reach(x, depth)),
+        Expression::Abstraction(ref body) => free_reach(body, depth + 1),
+        Expression::Index(i) if i >= depth => 1 + i - depth,
+        _ => 0,
+    }
+}
+
+fn violates_symmetry(dsl: &Language, expr: &Expression) -> bool {
+    if let Expression::Abstraction(ref body) = *expr {
+        return violates_symmetry(dsl, body);
+    }
+    let (f, xs) = uncurry(expr);
+    xs.into_iter()
+        .enumerate()
+        .any(|(i, x)| dsl.violates_symmetry(f, i, x) || violates_symmetry(dsl, x))
+}
+
+/// Whether `specific` is an instance of `general`, so that an expression of type `general` may be
+/// used wherever one of type `specific` was.
+fn generalizes(general: &TypeSchema, specific: &TypeSchema) -> bool {
+    let mut ctx = Context::default();
+    let specific = specific.instantiate(&mut ctx);
+    let general = general.instantiate(&mut ctx);
+    if ctx.unify(&general, &specific).is_err() {
+        return false;
+    }
+    let vars = specific.vars();
+    let images: HashSet<Type> = vars
+        .iter()
+        .map(|&v| Type::Variable(v).apply(&ctx))
+        .filter(|tp| matches!(*tp, Type::Variable(_)))
+        .collect();
+    images.len() == vars.len()
+}
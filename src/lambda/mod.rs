@@ -34,29 +34,43 @@
 //! # }
 //! ```
 
+mod bottomup;
 mod compression;
+mod contextual;
 mod enumerator;
 mod eval;
+mod export;
+mod gp;
 mod parser;
+pub use self::bottomup::BottomUpParams;
 pub use self::compression::{induce, CompressionParams, RescoredFrontier};
+pub use self::contextual::{ContextualLanguage, Productions};
 pub use self::eval::{
-    Evaluator, LazyEvaluator, LiftedFunction, LiftedLazyFunction, SimpleEvaluator,
+    CompiledExpression, EvalError, EvalLimits, EvalTrace, Evaluator, LazyEvaluator, LiftedFunction,
+    LiftedLazyFunction, SimpleEvaluator, TraceEvent,
 };
+pub use self::export::{ExportError, ExportTarget};
+pub use self::gp::GeneticParams;
 pub use self::parser::ParseError;
 
 use crossbeam_channel::bounded;
 use polytype::{Context, Type, TypeSchema, UnificationError};
+use rand::Rng;
+use rayon::prelude::*;
 use rayon::spawn;
 use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 use std::f64;
 use std::fmt;
+use std::hash::Hash;
 use std::ops::Index;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Instant;
 
-use {ECFrontier, Task, EC};
+use {ECFrontier, ECParams, Task, EC};
 
+const FIX: &str = "fix";
 const BOUND_VAR_COST: f64 = 0.1;
 const FREE_VAR_COST: f64 = 0.01;
 
@@ -66,6 +80,14 @@ const FREE_VAR_COST: f64 = 0.01;
 pub struct Language {
     pub primitives: Vec<(String, TypeSchema, f64)>,
     pub invented: Vec<(Expression, TypeSchema, f64)>,
+    /// An optional name for each invented expression, at its position in `invented`. Named
+    /// inventions are displayed and parsed as `#name` rather than by their full bodies. This vec
+    /// may be shorter than `invented`, in which case the remaining inventions are unnamed — use
+    /// via [`name_invented`] and [`name_inventions`].
+    ///
+    /// [`name_invented`]: #method.name_invented
+    /// [`name_inventions`]: #method.name_inventions
+    pub invented_names: Vec<Option<String>>,
     pub variable_logprob: f64,
     /// Symmetry breaking prevents certain productions from being made. Specifically, an item `(f,
     /// i, a)` means that enumeration will not yield an application of `f` where the `i`th argument
@@ -87,6 +109,7 @@ impl Language {
         Language {
             primitives,
             invented: vec![],
+            invented_names: vec![],
             variable_logprob: 0f64,
             symmetry_violations: Vec::new(),
         }
@@ -183,6 +206,238 @@ impl Language {
         Box::new(rx.into_iter())
     }
 
+    /// Enumerate completions of a sketch, an expression with [`Hole`]s, for the given request
+    /// type. Holes are filled in order of their joint prior probability, with each completion
+    /// yielded alongside the log-prior of its fillings. The types of holes are inferred from the
+    /// sketch and the request, and are refined as earlier holes are filled. Fillings respect the
+    /// language's symmetry violations. An ill-typed sketch has no completions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// # use programinduction::lambda::Language;
+    /// let dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let sketch = dsl.parse("(λ (+ $0 ?x))").unwrap();
+    /// let exprs: Vec<String> = dsl
+    ///     .enumerate_sketch(sketch, ptp!(@arrow[tp!(int), tp!(int)]))
+    ///     .take(3)
+    ///     .map(|(expr, _log_prior)| dsl.display(&expr))
+    ///     .collect();
+    ///
+    /// assert_eq!(
+    ///     exprs,
+    ///     vec!["(λ (+ $0 0))", "(λ (+ $0 1))", "(λ (+ $0 $0))"]
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`Hole`]: enum.Expression.html#variant.Hole
+    pub fn enumerate_sketch(
+        &self,
+        sketch: Expression,
+        tp: TypeSchema,
+    ) -> Box<dyn Iterator<Item = (Expression, f64)>> {
+        let (tx, rx) = bounded(1);
+        let dsl = self.clone();
+        spawn(move || {
+            let tx = tx.clone();
+            let termination_condition = |expr, logprior| tx.send((expr, logprior)).is_err();
+            enumerator::run_sketch(&dsl, &sketch, tp, termination_condition)
+        });
+        Box::new(rx.into_iter())
+    }
+
+    /// Enumerate expressions for a request type bottom-up, from the values they take on the given
+    /// inputs, calling `termination_condition` with each expression and its log-prior until it
+    /// returns `true`.
+    ///
+    /// Starting from the inputs and constants, larger programs are built by applying primitives
+    /// and inventions to programs already in a _bank_, in order of size. A program is discarded
+    /// if another program of the same type and no greater size is _observationally equivalent_
+    /// to it, i.e. evaluates to the same values on every input. Because of this, only one
+    /// expression is produced for each distinct behavior on the inputs, and the bank is keyed by
+    /// a hash of those values. Programs which fail to evaluate on any input within
+    /// [`eval_limits`] are discarded, as are applications with symmetry violations.
+    ///
+    /// Only first-order programs are built: primitives and inventions which take functions as
+    /// arguments, and inputs which are functions, are not used. Enumeration stops once programs
+    /// reach [`max_size`] or the bank reaches [`max_bank_size`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// use programinduction::lambda::{BottomUpParams, Language, SimpleEvaluator};
+    ///
+    /// fn evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
+    ///     match name {
+    ///         "0" => Ok(0),
+    ///         "1" => Ok(1),
+    ///         "+" => Ok(inps[0] + inps[1]),
+    ///         _ => unreachable!(),
+    ///     }
+    /// }
+    ///
+    /// let dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let inputs = vec![vec![2], vec![5]];
+    /// let mut exprs = Vec::new();
+    /// dsl.enumerate_bottom_up(
+    ///     &BottomUpParams::default(),
+    ///     SimpleEvaluator::of(evaluate),
+    ///     &ptp!(@arrow[tp!(int), tp!(int)]),
+    ///     &inputs,
+    ///     |expr, _log_prior| {
+    ///         exprs.push(dsl.display(&expr));
+    ///         exprs.len() == 5
+    ///     },
+    /// );
+    ///
+    /// // (+ 0 $0) is equivalent to $0, and (+ 1 $0) to (+ $0 1)
+    /// assert_eq!(
+    ///     exprs,
+    ///     vec!["(λ $0)", "(λ 0)", "(λ 1)", "(λ (+ $0 $0))", "(λ (+ $0 1))"]
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`max_size`]: struct.BottomUpParams.html#structfield.max_size
+    /// [`max_bank_size`]: struct.BottomUpParams.html#structfield.max_bank_size
+    /// [`eval_limits`]: struct.BottomUpParams.html#structfield.eval_limits
+    pub fn enumerate_bottom_up<E, F>(
+        &self,
+        params: &BottomUpParams,
+        evaluator: E,
+        tp: &TypeSchema,
+        inputs: &[Vec<E::Space>],
+        termination_condition: F,
+    ) where
+        E: Evaluator,
+        E::Space: Hash,
+        F: FnMut(Expression, f64) -> bool,
+    {
+        let evaluator = Arc::new(evaluator);
+        bottomup::run(
+            self,
+            params,
+            &evaluator,
+            tp,
+            inputs,
+            None,
+            termination_condition,
+        )
+    }
+
+    /// Like [`EC::explore`], but searches bottom-up with [`enumerate_bottom_up`] on the inputs of
+    /// each task's examples, so it can serve as an alternative search strategy in the EC
+    /// algorithm. Tasks are those made with [`task_by_evaluation`], whose observations are their
+    /// examples, and are explored in parallel.
+    ///
+    /// Each task's frontier holds at most [`frontier_limit`] solutions. Solutions with a
+    /// description length exceeding [`search_limit_description_length`] are ignored, and a task's
+    /// search stops once [`search_limit_timeout`] has elapsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// use programinduction::domains::strings;
+    /// use programinduction::lambda::{task_by_evaluation, BottomUpParams};
+    /// use programinduction::ECParams;
+    ///
+    /// let dsl = strings::dsl();
+    /// let examples = vec![(
+    ///     vec![strings::Space::Str("OFJQc>BLVP>eMS".to_string())],
+    ///     strings::Space::Str("OFJQc/BLVP/eMS".to_string()),
+    /// )];
+    /// let task = task_by_evaluation(
+    ///     strings::Evaluator,
+    ///     ptp!(@arrow[tp!(str), tp!(str)]),
+    ///     &examples,
+    /// );
+    /// let ec_params = ECParams {
+    ///     frontier_limit: 1,
+    ///     search_limit_timeout: None,
+    ///     search_limit_description_length: None,
+    /// };
+    ///
+    /// let frontiers =
+    ///     dsl.explore_bottom_up(&ec_params, &BottomUpParams::default(), strings::Evaluator, &[task]);
+    /// let solution = &frontiers[0].best_solution().expect("could not solve").0;
+    /// assert_eq!(
+    ///     dsl.display(solution),
+    ///     "(λ (join (char->str /) (split > $0)))"
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`EC::explore`]: ../trait.EC.html#method.explore
+    /// [`enumerate_bottom_up`]: #method.enumerate_bottom_up
+    /// [`task_by_evaluation`]: fn.task_by_evaluation.html
+    /// [`frontier_limit`]: ../struct.ECParams.html#structfield.frontier_limit
+    /// [`search_limit_description_length`]: ../struct.ECParams.html#structfield.search_limit_description_length
+    /// [`search_limit_timeout`]: ../struct.ECParams.html#structfield.search_limit_timeout
+    #[allow(clippy::type_complexity)]
+    pub fn explore_bottom_up<E, V>(
+        &self,
+        ec_params: &ECParams,
+        params: &BottomUpParams,
+        evaluator: E,
+        tasks: &[Task<Language, Expression, &[(Vec<V>, V)]>],
+    ) -> Vec<ECFrontier<Language>>
+    where
+        E: Evaluator<Space = V> + Send,
+        V: Clone + PartialEq + Hash + Send + Sync,
+    {
+        let evaluator = Arc::new(evaluator);
+        tasks
+            .par_iter()
+            .map(|task| {
+                let deadline = ec_params
+                    .search_limit_timeout
+                    .map(|timeout| Instant::now() + timeout);
+                let inputs: Vec<_> = task.observation.iter().map(|ex| ex.0.clone()).collect();
+                let mut frontier = ECFrontier::default();
+                bottomup::run(
+                    self,
+                    params,
+                    &evaluator,
+                    &task.tp,
+                    &inputs,
+                    deadline,
+                    |expr, logprior| {
+                        let within_dl = match ec_params.search_limit_description_length {
+                            Some(dl) => -logprior <= dl,
+                            None => true,
+                        };
+                        if within_dl {
+                            let l = (task.oracle)(self, &expr);
+                            if l.is_finite() {
+                                frontier.push(expr, logprior, l);
+                            }
+                        }
+                        frontier.len() >= ec_params.frontier_limit
+                    },
+                );
+                frontier
+            })
+            .collect()
+    }
+
     /// Update production probabilities and induce new primitives, with the guarantee that any
     /// changes to the language yield net lower prior probability for expressions in the frontier.
     ///
@@ -230,7 +485,9 @@ impl Language {
 
     /// Evaluate an expressions based on an input/output pair.
     ///
-    /// Inputs are given as a sequence representing sequentially applied arguments.
+    /// Inputs are given as a sequence representing sequentially applied arguments. A sketch
+    /// which reaches a [`Hole`] fails with the evaluator's [`limit_error`], so use
+    /// [`eval_with_limits`] to get an [`EvalError`] for it instead.
     ///
     /// # Examples
     ///
@@ -262,6 +519,11 @@ impl Language {
     /// assert_eq!(evaluated, 8);
     /// # }
     /// ```
+    ///
+    /// [`Hole`]: enum.Expression.html#variant.Hole
+    /// [`limit_error`]: trait.Evaluator.html#tymethod.limit_error
+    /// [`eval_with_limits`]: #method.eval_with_limits
+    /// [`EvalError`]: enum.EvalError.html
     pub fn eval<V, E>(&self, expr: &Expression, evaluator: E, inps: &[V]) -> Result<V, E::Error>
     where
         V: Clone + PartialEq + Send + Sync,
@@ -320,6 +582,167 @@ impl Language {
         eval::lazy_eval(self, expr, evaluator, inps)
     }
 
+    /// Compiles an expression for repeated evaluation, such as over every example of a task.
+    /// See [`CompiledExpression`] for more.
+    ///
+    /// [`CompiledExpression`]: struct.CompiledExpression.html
+    pub fn compile<V>(&self, expr: &Expression) -> CompiledExpression<V>
+    where
+        V: Clone + PartialEq + Send + Sync,
+    {
+        eval::compile(self, expr)
+    }
+
+    /// Like [`eval`], but evaluation stops with an [`EvalError`] once any of the given
+    /// [`EvalLimits`] is exhausted, rather than running indefinitely or overflowing the stack.
+    /// Reductions made by [`LiftedFunction`]s count towards the same limits. Evaluation of a sketch
+    /// which reaches a [`Hole`] stops with `EvalError::Hole`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate polytype;
+    /// # extern crate programinduction;
+    /// use programinduction::lambda::{EvalError, EvalLimits, Language, SimpleEvaluator};
+    ///
+    /// fn evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
+    ///     match name {
+    ///         "0" => Ok(0),
+    ///         "1" => Ok(1),
+    ///         "+" => Ok(inps[0] + inps[1]),
+    ///         _ => unreachable!(),
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let expr = dsl.parse("(λ (+ (+ 1 $0) (+ 1 $0)))").unwrap();
+    ///
+    /// let limits = EvalLimits {
+    ///     fuel: Some(100),
+    ///     ..Default::default()
+    /// };
+    /// let evaluated = dsl.eval_with_limits(&expr, SimpleEvaluator::of(evaluate), &[2], &limits);
+    /// assert_eq!(evaluated, Ok(6));
+    ///
+    /// let limits = EvalLimits {
+    ///     fuel: Some(3),
+    ///     ..Default::default()
+    /// };
+    /// let evaluated = dsl.eval_with_limits(&expr, SimpleEvaluator::of(evaluate), &[2], &limits);
+    /// assert_eq!(evaluated, Err(EvalError::OutOfFuel));
+    /// # }
+    /// ```
+    ///
+    /// [`eval`]: #method.eval
+    /// [`EvalError`]: enum.EvalError.html
+    /// [`EvalLimits`]: struct.EvalLimits.html
+    /// [`LiftedFunction`]: struct.LiftedFunction.html
+    /// [`Hole`]: enum.Expression.html#variant.Hole
+    pub fn eval_with_limits<V, E>(
+        &self,
+        expr: &Expression,
+        evaluator: E,
+        inps: &[V],
+        limits: &EvalLimits,
+    ) -> Result<V, EvalError<E::Error>>
+    where
+        V: Clone + PartialEq + Send + Sync,
+        E: Evaluator<Space = V>,
+    {
+        eval::eval_with_limits(self, expr, &Arc::new(evaluator), inps, limits)
+    }
+
+    /// Like [`eval_with_limits`], but for lazy evaluation with a [`LazyEvaluator`].
+    ///
+    /// [`eval_with_limits`]: #method.eval_with_limits
+    /// [`LazyEvaluator`]: trait.LazyEvaluator.html
+    pub fn lazy_eval_with_limits<V, E>(
+        &self,
+        expr: &Expression,
+        evaluator: E,
+        inps: &[V],
+        limits: &EvalLimits,
+    ) -> Result<V, EvalError<E::Error>>
+    where
+        V: Clone + PartialEq + Send + Sync,
+        E: LazyEvaluator<Space = V>,
+    {
+        eval::lazy_eval_with_limits(self, expr, &Arc::new(evaluator), inps, limits)
+    }
+
+    /// Like [`eval`], but also returns an [`EvalTrace`] of every call to the evaluator and to
+    /// lifted functions, which helps to debug an expression that gives the wrong output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate polytype;
+    /// # extern crate programinduction;
+    /// use programinduction::lambda::{Language, SimpleEvaluator};
+    ///
+    /// fn evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
+    ///     match name {
+    ///         "1" => Ok(1),
+    ///         "+" => Ok(inps[0] + inps[1]),
+    ///         _ => unreachable!(),
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let dsl = Language::uniform(vec![
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let expr = dsl.parse("(λ (+ (+ 1 $0) 1))").unwrap();
+    ///
+    /// let (evaluated, trace) = dsl.eval_with_trace(&expr, SimpleEvaluator::of(evaluate), &[2]);
+    /// assert_eq!(evaluated, Ok(4));
+    /// assert_eq!(
+    ///     trace.to_string(),
+    ///     "1 = 1\n(+ 1 2) = 3\n1 = 1\n(+ 3 1) = 4\n",
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`eval`]: #method.eval
+    /// [`EvalTrace`]: struct.EvalTrace.html
+    pub fn eval_with_trace<V, E>(
+        &self,
+        expr: &Expression,
+        evaluator: E,
+        inps: &[V],
+    ) -> (Result<V, E::Error>, EvalTrace<V>)
+    where
+        V: Clone + PartialEq + Send + Sync,
+        E: Evaluator<Space = V>,
+    {
+        eval::eval_with_trace(self, expr, &Arc::new(evaluator), inps)
+    }
+
+    /// Like [`eval_with_trace`], but for lazy evaluation with a [`LazyEvaluator`].
+    ///
+    /// [`eval_with_trace`]: #method.eval_with_trace
+    /// [`LazyEvaluator`]: trait.LazyEvaluator.html
+    pub fn lazy_eval_with_trace<V, E>(
+        &self,
+        expr: &Expression,
+        evaluator: E,
+        inps: &[V],
+    ) -> (Result<V, E::Error>, EvalTrace<V>)
+    where
+        V: Clone + PartialEq + Send + Sync,
+        E: LazyEvaluator<Space = V>,
+    {
+        eval::lazy_eval_with_trace(self, expr, &Arc::new(evaluator), inps)
+    }
+
     /// Get the log-likelihood of an expression normalized with other expressions with the given
     /// request type.
     ///
@@ -349,6 +772,47 @@ impl Language {
         enumerator::likelihood(self, request, expr)
     }
 
+    /// Sample an expression for the request according to the production log-probabilities of
+    /// primitives, inventions, and variables, respecting [`symmetry_violations`].
+    ///
+    /// Applications are nested at most `max_depth` deep (abstractions do not count towards depth),
+    /// so sampling always terminates. Returns `None` if no expression of the requested type could
+    /// be sampled within that depth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # extern crate rand;
+    /// # fn main() {
+    /// # use programinduction::lambda::Language;
+    /// let mut dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// dsl.add_symmetry_violation(2, 0, 0);
+    /// let req = ptp!(@arrow[tp!(int), tp!(int)]);
+    ///
+    /// let expr = dsl.sample(&req, 4, &mut rand::thread_rng()).unwrap();
+    /// assert!(!dsl.display(&expr).contains("(+ 0"));
+    /// # }
+    /// ```
+    ///
+    /// [`symmetry_violations`]: #structfield.symmetry_violations
+    pub fn sample<R: Rng>(
+        &self,
+        request: &TypeSchema,
+        max_depth: u32,
+        rng: &mut R,
+    ) -> Option<Expression> {
+        let mut ctx = Context::default();
+        let request = request.instantiate(&mut ctx);
+        let env = Rc::new(LinkedList::default());
+        enumerator::sample(self, &mut ctx, &request, &env, max_depth, rng)
+    }
+
     /// Register a new invented expression. If it has a valid type, this will be `Ok(num)`.
     ///
     /// # Examples
@@ -377,10 +841,255 @@ impl Language {
         log_probability: f64,
     ) -> Result<usize, InferenceError> {
         let tp = self.infer(&expr)?;
+        // a name left behind by an invention removed from `invented` is not inherited
+        self.invented_names.truncate(self.invented.len());
         self.invented.push((expr, tp, log_probability));
         Ok(self.invented.len() - 1)
     }
 
+    /// Remove every invented expression that is not used by the given frontiers, either directly
+    /// or within another invention that is used. Remaining inventions are renumbered, and
+    /// expressions in the frontiers are rewritten accordingly. Log-priors in the frontiers are not
+    /// recomputed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # use programinduction::lambda::{Expression, Language};
+    /// # use programinduction::ECFrontier;
+    /// # fn main() {
+    /// let mut dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// dsl.invent(dsl.parse("(+ 0)").unwrap(), -0.5).unwrap();
+    /// dsl.invent(dsl.parse("(+ 1)").unwrap(), -0.5).unwrap();
+    ///
+    /// let expr = dsl.parse("(#(+ 1) 1)").unwrap();
+    /// let mut frontiers = vec![ECFrontier(vec![(expr, -2.0, 0.0)])];
+    /// dsl.prune_invented(&mut frontiers);
+    ///
+    /// assert_eq!(dsl.invented.len(), 1);
+    /// assert_eq!(dsl.display(&frontiers[0][0].0), "(#(+ 1) 1)");
+    /// assert_eq!(
+    ///     frontiers[0][0].0,
+    ///     Expression::Application(
+    ///         Box::new(Expression::Invented(0)),
+    ///         Box::new(Expression::Primitive(1)),
+    ///     )
+    /// );
+    /// # }
+    /// ```
+    pub fn prune_invented(&mut self, frontiers: &mut [ECFrontier<Language>]) {
+        let (_, used_invented) = self.used(frontiers);
+        let used_primitives = vec![true; self.primitives.len()];
+        self.retain(frontiers, &used_primitives, &used_invented)
+    }
+
+    /// Like [`prune_invented`], but also removes every primitive that is not used by the given
+    /// frontiers or by a remaining invention. Remaining primitives are renumbered, along with
+    /// [`symmetry_violations`].
+    ///
+    /// [`prune_invented`]: #method.prune_invented
+    /// [`symmetry_violations`]: #structfield.symmetry_violations
+    pub fn prune(&mut self, frontiers: &mut [ECFrontier<Language>]) {
+        let (used_primitives, used_invented) = self.used(frontiers);
+        self.retain(frontiers, &used_primitives, &used_invented)
+    }
+
+    /// Which primitives and inventions are used by the frontiers, including within used
+    /// inventions.
+    fn used(&self, frontiers: &[ECFrontier<Language>]) -> (Vec<bool>, Vec<bool>) {
+        let mut used_primitives = vec![false; self.primitives.len()];
+        let mut used_invented = vec![false; self.invented.len()];
+        for &(ref expr, _, _) in frontiers.iter().flat_map(|f| f.iter()) {
+            expr.mark_used(&mut used_primitives, &mut used_invented);
+        }
+        // inventions only refer to earlier inventions
+        for i in (0..self.invented.len()).rev() {
+            if used_invented[i] {
+                self.invented[i]
+                    .0
+                    .mark_used(&mut used_primitives, &mut used_invented);
+            }
+        }
+        (used_primitives, used_invented)
+    }
+
+    /// Removes the primitives and inventions which aren't used, renumbering everything that refers
+    /// to those which remain.
+    fn retain(
+        &mut self,
+        frontiers: &mut [ECFrontier<Language>],
+        used_primitives: &[bool],
+        used_invented: &[bool],
+    ) {
+        fn renumbering(used: &[bool]) -> Vec<Option<usize>> {
+            let mut n = 0;
+            used.iter()
+                .map(|&used| {
+                    if used {
+                        n += 1;
+                        Some(n - 1)
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        }
+        let primitives = renumbering(used_primitives);
+        let invented = renumbering(used_invented);
+
+        let mut i = 0;
+        self.primitives.retain(|_| {
+            i += 1;
+            used_primitives[i - 1]
+        });
+        let mut i = 0;
+        self.invented.retain(|_| {
+            i += 1;
+            used_invented[i - 1]
+        });
+        for inv in &mut self.invented {
+            inv.0.renumber(&primitives, &invented);
+        }
+        let mut i = 0;
+        self.invented_names.retain(|_| {
+            i += 1;
+            used_invented.get(i - 1) == Some(&true)
+        });
+        for &mut (ref mut expr, _, _) in frontiers.iter_mut().flat_map(|f| f.iter_mut()) {
+            expr.renumber(&primitives, &invented);
+        }
+        let mut symmetry_violations: Vec<_> = self
+            .symmetry_violations
+            .iter()
+            .filter_map(|&(f, i, a)| match (primitives[f], primitives[a]) {
+                (Some(f), Some(a)) => Some((f, i, a)),
+                _ => None,
+            })
+            .collect();
+        symmetry_violations.sort();
+        self.symmetry_violations = symmetry_violations;
+    }
+
+    /// Give an invented expression a name, so it is displayed and parsed as `#name` rather than by
+    /// its full body. Names may contain any characters other than whitespace and parentheses.
+    /// The name is stored in [`invented_names`], and stays with the invention when inventions are
+    /// pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # use programinduction::lambda::{Language, NameError};
+    /// # fn main() {
+    /// let mut dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let inc = dsl.invent(dsl.parse("(+ 1)").unwrap(), -0.5).unwrap();
+    /// dsl.invent(dsl.parse("(λ (#(+ 1) (#(+ 1) $0)))").unwrap(), -0.5).unwrap();
+    /// dsl.name_invented(inc, "inc").unwrap();
+    /// dsl.name_inventions();
+    ///
+    /// let expr = dsl.parse("(#f1 (#inc 0))").unwrap();
+    /// assert_eq!(dsl.display(&expr), "(#f1 (#inc 0))");
+    /// assert_eq!(
+    ///     dsl.display_library(),
+    ///     "#inc = (+ 1) : int → int\n#f1 = (λ (#inc (#inc $0))) : int → int\n",
+    /// );
+    /// assert_eq!(
+    ///     dsl.name_invented(1, "inc"),
+    ///     Err(NameError::NameInUse(String::from("inc"))),
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`invented_names`]: #structfield.invented_names
+    pub fn name_invented(&mut self, num: usize, name: &str) -> Result<(), NameError> {
+        if num >= self.invented.len() {
+            return Err(NameError::InvalidInvention(num));
+        }
+        if name.is_empty()
+            || name
+                .chars()
+                .any(|c| c.is_whitespace() || c == '(' || c == ')')
+        {
+            return Err(NameError::InvalidName(String::from(name)));
+        }
+        match self.invented_by_name(name) {
+            Some(other) if other != num => Err(NameError::NameInUse(String::from(name))),
+            _ => {
+                self.set_invented_name(num, String::from(name));
+                Ok(())
+            }
+        }
+    }
+
+    /// Give every unnamed invented expression a generated name like `f3`, where `3` is its
+    /// position in `invented`. See [`name_invented`].
+    ///
+    /// [`name_invented`]: #method.name_invented
+    pub fn name_inventions(&mut self) {
+        for num in 0..self.invented.len() {
+            if self.invented_name(num).is_some() {
+                continue;
+            }
+            let mut name = format!("f{}", num);
+            while self.invented_by_name(&name).is_some() {
+                name.push('\'');
+            }
+            self.set_invented_name(num, name);
+        }
+    }
+
+    fn set_invented_name(&mut self, num: usize, name: String) {
+        if self.invented_names.len() <= num {
+            self.invented_names.resize(num + 1, None);
+        }
+        self.invented_names[num] = Some(name);
+    }
+
+    /// The name of an invented expression, if it has one. See [`name_invented`].
+    ///
+    /// [`name_invented`]: #method.name_invented
+    pub fn invented_name(&self, num: usize) -> Option<&str> {
+        if num < self.invented.len() {
+            self.invented_names.get(num)?.as_ref().map(String::as_str)
+        } else {
+            None
+        }
+    }
+
+    /// The position in `invented` of the invented expression with the given name, if there is
+    /// one. See [`name_invented`].
+    ///
+    /// [`name_invented`]: #method.name_invented
+    pub fn invented_by_name(&self, name: &str) -> Option<usize> {
+        (0..self.invented.len()).find(|&num| self.invented_name(num) == Some(name))
+    }
+
+    /// A listing of every invented expression in order, one per line, with its name if it has
+    /// one, its body, and its type.
+    pub fn display_library(&self) -> String {
+        self.invented
+            .iter()
+            .enumerate()
+            .map(
+                |(num, &(ref expr, ref tp, _))| match self.invented_name(num) {
+                    Some(name) => format!("#{} = {} : {}\n", name, self.display(expr), tp),
+                    None => format!("#{} : {}\n", self.display(expr), tp),
+                },
+            )
+            .collect()
+    }
+
     /// Introduce a symmetry-breaking pattern to the Language.
     ///
     /// # Examples
@@ -426,7 +1135,10 @@ impl Language {
             self.symmetry_violations.insert(i, x)
         }
     }
-    /// Check whether expressions break symmetry.
+    /// Check whether expressions break symmetry. This includes the built-in [`fix_primitive`] given
+    /// a function which trivially never terminates.
+    ///
+    /// [`fix_primitive`]: fn.fix_primitive.html
     ///
     /// # Examples
     ///
@@ -452,33 +1164,123 @@ impl Language {
     /// assert!(!dsl.violates_symmetry(f, 1, x));
     /// # }
     /// ```
-    pub fn violates_symmetry(&self, f: &Expression, index: usize, x: &Expression) -> bool {
-        match (f, x) {
-            (&Expression::Primitive(f), &Expression::Primitive(x)) => {
-                let x = (f, index, x);
-                self.symmetry_violations.binary_search(&x).is_ok()
-            }
-            (&Expression::Primitive(f), &Expression::Application(ref x, _)) => {
-                let mut z: &Expression = &**x;
-                while let Expression::Application(ref x, _) = *z {
-                    z = x
-                }
-                if let Expression::Primitive(x) = *z {
-                    let x = (f, index, x);
-                    self.symmetry_violations.binary_search(&x).is_ok()
-                } else {
-                    false
-                }
-            }
+    pub fn violates_symmetry(&self, f: &Expression, index: usize, x: &Expression) -> bool {
+        match (f, x) {
+            (&Expression::Primitive(f), &Expression::Primitive(x)) => {
+                let x = (f, index, x);
+                self.symmetry_violations.binary_search(&x).is_ok()
+            }
+            (&Expression::Primitive(f), &Expression::Application(ref x, _)) => {
+                let mut z: &Expression = x;
+                while let Expression::Application(ref x, _) = *z {
+                    z = x
+                }
+                if let Expression::Primitive(x) = *z {
+                    let x = (f, index, x);
+                    self.symmetry_violations.binary_search(&x).is_ok()
+                } else {
+                    false
+                }
+            }
+            (&Expression::Primitive(f), x) if index == 0 && self.is_fix(f) => fix_diverges(x),
+            _ => false,
+        }
+    }
+
+    /// Whether a primitive is the built-in [`fix_primitive`].
+    ///
+    /// [`fix_primitive`]: fn.fix_primitive.html
+    fn is_fix(&self, num: usize) -> bool {
+        match self.primitives.get(num) {
+            Some((name, tp, _)) => name == FIX && *tp == fix_primitive().1,
+            None => false,
+        }
+    }
+
+    /// Remove all invented expressions by pulling out their underlying expressions.
+    pub fn strip_invented(&self, expr: &Expression) -> Expression {
+        expr.strip_invented(&self.invented)
+    }
+
+    /// The canonical form of a closed expression: inventions are stripped (see
+    /// [`strip_invented`]), the result is beta-normalized within `max_steps` reductions (see
+    /// [`Expression::beta_normalize`]), and then put in eta-long form (see
+    /// [`Expression::etalong`]). Returns `None` if the expression has no normal form within
+    /// `max_steps` or is ill-typed.
+    ///
+    /// Because canonical forms only refer to primitives, they can be compared across languages
+    /// which share primitives, such as different iterations of a DSL under compression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// # use programinduction::lambda::Language;
+    /// let mut dsl = Language::uniform(vec![
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// dsl.invent(dsl.parse("(+ 1)").unwrap(), -0.5).unwrap();
+    ///
+    /// let expr = dsl.parse("(λ ((λ (#(+ 1) $0)) (#(+ 1) $0)))").unwrap();
+    /// let canonical = dsl.canonicalize(&expr, 100).unwrap();
+    /// assert_eq!(dsl.display(&canonical), "(λ (+ 1 (+ 1 $0)))");
+    /// # }
+    /// ```
+    ///
+    /// [`strip_invented`]: #method.strip_invented
+    /// [`Expression::beta_normalize`]: enum.Expression.html#method.beta_normalize
+    /// [`Expression::etalong`]: enum.Expression.html#method.etalong
+    pub fn canonicalize(&self, expr: &Expression, max_steps: usize) -> Option<Expression> {
+        let mut expr = self.strip_invented(expr);
+        if expr.beta_normalize(max_steps) && expr.etalong(self) {
+            Some(expr)
+        } else {
+            None
+        }
+    }
+
+    /// Whether two closed expressions are equivalent modulo inventions, beta-reduction, and
+    /// eta-conversion, i.e. whether they have the same [`canonicalize`]d form. Expressions without
+    /// a canonical form within `max_steps` reductions are never equivalent.
+    ///
+    /// This is useful for deduplicating frontiers, whose expressions often differ only in their
+    /// use of inventions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// # use programinduction::lambda::Language;
+    /// let mut dsl = Language::uniform(vec![
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// dsl.invent(dsl.parse("(+ 1)").unwrap(), -0.5).unwrap();
+    ///
+    /// let a = dsl.parse("#(+ 1)").unwrap();
+    /// let b = dsl.parse("(λ ((λ (+ $0)) 1 $0))").unwrap();
+    /// let c = dsl.parse("(λ (+ $0 1))").unwrap();
+    /// assert!(dsl.equivalent(&a, &b, 100));
+    /// assert!(!dsl.equivalent(&a, &c, 100));
+    /// # }
+    /// ```
+    ///
+    /// [`canonicalize`]: #method.canonicalize
+    pub fn equivalent(&self, a: &Expression, b: &Expression, max_steps: usize) -> bool {
+        match (
+            self.canonicalize(a, max_steps),
+            self.canonicalize(b, max_steps),
+        ) {
+            (Some(a), Some(b)) => a == b,
             _ => false,
         }
     }
 
-    /// Remove all invented expressions by pulling out their underlying expressions.
-    pub fn strip_invented(&self, expr: &Expression) -> Expression {
-        expr.strip_invented(&self.invented)
-    }
-
     /// A cheap function used as the objective for dsl compression. See
     /// [`lambda::CompressionParams`] for details.
     ///
@@ -513,10 +1315,14 @@ impl Language {
     /// The inverse of [`display`].
     ///
     /// Lambda expressions take the form `(lambda BODY)` or `(λ BODY)`, where BODY is an expression
-    /// that may use a corresponding De Bruijn [`Index`].
+    /// that may use a corresponding De Bruijn [`Index`]. An invented expression is written `#BODY`,
+    /// or `#NAME` if it was named with [`name_invented`]. A [`Hole`] in a sketch is written `?`,
+    /// optionally followed by a label for readability such as `?f`; labels are not retained.
     ///
     /// [`display`]: #method.display
     /// [`Index`]: enum.Expression.html#variant.Index
+    /// [`Hole`]: enum.Expression.html#variant.Hole
+    /// [`name_invented`]: #method.name_invented
     pub fn parse(&self, inp: &str) -> Result<Expression, ParseError> {
         parser::parse(self, inp)
     }
@@ -527,17 +1333,174 @@ impl Language {
         expr.show(self, false)
     }
 
-    /// Like `display`, but in a format ready for lisp interpretation.
+    /// Like [`parse`], but with named binders rather than De Bruijn indices, e.g.
+    /// `(lambda (x y) (+ x y))` or `(λ (x y) (+ x y))`.
+    ///
+    /// A variable refers to the innermost binder of the same name, and otherwise to a primitive.
+    /// Binders may be annotated with a type, as in `(λ ((xs : list(int)) n) (+ n (len xs)))`,
+    /// and annotations are checked with [`infer`] but not retained in the expression. Invented
+    /// expressions are written `#NAME` or as `#BODY` in this syntax, holes as `?`, and free
+    /// variables as `$0`, `$1`, etc.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # use programinduction::lambda::Language;
+    /// # fn main() {
+    /// let dsl = Language::uniform(vec![
+    ///     ("0", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ///     ("len", ptp!(0; @arrow[tp!(list(tp!(0))), tp!(int)])),
+    /// ]);
+    /// let expr = dsl.parse_named("(λ (x y) (+ x y))").unwrap();
+    /// assert_eq!(expr, dsl.parse("(λ (λ (+ $1 $0)))").unwrap());
+    ///
+    /// // type annotations are checked
+    /// assert!(dsl.parse_named("(λ ((xs : list(bool)) n) (+ n (len xs)))").is_ok());
+    /// assert!(dsl.parse_named("(λ ((xs : int)) (len xs))").is_err());
+    /// # }
+    /// ```
+    ///
+    /// [`parse`]: #method.parse
+    /// [`infer`]: #method.infer
+    pub fn parse_named(&self, inp: &str) -> Result<Expression, ParseError> {
+        parser::parse_named(self, inp)
+    }
+    /// The inverse of [`parse_named`], which chooses fresh names for binders. For any
+    /// expression `expr`, `dsl.parse_named(&dsl.display_named(&expr))` gives back `expr`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # use programinduction::lambda::Language;
+    /// # fn main() {
+    /// let dsl = Language::uniform(vec![
+    ///     ("x", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    /// let expr = dsl.parse("(λ (+ $0 ((λ (+ $1 $0)) x)))").unwrap();
+    /// let named = dsl.display_named(&expr);
+    /// assert_eq!(named, "(λ (y) (+ y ((λ (z) (+ y z)) x)))");
+    /// assert_eq!(dsl.parse_named(&named).unwrap(), expr);
+    /// # }
+    /// ```
+    ///
+    /// [`parse_named`]: #method.parse_named
+    pub fn display_named(&self, expr: &Expression) -> String {
+        expr.show_named(self, &mut Vec::new(), false)
+    }
+
+    /// Like `display`, but in a format ready for lisp interpretation. Named inventions are written
+    /// by their names, which are subject to `conversions` like primitives; others are inlined.
     pub fn lispify(&self, expr: &Expression, conversions: &HashMap<String, String>) -> String {
         expr.as_lisp(self, false, conversions, 0)
     }
 
+    /// Generate standalone code for a closed expression as a function with the given name, which
+    /// takes an argument for every argument of the expression's type. Inventions used by the
+    /// expression become helper functions, named by their [`invented_name`] or like `f3`. Helper
+    /// names are suffixed with `_` where they would clash with each other, with the function, or
+    /// with an identifier in a template; a function name that clashes with an identifier in a
+    /// template is an error.
+    ///
+    /// Every primitive needs a template of code in the target language, where `{0}`, `{1}`, etc.
+    /// are replaced by its arguments. Functions that are values, such as an argument to a
+    /// primitive like `map`, are curried: they take one argument at a time. Generated variables
+    /// never use an identifier from a template, so variables bound within a template, like `acc`
+    /// in `lambda acc, x: {0}(x)(acc)`, cannot capture them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # use programinduction::lambda::{ExportTarget, Language};
+    /// # use std::collections::HashMap;
+    /// # fn main() {
+    /// let mut dsl = Language::uniform(vec![
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ///     ("map", ptp!(0, 1; @arrow[
+    ///         tp!(@arrow[tp!(0), tp!(1)]),
+    ///         tp!(list(tp!(0))),
+    ///         tp!(list(tp!(1))),
+    ///     ])),
+    /// ]);
+    /// let incr = dsl.invent(dsl.parse("(+ 1)").unwrap(), -0.5).unwrap();
+    /// dsl.name_invented(incr, "incr").unwrap();
+    /// let expr = dsl.parse("(λ (map #incr $0))").unwrap();
+    ///
+    /// let mut python = HashMap::new();
+    /// python.insert(String::from("1"), String::from("1"));
+    /// python.insert(String::from("+"), String::from("{0} + {1}"));
+    /// python.insert(String::from("map"), String::from("list(map({0}, {1}))"));
+    /// assert_eq!(
+    ///     dsl.export(&expr, "incr_all", ExportTarget::Python, &python).unwrap(),
+    ///     "\
+    /// def incr(x):
+    ///     return (1 + x)
+    ///
+    /// def incr_all(x):
+    ///     return (list(map((lambda y: incr(y)), x)))
+    /// ",
+    /// );
+    ///
+    /// let mut scheme = HashMap::new();
+    /// scheme.insert(String::from("1"), String::from("1"));
+    /// scheme.insert(String::from("+"), String::from("(+ {0} {1})"));
+    /// scheme.insert(String::from("map"), String::from("(map {0} {1})"));
+    /// assert_eq!(
+    ///     dsl.export(&expr, "incr-all", ExportTarget::Scheme, &scheme).unwrap(),
+    ///     "\
+    /// (define (incr x) (+ 1 x))
+    /// (define (incr-all x) (map (lambda (y) (incr y)) x))
+    /// ",
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`invented_name`]: #method.invented_name
+    pub fn export(
+        &self,
+        expr: &Expression,
+        name: &str,
+        target: ExportTarget,
+        templates: &HashMap<String, String>,
+    ) -> Result<String, ExportError> {
+        export::export(self, expr, name, target, templates)
+    }
+
     fn candidates(
         &self,
         request: &Type,
         ctx: &Context,
         env: &VecDeque<Type>,
     ) -> Vec<(f64, Expression, Type, Context)> {
+        let logprobs = self
+            .primitives
+            .iter()
+            .map(|p| p.2)
+            .chain(self.invented.iter().map(|inv| inv.2));
+        self.candidates_with(logprobs, self.variable_logprob, request, ctx, env)
+    }
+
+    /// Like `candidates`, but with the given log-probabilities for each primitive then each
+    /// invention, and for variables.
+    fn candidates_with<I>(
+        &self,
+        logprobs: I,
+        variable_logprob: f64,
+        request: &Type,
+        ctx: &Context,
+        env: &VecDeque<Type>,
+    ) -> Vec<(f64, Expression, Type, Context)>
+    where
+        I: Iterator<Item = f64>,
+    {
         // make cands as big as possible to prevent reallocation
         let mut cands = Vec::with_capacity(self.primitives.len() + self.invented.len() + env.len());
         // primitives and inventions
@@ -545,13 +1508,13 @@ impl Language {
             .primitives
             .iter()
             .enumerate()
-            .map(|(i, &(_, ref tp, p))| (p, tp, Expression::Primitive(i)));
+            .map(|(i, (_, tp, _))| (tp, Expression::Primitive(i)));
         let invented = self
             .invented
             .iter()
             .enumerate()
-            .map(|(i, &(_, ref tp, p))| (p, tp, Expression::Invented(i)));
-        for (p, tp, expr) in prims.chain(invented) {
+            .map(|(i, (_, tp, _))| (tp, Expression::Invented(i)));
+        for (p, (tp, expr)) in logprobs.zip(prims.chain(invented)) {
             let mut ctx = ctx.clone();
             let mut tp = tp.clone().instantiate_owned(&mut ctx);
             let unifies = {
@@ -580,7 +1543,7 @@ impl Language {
             if ctx.unify_fast(ret.clone(), request.clone()).is_ok() {
                 let mut tp = tp.clone();
                 tp.apply_mut(&ctx);
-                cands.push((self.variable_logprob, expr, tp, ctx))
+                cands.push((variable_logprob, expr, tp, ctx))
             }
         }
         // update probabilities for indices
@@ -615,6 +1578,12 @@ impl EC for Language {
     {
         enumerator::run(self, tp, termination_condition)
     }
+    fn enumerate_sketch<F>(&self, sketch: &Expression, tp: TypeSchema, termination_condition: F)
+    where
+        F: Fn(Expression, f64) -> bool + Send + Sync,
+    {
+        enumerator::run_sketch(self, sketch, tp, termination_condition)
+    }
     fn compress<O: Sync>(
         &self,
         params: &Self::Params,
@@ -640,6 +1609,15 @@ pub enum Expression {
     /// The number associated with an invented expression is used by the Language to identify the
     /// invention.
     Invented(usize),
+    /// A hole in a sketch, written `?`, which is filled by [`Language::enumerate_sketch`].
+    /// Evaluation which reaches a hole stops with [`EvalError::Hole`].
+    ///
+    /// This variant was added with sketches, so a `match` on `Expression` written before then
+    /// needs an arm for it.
+    ///
+    /// [`Language::enumerate_sketch`]: struct.Language.html#method.enumerate_sketch
+    /// [`EvalError::Hole`]: enum.EvalError.html#variant.Hole
+    Hole,
 }
 impl Expression {
     fn infer(
@@ -694,6 +1672,7 @@ impl Expression {
                     Err(InferenceError::InvalidInvention(num))
                 }
             }
+            Expression::Hole => Ok(ctx.new_variable()),
         }
     }
     /// Puts a beta-normalized expression in eta-long form. Invalid types or non-beta-normalized
@@ -772,6 +1751,10 @@ impl Expression {
                         return false;
                     }
                     Expression::Application(_, _) => unreachable!(),
+                    Expression::Hole => {
+                        eprintln!("eta-long called on sketch {}", dsl.display(self));
+                        return false;
+                    }
                     Expression::Primitive(i) => dsl.primitives[i].1.instantiate(ctx),
                     Expression::Invented(i) => dsl.invented[i].1.instantiate(ctx),
                     Expression::Index(i) => env[i].apply(ctx),
@@ -809,10 +1792,129 @@ impl Expression {
                 return ctx.unify(&t, req).is_ok();
             }
             Expression::Index(i) => return ctx.unify(&env[i], req).is_ok(),
+            Expression::Hole => {
+                eprintln!("eta-long called on sketch {}", dsl.display(self));
+                return false;
+            }
         };
         *self = new_self;
         true
     }
+    /// Beta-reduces the expression in normal order (leftmost-outermost first), taking at most
+    /// `max_steps` reductions. Returns whether the expression is in beta-normal form, which is
+    /// always reached if one exists and `max_steps` is large enough. Inventions are not inlined,
+    /// so use [`Language::strip_invented`] first to reduce through them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate polytype;
+    /// # extern crate programinduction;
+    /// # fn main() {
+    /// # use programinduction::lambda::Language;
+    /// let dsl = Language::uniform(vec![
+    ///     ("1", ptp!(int)),
+    ///     ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    /// ]);
+    ///
+    /// let mut expr = dsl.parse("((λ (λ (+ $1 $0))) 1)").unwrap();
+    /// assert!(expr.beta_normalize(100));
+    /// assert_eq!(dsl.display(&expr), "(λ (+ 1 $0))");
+    ///
+    /// // omega has no normal form
+    /// let mut expr = dsl.parse("((λ ($0 $0)) (λ ($0 $0)))").unwrap();
+    /// assert!(!expr.beta_normalize(100));
+    /// # }
+    /// ```
+    ///
+    /// [`Language::strip_invented`]: struct.Language.html#method.strip_invented
+    pub fn beta_normalize(&mut self, max_steps: usize) -> bool {
+        for _ in 0..max_steps {
+            if !self.beta_step() {
+                return true;
+            }
+        }
+        self.is_beta_normal()
+    }
+    /// Performs the leftmost-outermost beta-reduction, returning whether there was one.
+    fn beta_step(&mut self) -> bool {
+        let reduced = match *self {
+            Expression::Application(ref f, ref x) => match **f {
+                Expression::Abstraction(ref body) => {
+                    let mut body = (**body).clone();
+                    body.substitute(0, x);
+                    Some(body)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(reduced) = reduced {
+            *self = reduced;
+            return true;
+        }
+        match *self {
+            Expression::Application(ref mut f, ref mut x) => f.beta_step() || x.beta_step(),
+            Expression::Abstraction(ref mut body) => body.beta_step(),
+            _ => false,
+        }
+    }
+    fn is_beta_normal(&self) -> bool {
+        match *self {
+            Expression::Application(ref f, ref x) => match **f {
+                Expression::Abstraction(_) => false,
+                _ => f.is_beta_normal() && x.is_beta_normal(),
+            },
+            Expression::Abstraction(ref body) => body.is_beta_normal(),
+            _ => true,
+        }
+    }
+    /// Replaces the variable bound `depth` abstractions above with `value`, removing that binder.
+    fn substitute(&mut self, depth: usize, value: &Expression) {
+        match *self {
+            Expression::Index(i) if i == depth => {
+                let mut value = value.clone();
+                value.shift(depth as i64);
+                *self = value;
+            }
+            Expression::Index(ref mut i) if *i > depth => *i -= 1,
+            Expression::Application(ref mut f, ref mut x) => {
+                f.substitute(depth, value);
+                x.substitute(depth, value);
+            }
+            Expression::Abstraction(ref mut body) => body.substitute(depth + 1, value),
+            _ => (),
+        }
+    }
+    fn mark_used(&self, primitives: &mut [bool], invented: &mut [bool]) {
+        match *self {
+            Expression::Primitive(num) => primitives[num] = true,
+            Expression::Invented(num) => invented[num] = true,
+            Expression::Application(ref f, ref x) => {
+                f.mark_used(primitives, invented);
+                x.mark_used(primitives, invented);
+            }
+            Expression::Abstraction(ref body) => body.mark_used(primitives, invented),
+            _ => (),
+        }
+    }
+    /// Renumbers primitives and inventions, which must not have been removed.
+    fn renumber(&mut self, primitives: &[Option<usize>], invented: &[Option<usize>]) {
+        match *self {
+            Expression::Primitive(ref mut num) => {
+                *num = primitives[*num].expect("renumbered a removed primitive")
+            }
+            Expression::Invented(ref mut num) => {
+                *num = invented[*num].expect("renumbered a removed invention")
+            }
+            Expression::Application(ref mut f, ref mut x) => {
+                f.renumber(primitives, invented);
+                x.renumber(primitives, invented);
+            }
+            Expression::Abstraction(ref mut body) => body.renumber(primitives, invented),
+            _ => (),
+        }
+    }
     fn strip_invented(&self, invented: &[(Expression, TypeSchema, f64)]) -> Expression {
         match *self {
             Expression::Application(ref f, ref x) => Expression::Application(
@@ -856,6 +1958,45 @@ impl Expression {
             _ => true,
         }
     }
+    /// Like `show`, but with named binders. `names` are the names of enclosing binders, innermost
+    /// last.
+    fn show_named(&self, dsl: &Language, names: &mut Vec<String>, is_function: bool) -> String {
+        match *self {
+            Expression::Abstraction(_) => {
+                let mut body = self;
+                let mut binders = Vec::new();
+                while let Expression::Abstraction(ref inner) = *body {
+                    let name = fresh_name(dsl, names);
+                    binders.push(name.clone());
+                    names.push(name);
+                    body = inner;
+                }
+                let body = body.show_named(dsl, names, false);
+                let new_len = names.len() - binders.len();
+                names.truncate(new_len);
+                format!("(λ ({}) {})", binders.join(" "), body)
+            }
+            Expression::Application(ref f, ref x) => {
+                let f = f.show_named(dsl, names, true);
+                let x = x.show_named(dsl, names, false);
+                if is_function {
+                    format!("{} {}", f, x)
+                } else {
+                    format!("({} {})", f, x)
+                }
+            }
+            Expression::Index(i) if i < names.len() => names[names.len() - 1 - i].clone(),
+            Expression::Index(i) => format!("${}", i - names.len()),
+            Expression::Invented(num) => match dsl.invented_name(num) {
+                Some(name) => format!("#{}", name),
+                None => format!(
+                    "#{}",
+                    dsl.invented[num].0.show_named(dsl, &mut Vec::new(), false)
+                ),
+            },
+            _ => self.show(dsl, is_function),
+        }
+    }
     fn as_lisp(
         &self,
         dsl: &Language,
@@ -889,11 +2030,16 @@ impl Expression {
                 let var = (96 + (depth - i) as u8) as char;
                 format!("{}", var)
             }
-            Expression::Invented(num) => {
-                dsl.invented[num as usize]
+            Expression::Invented(num) => match dsl.invented_name(num) {
+                Some(name) => conversions
+                    .get(name)
+                    .map_or(name, String::as_str)
+                    .to_string(),
+                None => dsl.invented[num as usize]
                     .0
-                    .as_lisp(dsl, false, conversions, depth)
-            }
+                    .as_lisp(dsl, false, conversions, depth),
+            },
+            Expression::Hole => String::from("?"),
         }
     }
     fn show(&self, dsl: &Language, is_function: bool) -> String {
@@ -908,11 +2054,151 @@ impl Expression {
             }
             Expression::Abstraction(ref body) => format!("(λ {})", body.show(dsl, false)),
             Expression::Index(i) => format!("${}", i),
-            Expression::Invented(num) => {
-                format!("#{}", dsl.invented[num as usize].0.show(dsl, false))
+            Expression::Invented(num) => match dsl.invented_name(num) {
+                Some(name) => format!("#{}", name),
+                None => format!("#{}", dsl.invented[num as usize].0.show(dsl, false)),
+            },
+            Expression::Hole => String::from("?"),
+        }
+    }
+}
+
+/// A name for a binder which is not a primitive and doesn't shadow any of `names`.
+fn fresh_name(dsl: &Language, names: &[String]) -> String {
+    const BASES: [&str; 6] = ["x", "y", "z", "u", "v", "w"];
+    (0..)
+        .flat_map(|n| {
+            BASES.iter().map(move |base| {
+                if n == 0 {
+                    String::from(*base)
+                } else {
+                    format!("{}{}", base, n)
+                }
+            })
+        })
+        .find(|name| !names.contains(name) && !dsl.primitives.iter().any(|p| &p.0 == name))
+        .unwrap()
+}
+
+/// The built-in fixed-point primitive for general recursion, `fix`, with type
+/// `((t0 → t1) → t0 → t1) → t0 → t1`. Include it among the primitives of a [`Language`] to use it.
+///
+/// Evaluation of `(fix f x)` unrolls to `(f (fix f) x)` without calling the evaluator, so in the
+/// body of `f` the variable `$1` is the recursive function and `$0` is its argument. The
+/// recursive call is only made when it is needed with a [`LazyEvaluator`], so a lazy conditional
+/// can provide a base case; with an [`Evaluator`], every argument is evaluated eagerly. Runaway
+/// recursion can be stopped with [`EvalLimits`], where `max_depth` also protects the stack.
+///
+/// Enumeration and sampling never produce a `fix` whose function trivially fails to terminate,
+/// because it recurses unconditionally or on its own unchanged argument.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate polytype;
+/// # extern crate programinduction;
+/// use programinduction::lambda::{self, Language, LazyEvaluator, LiftedLazyFunction};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum Space {
+///     Bool(bool),
+///     Num(i32),
+///     List(Vec<i32>),
+/// }
+///
+/// struct ListsEvaluator;
+/// impl LazyEvaluator for ListsEvaluator {
+///     type Space = Space;
+///     type Error = ();
+///     fn lazy_evaluate(
+///         &self,
+///         primitive: &str,
+///         inps: &[LiftedLazyFunction<Space, Self>],
+///     ) -> Result<Space, ()> {
+///         match primitive {
+///             "0" => Ok(Space::Num(0)),
+///             "+1" => match inps[0].eval(&[])? {
+///                 Space::Num(x) => Ok(Space::Num(x + 1)),
+///                 _ => Err(()),
+///             },
+///             "if" => match inps[0].eval(&[])? {
+///                 Space::Bool(true) => inps[1].eval(&[]),
+///                 Space::Bool(false) => inps[2].eval(&[]),
+///                 _ => Err(()),
+///             },
+///             "empty?" => match inps[0].eval(&[])? {
+///                 Space::List(xs) => Ok(Space::Bool(xs.is_empty())),
+///                 _ => Err(()),
+///             },
+///             "cdr" => match inps[0].eval(&[])? {
+///                 Space::List(ref xs) if !xs.is_empty() => Ok(Space::List(xs[1..].to_vec())),
+///                 _ => Err(()),
+///             },
+///             _ => unreachable!(),
+///         }
+///     }
+///     fn limit_error(&self) {}
+/// }
+///
+/// # fn main() {
+/// let dsl = Language::uniform(vec![
+///     lambda::fix_primitive(),
+///     ("0", ptp!(int)),
+///     ("+1", ptp!(@arrow[tp!(int), tp!(int)])),
+///     ("if", ptp!(0; @arrow[tp!(bool), tp!(0), tp!(0), tp!(0)])),
+///     ("empty?", ptp!(0; @arrow[tp!(list(tp!(0))), tp!(bool)])),
+///     ("cdr", ptp!(0; @arrow[tp!(list(tp!(0))), tp!(list(tp!(0)))])),
+/// ]);
+/// let length = dsl
+///     .parse("(λ (fix (λ (λ (if (empty? $0) 0 (+1 ($1 (cdr $0)))))) $0))")
+///     .unwrap();
+/// let inps = vec![Space::List(vec![4, 2, 7])];
+/// assert_eq!(dsl.lazy_eval(&length, ListsEvaluator, &inps), Ok(Space::Num(3)));
+///
+/// // recursing on the same argument never terminates
+/// let fix = &lambda::Expression::Primitive(0);
+/// let f = dsl.parse("(λ (λ (if (empty? $0) 0 ($1 $0))))").unwrap();
+/// assert!(dsl.violates_symmetry(fix, 0, &f));
+/// # }
+/// ```
+///
+/// [`Language`]: struct.Language.html
+/// [`Evaluator`]: trait.Evaluator.html
+/// [`LazyEvaluator`]: trait.LazyEvaluator.html
+/// [`EvalLimits`]: struct.EvalLimits.html
+pub fn fix_primitive() -> (&'static str, TypeSchema) {
+    let f = tp!(@arrow[tp!(0), tp!(1)]);
+    (
+        FIX,
+        ptp!(0, 1; @arrow[tp!(@arrow[f.clone(), f.clone()]), f]),
+    )
+}
+
+/// Whether the function given to `fix` trivially fails to terminate: its body is a recursive call,
+/// or it makes a recursive call on its own argument.
+fn fix_diverges(f: &Expression) -> bool {
+    fn recurses_on_argument(expr: &Expression, depth: usize) -> bool {
+        match *expr {
+            Expression::Application(ref f, ref x) => {
+                (**f == Expression::Index(depth + 1) && **x == Expression::Index(depth))
+                    || recurses_on_argument(f, depth)
+                    || recurses_on_argument(x, depth)
+            }
+            Expression::Abstraction(ref body) => recurses_on_argument(body, depth + 1),
+            _ => false,
+        }
+    }
+    if let Expression::Abstraction(ref body) = *f {
+        if let Expression::Abstraction(ref body) = **body {
+            let mut head: &Expression = body;
+            while let Expression::Application(ref f, _) = *head {
+                head = f;
             }
+            return *head == Expression::Index(1) || recurses_on_argument(body, 0);
         }
     }
+    false
 }
 
 /// Create a task based on evaluating lambda calculus expressions on test input/output pairs.
@@ -964,19 +2250,45 @@ pub fn task_by_evaluation<'a, E, V>(
     tp: TypeSchema,
     examples: &'a [(Vec<V>, V)],
 ) -> Task<'a, Language, Expression, &'a [(Vec<V>, V)]>
+where
+    E: Evaluator<Space = V> + Send + 'a,
+    V: PartialEq + Clone + Send + Sync + 'a,
+{
+    task_by_evaluation_with_limits(evaluator, tp, examples, EvalLimits::default())
+}
+
+/// Like [`task_by_evaluation`], but each example is evaluated with the given [`EvalLimits`], and
+/// an expression which exhausts them is not a solution.
+///
+/// [`task_by_evaluation`]: fn.task_by_evaluation.html
+/// [`EvalLimits`]: struct.EvalLimits.html
+pub fn task_by_evaluation_with_limits<'a, E, V>(
+    evaluator: E,
+    tp: TypeSchema,
+    examples: &'a [(Vec<V>, V)],
+    limits: EvalLimits,
+) -> Task<'a, Language, Expression, &'a [(Vec<V>, V)]>
 where
     E: Evaluator<Space = V> + Send + 'a,
     V: PartialEq + Clone + Send + Sync + 'a,
 {
     let evaluator = Arc::new(evaluator);
     let oracle = Box::new(move |dsl: &Language, expr: &Expression| {
-        let success = examples.iter().all(|&(ref inps, ref out)| {
-            if let Ok(o) = dsl.eval_arc(expr, &evaluator, inps) {
-                o == *out
-            } else {
-                false
-            }
-        });
+        let compiled = dsl.compile(expr);
+        let inputs = examples.iter().map(|(inps, _)| &inps[..]);
+        let success = compiled
+            .eval_batch_with_limits(&evaluator, inputs, &limits)
+            .into_iter()
+            .zip(examples)
+            .all(
+                |(o, (_, out))| {
+                    if let Ok(o) = o {
+                        o == *out
+                    } else {
+                        false
+                    }
+                },
+            );
         if success {
             0f64
         } else {
@@ -999,19 +2311,43 @@ pub fn task_by_lazy_evaluation<'a, E, V>(
     tp: TypeSchema,
     examples: &'a [(Vec<V>, V)],
 ) -> Task<'a, Language, Expression, &'a [(Vec<V>, V)]>
+where
+    E: LazyEvaluator<Space = V> + Send + 'a,
+    V: PartialEq + Clone + Send + Sync + 'a,
+{
+    task_by_lazy_evaluation_with_limits(evaluator, tp, examples, EvalLimits::default())
+}
+
+/// Like [`task_by_evaluation_with_limits`], but for use with a [`LazyEvaluator`].
+///
+/// [`task_by_evaluation_with_limits`]: fn.task_by_evaluation_with_limits.html
+/// [`LazyEvaluator`]: trait.LazyEvaluator.html
+pub fn task_by_lazy_evaluation_with_limits<'a, E, V>(
+    evaluator: E,
+    tp: TypeSchema,
+    examples: &'a [(Vec<V>, V)],
+    limits: EvalLimits,
+) -> Task<'a, Language, Expression, &'a [(Vec<V>, V)]>
 where
     E: LazyEvaluator<Space = V> + Send + 'a,
     V: PartialEq + Clone + Send + Sync + 'a,
 {
     let evaluator = Arc::new(evaluator);
     let oracle = Box::new(move |dsl: &Language, expr: &Expression| {
-        let success = examples.iter().all(|&(ref inps, ref out)| {
-            if let Ok(o) = dsl.lazy_eval_arc(expr, &evaluator, inps) {
-                o == *out
-            } else {
-                false
-            }
-        });
+        let compiled = dsl.compile(expr);
+        let inputs = examples.iter().map(|(inps, _)| &inps[..]);
+        let success = compiled
+            .lazy_eval_batch_with_limits(&evaluator, inputs, &limits)
+            .zip(examples)
+            .all(
+                |(o, (_, out))| {
+                    if let Ok(o) = o {
+                        o == *out
+                    } else {
+                        false
+                    }
+                },
+            );
         if success {
             0f64
         } else {
@@ -1111,3 +2447,32 @@ impl Error for InferenceError {
         "could not infer type"
     }
 }
+
+/// An error from [`Language::name_invented`].
+///
+/// [`Language::name_invented`]: struct.Language.html#method.name_invented
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    /// There is no invented expression at the given position in `invented`.
+    InvalidInvention(usize),
+    /// The name is empty, or contains whitespace or parentheses.
+    InvalidName(String),
+    /// The name is already used by a different invented expression.
+    NameInUse(String),
+}
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            NameError::InvalidInvention(n) => write!(f, "invention {} not in Language", n),
+            NameError::InvalidName(ref name) => write!(f, "invalid invention name: {:?}", name),
+            NameError::NameInUse(ref name) => {
+                write!(f, "invention name already in use: {:?}", name)
+            }
+        }
+    }
+}
+impl Error for NameError {
+    fn description(&self) -> &str {
+        "could not name invention"
+    }
+}
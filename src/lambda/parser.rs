@@ -1,3 +1,4 @@
+use polytype::Type;
 use std::collections::VecDeque;
 use std::{error, fmt};
 
@@ -126,6 +127,25 @@ fn streaming_parse(
             None
         }
     };
+    let named_invented = || {
+        if !inp.starts_with('#') || inp[1..].starts_with('(') {
+            return None;
+        }
+        let di = inp
+            .find(|c: char| c.is_whitespace() || c == ')')
+            .unwrap_or(inp.len());
+        let name = &inp[1..di];
+        if let Some(num) = dsl.invented_by_name(name) {
+            Some(Ok((di, Expression::Invented(num))))
+        } else if dsl.primitives.iter().any(|(name, _, _)| name == &inp[..di]) {
+            None // a primitive's name
+        } else {
+            Some(Err(ParseError::new(
+                offset,
+                "invented name is unfamiliar to context",
+            )))
+        }
+    };
     let invented = || {
         if inp.chars().take(2).collect::<String>() == "#(" {
             Some(1)
@@ -145,6 +165,22 @@ fn streaming_parse(
             }
         })
     };
+    let hole = || {
+        if !inp.starts_with('?') {
+            return None;
+        }
+        let di = inp
+            .find(|c: char| c.is_whitespace() || c == ')')
+            .unwrap_or(inp.len());
+        let label = &inp[1..di];
+        if dsl.primitives.iter().any(|(name, _, _)| name == &inp[..di]) {
+            None // a primitive's name
+        } else if label.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            Some(Ok((di, Expression::Hole)))
+        } else {
+            Some(Err(ParseError::new(offset + di, "invalid hole label")))
+        }
+    };
     let primitive = || {
         match inp.find(|c: char| c.is_whitespace() || c == ')') {
             None if !inp.is_empty() => Some(inp.len()),
@@ -170,6 +206,8 @@ fn streaming_parse(
         .or_else(application)
         .or_else(index)
         .or_else(invented)
+        .or_else(named_invented)
+        .or_else(hole)
         .or_else(primitive)
         .unwrap_or_else(|| {
             Err(ParseError::new(
@@ -178,3 +216,251 @@ fn streaming_parse(
             ))
         })
 }
+
+pub fn parse_named(dsl: &Language, inp: &str) -> Result<Expression, ParseError> {
+    let mut parser = NamedParser {
+        dsl,
+        inp,
+        pos: 0,
+        scope: Vec::new(),
+        annotations: Vec::new(),
+    };
+    let expr = parser.expr()?;
+    parser.skip_whitespace();
+    if parser.pos < inp.len() {
+        return Err(ParseError::new(
+            parser.pos,
+            "expected end of expression, found more tokens",
+        ));
+    }
+    if parser.annotations.is_empty() {
+        return Ok(expr);
+    }
+    // Each annotated abstraction was wrapped in an application of a new primitive, whose type
+    // constrains the abstraction's argument. Annotations are checked one more at a time, so the
+    // first that is inconsistent can be reported.
+    let n_primitives = dsl.primitives.len();
+    let mut annotated_dsl = dsl.clone();
+    for (_, tp) in &parser.annotations {
+        let ret = Type::Variable(tp.vars().into_iter().max().map_or(0, |v| v + 1));
+        let f = Type::arrow(tp.clone(), ret);
+        annotated_dsl.primitives.push((
+            String::from(":"),
+            Type::arrow(f.clone(), f).generalize(&[]),
+            0.0,
+        ));
+    }
+    for (i, &(location, _)) in parser.annotations.iter().enumerate() {
+        let checked = strip_annotations(&expr, n_primitives + i + 1);
+        if annotated_dsl.infer(&checked).is_err() {
+            return Err(ParseError::new(
+                location,
+                "type annotation is inconsistent with the expression",
+            ));
+        }
+    }
+    Ok(strip_annotations(&expr, n_primitives))
+}
+
+/// Removes the applications of annotation primitives numbered `first` and above.
+fn strip_annotations(expr: &Expression, first: usize) -> Expression {
+    match *expr {
+        Expression::Application(ref f, ref x) => match **f {
+            Expression::Primitive(num) if num >= first => strip_annotations(x, first),
+            _ => Expression::Application(
+                Box::new(strip_annotations(f, first)),
+                Box::new(strip_annotations(x, first)),
+            ),
+        },
+        Expression::Abstraction(ref body) => {
+            Expression::Abstraction(Box::new(strip_annotations(body, first)))
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// A recursive-descent parser for expressions with named binders, like `(λ (x y) (+ x y))`.
+struct NamedParser<'a> {
+    dsl: &'a Language,
+    inp: &'a str,
+    pos: usize,
+    /// Names of the enclosing binders, innermost last.
+    scope: Vec<&'a str>,
+    /// Locations and types of binder annotations, in the order they were parsed.
+    annotations: Vec<(usize, Type)>,
+}
+impl<'a> NamedParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.inp[self.pos..]
+    }
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+    fn atom(&mut self) -> Result<&'a str, ParseError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(ParseError::new(self.pos, "unexpected end of expression"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+    fn expr(&mut self) -> Result<Expression, ParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if self.eat('(') {
+            let checkpoint = self.pos;
+            match self.atom() {
+                Ok("lambda") | Ok("λ") => return self.abstraction(),
+                _ => self.pos = checkpoint,
+            }
+            let mut app = self.expr()?;
+            while !self.eat(')') {
+                if self.pos >= self.inp.len() {
+                    return Err(ParseError::new(self.pos, "incomplete application"));
+                }
+                let x = self.expr()?;
+                app = Expression::Application(Box::new(app), Box::new(x));
+            }
+            return Ok(app);
+        }
+        if self.rest().starts_with("#(") {
+            self.pos += 1;
+            // inventions are closed, so their bodies are parsed with a fresh scope
+            let scope = ::std::mem::take(&mut self.scope);
+            let body = self.expr();
+            self.scope = scope;
+            let body = strip_annotations(&body?, self.dsl.primitives.len());
+            return match self.dsl.invented.iter().position(|inv| inv.0 == body) {
+                Some(num) => Ok(Expression::Invented(num)),
+                None => Err(ParseError::new(
+                    self.pos,
+                    "invented expr is unfamiliar to context",
+                )),
+            };
+        }
+        let atom = self.atom()?;
+        if let Some(pos) = self.scope.iter().rposition(|&name| name == atom) {
+            Ok(Expression::Index(self.scope.len() - 1 - pos))
+        } else if let Some(num) = self.dsl.primitives.iter().position(|p| p.0 == atom) {
+            Ok(Expression::Primitive(num))
+        } else if let Some(atom) = atom.strip_prefix('#') {
+            match self.dsl.invented_by_name(atom) {
+                Some(num) => Ok(Expression::Invented(num)),
+                None => Err(ParseError::new(
+                    start,
+                    "invented name is unfamiliar to context",
+                )),
+            }
+        } else if let Some(atom) = atom.strip_prefix('$') {
+            match atom.parse::<usize>() {
+                Ok(num) => Ok(Expression::Index(num + self.scope.len())),
+                Err(_) => Err(ParseError::new(start, "invalid free variable")),
+            }
+        } else if let Some(atom) = atom.strip_prefix('?') {
+            if atom.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                Ok(Expression::Hole)
+            } else {
+                Err(ParseError::new(start, "invalid hole label"))
+            }
+        } else {
+            Err(ParseError::new(
+                start,
+                "unbound variable or unknown primitive",
+            ))
+        }
+    }
+    /// Parses the remainder of `(λ (BINDER ...) BODY)`, where a BINDER is either `NAME` or
+    /// `(NAME : TYPE)`.
+    fn abstraction(&mut self) -> Result<Expression, ParseError> {
+        if !self.eat('(') {
+            return Err(ParseError::new(self.pos, "expected a list of binders"));
+        }
+        let mut binders = Vec::new();
+        while !self.eat(')') {
+            if self.eat('(') {
+                let name = self.binder_name()?;
+                if self.atom()? != ":" {
+                    return Err(ParseError::new(self.pos, "expected a type annotation"));
+                }
+                self.skip_whitespace();
+                let location = self.pos;
+                let tp = self.annotation()?;
+                binders.push((name, Some((location, tp))));
+            } else {
+                binders.push((self.binder_name()?, None));
+            }
+        }
+        if binders.is_empty() {
+            return Err(ParseError::new(self.pos, "lambda without binders"));
+        }
+        let n = binders.len();
+        let mut annotations = Vec::new();
+        for (name, annotation) in binders {
+            self.scope.push(name);
+            annotations.push(annotation.map(|(location, tp)| {
+                self.annotations.push((location, tp));
+                self.dsl.primitives.len() + self.annotations.len() - 1
+            }));
+        }
+        let body = self.expr();
+        let new_len = self.scope.len() - n;
+        self.scope.truncate(new_len);
+        let mut expr = body?;
+        if !self.eat(')') {
+            return Err(ParseError::new(self.pos, "incomplete abstraction"));
+        }
+        for annotation in annotations.into_iter().rev() {
+            expr = Expression::Abstraction(Box::new(expr));
+            if let Some(num) = annotation {
+                expr =
+                    Expression::Application(Box::new(Expression::Primitive(num)), Box::new(expr));
+            }
+        }
+        Ok(expr)
+    }
+    fn binder_name(&mut self) -> Result<&'a str, ParseError> {
+        let start = self.pos;
+        let name = self.atom()?;
+        if name.starts_with(&['$', '#', '?'][..])
+            || name.contains(':')
+            || name == "lambda"
+            || name == "λ"
+        {
+            Err(ParseError::new(start, "invalid binder name"))
+        } else {
+            Ok(name)
+        }
+    }
+    /// Parses a type up to the closing parenthesis of its annotation.
+    fn annotation(&mut self) -> Result<Type, ParseError> {
+        let start = self.pos;
+        let mut depth = 0;
+        for (i, c) in self.rest().char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    self.pos += i + 1;
+                    return Type::parse(&self.inp[start..start + i])
+                        .map_err(|_| ParseError::new(start, "invalid type annotation"));
+                }
+                ')' => depth -= 1,
+                _ => (),
+            }
+        }
+        Err(ParseError::new(start, "incomplete type annotation"))
+    }
+}
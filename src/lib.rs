@@ -81,10 +81,14 @@
 //! constraint for relevant programs as a type in the [`tp`] field. The [`observation`] field is
 //! not utilized by GP (we recommend setting it to [`unit`]). Programs may be expressed under
 //! different representations, so we provide a representation-agnostic trait [`GP`]. We provide an
-//! implementation for probabilistic context free grammars in the [`pcfg`] module.
+//! implementation for probabilistic context free grammars in the [`pcfg`] module, and for
+//! polymorphically-typed lambda calculus in the [`lambda`] module.
 //!
 //! See the [`GP`] trait for details and an example.
 //!
+//! The mutations of a [`GP`] representation also drive local search: [`anneal`] performs
+//! simulated annealing and [`temper`] performs parallel tempering.
+//!
 //! [Human-level concept learning through probabilistic program induction]: http://web.mit.edu/cocosci/Papers/Science-2015-Lake-1332-8.pdf
 //! [Bootstrap learning via modular concept discovery]: https://hips.seas.harvard.edu/files/dechter-bootstrap-ijcai-2013.pdf
 //! [_A Field Guide to Genetic Programming_]: http://www.gp-field-guide.org.uk
@@ -94,6 +98,8 @@
 //! [`tp`]: struct.Task.html#structfield.tp
 //! [`EC`]: trait.EC.html
 //! [`GP`]: trait.GP.html
+//! [`anneal`]: fn.anneal.html
+//! [`temper`]: fn.temper.html
 //! [`lambda`]: lambda/index.html
 //! [`pcfg`]: pcfg/index.html
 //! [`unit`]: https://doc.rust-lang.org/std/primitive.unit.html
@@ -114,6 +120,7 @@ extern crate rayon;
 extern crate serde;
 extern crate term_rewriting;
 
+mod anneal;
 pub mod domains;
 mod ec;
 mod gp;
@@ -121,6 +128,7 @@ pub mod lambda;
 pub mod pcfg;
 pub mod trs;
 mod utils;
+pub use anneal::*;
 pub use ec::*;
 pub use gp::*;
 
@@ -146,6 +154,20 @@ pub struct Task<'a, R: Send + Sync + Sized, X: Clone + Send + Sync, O: Sync> {
     /// [`unit`]: https://doc.rust-lang.org/std/primitive.unit.html
     pub observation: O,
 }
+impl<'a, R, X, O> Task<'a, R, X, O>
+where
+    R: 'a + Send + Sync + Sized,
+    X: 'a + Clone + Send + Sync,
+    O: Sync,
+{
+    /// Restrict the candidate solutions for this task to completions of a sketch, a partial
+    /// expression. Sketched tasks are explored with [`EC::explore_sketched`].
+    ///
+    /// [`EC::explore_sketched`]: trait.EC.html#method.explore_sketched
+    pub fn with_sketch(self, sketch: X) -> SketchedTask<'a, R, X, O> {
+        SketchedTask { task: self, sketch }
+    }
+}
 impl<'a, R, X> Task<'a, R, X, ()>
 where
     R: 'a + Send + Sync + Sized,
@@ -162,6 +184,21 @@ where
     }
 }
 
+/// A [`Task`] whose candidate solutions are completions of a sketch. Made with
+/// [`Task::with_sketch`].
+///
+/// [`Task`]: struct.Task.html
+/// [`Task::with_sketch`]: struct.Task.html#method.with_sketch
+pub struct SketchedTask<'a, R: Send + Sync + Sized, X: Clone + Send + Sync, O: Sync> {
+    /// The task being solved.
+    pub task: Task<'a, R, X, O>,
+    /// A partial expression, such as a [`lambda::Expression`] with holes, whose completions are
+    /// the only candidate solutions for the `task`.
+    ///
+    /// [`lambda::Expression`]: lambda/enum.Expression.html
+    pub sketch: X,
+}
+
 fn noop_oracle<R, X>(_: &R, _: &X) -> f64
 where
     R: Send + Sync + Sized,
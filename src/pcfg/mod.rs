@@ -55,7 +55,7 @@ use std::fmt::Debug;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use {ECFrontier, Task, EC, GP};
+use {ECFrontier, GPCheckpoint, Task, EC, GP};
 
 /// (representation) Probabilistic context-free grammar. Currently cannot handle bound variables or
 /// polymorphism.
@@ -345,6 +345,20 @@ impl EC for Grammar {
             _ => panic!("PCFGs can't handle polytypes"),
         }
     }
+    /// Sentences of a PCFG have no holes, so the only completion of a sketch is the sketch
+    /// itself, if it is of the requested nonterminal.
+    fn enumerate_sketch<F>(&self, sketch: &AppliedRule, tp: TypeSchema, termination_condition: F)
+    where
+        F: Fn(Self::Expression, f64) -> bool + Send + Sync,
+    {
+        match tp {
+            TypeSchema::Monotype(ref tp) if *tp == sketch.0 => {
+                termination_condition(sketch.clone(), self.likelihood(sketch));
+            }
+            TypeSchema::Monotype(_) => (),
+            _ => panic!("PCFGs can't handle polytypes"),
+        }
+    }
     /// This is exactly the same as [`Grammar::update_parameters`], but optimized to deal with
     /// frontiers.
     ///
@@ -403,6 +417,8 @@ pub struct GeneticParams {
     pub mutation_subtree: f64,
     /// A reproduction mutation is a no-op.
     pub mutation_reproduction: f64,
+    /// The operator used for crossover.
+    pub crossover: Crossover,
 }
 impl Default for GeneticParams {
     fn default() -> GeneticParams {
@@ -411,10 +427,41 @@ impl Default for GeneticParams {
             mutation_point: 0.45,
             mutation_subtree: 0.45,
             mutation_reproduction: 0.1,
+            crossover: Crossover::Subtree,
         }
     }
 }
 
+/// Crossover operators for PCFG genetic programming ([`GP`]).
+///
+/// Each operator replaces a node of one parent (the _crossover point_) with a subtree of the
+/// other parent that is produced by the same nonterminal. If the other parent has no such subtree,
+/// the first parent is reproduced.
+///
+/// [`GP`]: ../trait.GP.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossover {
+    /// The crossover point and the inserted subtree are both selected according to the
+    /// [`progeny_factor`].
+    ///
+    /// [`progeny_factor`]: struct.GeneticParams.html#structfield.progeny_factor
+    Subtree,
+    /// Like `Subtree`, but the inserted subtree may be no larger than `1 + 2 * n` nodes, where `n`
+    /// is the size of the subtree it replaces. This limits the growth of offspring.
+    SizeFair,
+    /// Like `Subtree`, but the inserted subtree is the one whose position in its parent is most
+    /// aligned with the crossover point: the subtree at the same position is preferred, followed
+    /// by those sharing the longest path from the root.
+    Homologous,
+    /// The crossover point is selected with weight proportional to the negative log-likelihood
+    /// ([`Grammar::likelihood`]) of the subtree it roots, and the inserted subtree with weight
+    /// proportional to its likelihood. Improbable subtrees are thus replaced with probable ones.
+    /// If every subtree has likelihood one, the crossover point is selected uniformly.
+    ///
+    /// [`Grammar::likelihood`]: struct.Grammar.html#method.likelihood
+    Likelihood,
+}
+
 impl GP for Grammar {
     type Expression = AppliedRule;
     type Params = GeneticParams;
@@ -438,12 +485,31 @@ impl GP for Grammar {
         params: &Self::Params,
         rng: &mut R,
         prog: &Self::Expression,
-        _obs: &Self::Observation,
+        obs: &Self::Observation,
     ) -> Vec<Self::Expression> {
         let tot = params.mutation_point + params.mutation_subtree + params.mutation_reproduction;
-        match Uniform::from(0f64..tot).sample(rng) {
+        let operator = match Uniform::from(0f64..tot).sample(rng) {
+            x if x < params.mutation_point => 0,
+            x if x < params.mutation_point + params.mutation_subtree => 1,
+            _ => 2,
+        };
+        self.mutate_with_operator(params, rng, prog, obs, operator)
+    }
+    /// Point mutation, subtree mutation, and reproduction.
+    fn n_mutation_operators(&self, _params: &Self::Params) -> usize {
+        3
+    }
+    fn mutate_with_operator<R: Rng>(
+        &self,
+        params: &Self::Params,
+        rng: &mut R,
+        prog: &Self::Expression,
+        _obs: &Self::Observation,
+        operator: usize,
+    ) -> Vec<Self::Expression> {
+        match operator {
             // point mutation
-            x if x < params.mutation_point => {
+            0 => {
                 vec![mutate_random_node(params, prog.clone(), rng, |ar, rng| {
                     let rule = &self.rules[&ar.0][ar.1];
                     let mut candidates: Vec<_> = self.rules[&ar.0]
@@ -461,13 +527,11 @@ impl GP for Grammar {
                 })]
             }
             // subtree mutation
-            x if x < params.mutation_point + params.mutation_subtree => {
-                vec![mutate_random_node(params, prog.clone(), rng, |ar, rng| {
-                    self.sample(&ar.0, rng)
-                })]
-            }
+            1 => vec![mutate_random_node(params, prog.clone(), rng, |ar, rng| {
+                self.sample(&ar.0, rng)
+            })],
             // reproduction
-            _ => vec![prog.clone()], // reproduction
+            _ => vec![prog.clone()],
         }
     }
     fn crossover<R: Rng>(
@@ -478,10 +542,45 @@ impl GP for Grammar {
         parent2: &Self::Expression,
         _obs: &Self::Observation,
     ) -> Vec<Self::Expression> {
-        vec![
-            crossover_random_node(params, parent1, parent2, rng),
-            crossover_random_node(params, parent2, parent1, rng),
-        ]
+        match params.crossover {
+            Crossover::Subtree => vec![
+                crossover_random_node(params, parent1, parent2, rng),
+                crossover_random_node(params, parent2, parent1, rng),
+            ],
+            crossover => vec![
+                crossover_aligned_node(self, params, crossover, parent1, parent2, rng),
+                crossover_aligned_node(self, params, crossover, parent2, parent1, rng),
+            ],
+        }
+    }
+    fn size(&self, expr: &Self::Expression) -> usize {
+        1 + expr.2.iter().map(|ar| self.size(ar)).sum::<usize>()
+    }
+    fn depth(&self, expr: &Self::Expression) -> usize {
+        1 + expr.2.iter().map(|ar| self.depth(ar)).max().unwrap_or(0)
+    }
+    /// Overlays the two statements from the root. Any subtrees that differ at their root
+    /// contribute their combined size.
+    fn distance(&self, expr1: &Self::Expression, expr2: &Self::Expression) -> f64 {
+        if expr1.0 == expr2.0 && expr1.1 == expr2.1 {
+            expr1
+                .2
+                .iter()
+                .zip(&expr2.2)
+                .map(|(ar1, ar2)| self.distance(ar1, ar2))
+                .sum()
+        } else {
+            (self.size(expr1) + self.size(expr2)) as f64
+        }
+    }
+}
+impl GPCheckpoint for Grammar {
+    type ParseError = ParseError;
+    fn display_expression(&self, expr: &Self::Expression) -> String {
+        self.display(expr)
+    }
+    fn parse_expression(&self, s: &str) -> Result<Self::Expression, Self::ParseError> {
+        self.parse(s)
     }
 }
 
@@ -612,12 +711,13 @@ where
     }
 }
 
-use self::gp::{crossover_random_node, mutate_random_node};
+use self::gp::{crossover_aligned_node, crossover_random_node, mutate_random_node};
 mod gp {
-    use super::{AppliedRule, GeneticParams};
+    use super::{AppliedRule, Crossover, GeneticParams, Grammar};
     use polytype::Type;
-    use rand::distributions::{Distribution, Uniform};
+    use rand::distributions::{Distribution, Uniform, WeightedIndex};
     use rand::Rng;
+    use GP;
 
     pub fn mutate_random_node<R, F>(
         params: &GeneticParams,
@@ -692,6 +792,99 @@ mod gp {
         })
     }
 
+    /// Crossover for every [`Crossover`] other than `Subtree`, which locates nodes by their path
+    /// from the root.
+    ///
+    /// [`Crossover`]: ../enum.Crossover.html
+    pub fn crossover_aligned_node<R: Rng>(
+        g: &Grammar,
+        params: &GeneticParams,
+        crossover: Crossover,
+        parent1: &AppliedRule,
+        parent2: &AppliedRule,
+        rng: &mut R,
+    ) -> AppliedRule {
+        let mut points = Vec::new();
+        fetch_nodes(parent1, &mut vec![], &mut points);
+        let weights: Vec<f64> = match crossover {
+            Crossover::Likelihood => points.iter().map(|(_, ar)| -g.likelihood(ar)).collect(),
+            _ => points
+                .iter()
+                .map(|(path, _)| params.progeny_factor.powf(path.len() as f64))
+                .collect(),
+        };
+        let (path, point) = &points[weighted_choice(&weights, rng)];
+
+        let mut donors = Vec::new();
+        fetch_nodes(parent2, &mut vec![], &mut donors);
+        donors.retain(|(_, ar)| ar.0 == point.0);
+        if crossover == Crossover::SizeFair {
+            let max_size = 1 + 2 * g.size(point);
+            donors.retain(|(_, ar)| g.size(ar) <= max_size);
+        }
+        if donors.is_empty() {
+            return parent1.clone();
+        }
+        let weights: Vec<f64> = match crossover {
+            Crossover::Likelihood => donors
+                .iter()
+                .map(|(_, ar)| g.likelihood(ar).exp())
+                .collect(),
+            Crossover::Homologous => {
+                let alignment = |donor: &[usize]| {
+                    let common = donor.iter().zip(path).take_while(|(a, b)| a == b).count();
+                    (common, donor.len() == path.len())
+                };
+                let best = donors
+                    .iter()
+                    .map(|(donor, _)| alignment(donor))
+                    .max()
+                    .expect("donors are nonempty");
+                donors
+                    .iter()
+                    .map(|(donor, _)| if alignment(donor) == best { 1.0 } else { 0.0 })
+                    .collect()
+            }
+            _ => donors
+                .iter()
+                .map(|(donor, _)| params.progeny_factor.powf(donor.len() as f64))
+                .collect(),
+        };
+        let donor = donors[weighted_choice(&weights, rng)].1.clone();
+        let mut child = parent1.clone();
+        *node_at(&mut child, path) = donor;
+        child
+    }
+
+    /// Samples an index with the given weights. If there is no positive weight, as when every
+    /// crossover point has likelihood one under `Crossover::Likelihood`, every index is equally
+    /// likely. Weights must otherwise be finite and nonnegative.
+    fn weighted_choice<R: Rng>(weights: &[f64], rng: &mut R) -> usize {
+        debug_assert!(weights.iter().all(|w| w.is_finite() && *w >= 0.0));
+        match WeightedIndex::new(weights) {
+            Ok(dist) => dist.sample(rng),
+            Err(_) => rng.gen_range(0, weights.len()),
+        }
+    }
+
+    /// Every node with its path from the root, in pre-order.
+    fn fetch_nodes<'a>(
+        ar: &'a AppliedRule,
+        path: &mut Vec<usize>,
+        nodes: &mut Vec<(Vec<usize>, &'a AppliedRule)>,
+    ) {
+        nodes.push((path.clone(), ar));
+        for (i, ar) in ar.2.iter().enumerate() {
+            path.push(i);
+            fetch_nodes(ar, path, nodes);
+            path.pop();
+        }
+    }
+
+    fn node_at<'a>(ar: &'a mut AppliedRule, path: &[usize]) -> &'a mut AppliedRule {
+        path.iter().fold(ar, |ar, &i| &mut ar.2[i])
+    }
+
     fn fetch_subtrees_with_type<'a>(
         params: &GeneticParams,
         ar: &'a AppliedRule,
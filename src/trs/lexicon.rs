@@ -10,9 +10,10 @@ use term_rewriting::{
     Atom, Context, Operator, Place, Rule, RuleContext, Signature, Term, Variable, TRS as UntypedTRS,
 };
 
+use super::parser::{parse_trs, ParseError};
 use super::{SampleError, TypeError, TRS};
 use utils::{logsumexp, weighted_permutation};
-use GP;
+use {GPCheckpoint, GP};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Parameters for [`Lexicon`] genetic programming ([`GP`]).
@@ -159,9 +160,7 @@ impl Lexicon {
         let sig = &self.0.read().expect("poisoned lexicon").signature;
         sig.operators()
             .into_iter()
-            .find(|op| {
-                op.arity() == arity && op.name().as_ref().map(std::string::String::as_str) == name
-            })
+            .find(|op| op.arity() == arity && op.name().as_deref() == name)
             .ok_or(())
     }
     /// All the free type variables in the lexicon.
@@ -258,8 +257,10 @@ impl Lexicon {
     /// ```
     /// # #[macro_use] extern crate polytype;
     /// # extern crate programinduction;
+    /// # extern crate rand;
     /// # use programinduction::trs::Lexicon;
     /// # use polytype::Context as TypeContext;
+    /// # use rand::thread_rng;
     /// # fn main() {
     /// let operators = vec![
     ///     (2, Some("PLUS".to_string()), ptp![@arrow[tp!(int), tp!(int), tp!(int)]]),
@@ -276,12 +277,15 @@ impl Lexicon {
     /// let atom_weights = (0.5, 0.25, 0.25);
     /// let max_size = 50;
     ///
-    /// let term = lexicon.sample_term(&schema, &mut ctx, atom_weights, invent, variable, max_size).unwrap();
+    /// let rng = &mut thread_rng();
+    ///
+    /// let term = lexicon.sample_term(&schema, &mut ctx, atom_weights, invent, variable, max_size, rng).unwrap();
     /// # }
     /// ```
     ///
     /// [`term_rewriting::Term`]: https://docs.rs/term_rewriting/~0.3/term_rewriting/enum.Term.html
-    pub fn sample_term(
+    #[allow(clippy::too_many_arguments)]
+    pub fn sample_term<R: Rng>(
         &mut self,
         schema: &TypeSchema,
         ctx: &mut TypeContext,
@@ -289,12 +293,23 @@ impl Lexicon {
         invent: bool,
         variable: bool,
         max_size: usize,
+        rng: &mut R,
     ) -> Result<Term, SampleError> {
         let mut lex = self.0.write().expect("poisoned lexicon");
-        lex.sample_term(schema, ctx, atom_weights, invent, variable, max_size, 0)
+        lex.sample_term(
+            schema,
+            ctx,
+            atom_weights,
+            invent,
+            variable,
+            max_size,
+            0,
+            rng,
+        )
     }
     /// Sample a `Term` conditioned on a `Context` rather than a `TypeSchema`.
-    pub fn sample_term_from_context(
+    #[allow(clippy::too_many_arguments)]
+    pub fn sample_term_from_context<R: Rng>(
         &mut self,
         context: &Context,
         ctx: &mut TypeContext,
@@ -302,33 +317,45 @@ impl Lexicon {
         invent: bool,
         variable: bool,
         max_size: usize,
+        rng: &mut R,
     ) -> Result<Term, SampleError> {
         let mut lex = self.0.write().expect("poisoned lexicon");
-        lex.sample_term_from_context(context, ctx, atom_weights, invent, variable, max_size, 0)
+        lex.sample_term_from_context(
+            context,
+            ctx,
+            atom_weights,
+            invent,
+            variable,
+            max_size,
+            0,
+            rng,
+        )
     }
     /// Sample a `Rule`.
-    pub fn sample_rule(
+    pub fn sample_rule<R: Rng>(
         &mut self,
         schema: &TypeSchema,
         ctx: &mut TypeContext,
         atom_weights: (f64, f64, f64),
         invent: bool,
         max_size: usize,
+        rng: &mut R,
     ) -> Result<Rule, SampleError> {
         let mut lex = self.0.write().expect("poisoned lexicon");
-        lex.sample_rule(schema, ctx, atom_weights, invent, max_size, 0)
+        lex.sample_rule(schema, ctx, atom_weights, invent, max_size, 0, rng)
     }
     /// Sample a `Rule` conditioned on a `Context` rather than a `TypeSchema`.
-    pub fn sample_rule_from_context(
+    pub fn sample_rule_from_context<R: Rng>(
         &mut self,
         context: RuleContext,
         ctx: &mut TypeContext,
         atom_weights: (f64, f64, f64),
         invent: bool,
         max_size: usize,
+        rng: &mut R,
     ) -> Result<Rule, SampleError> {
         let mut lex = self.0.write().expect("posioned lexicon");
-        lex.sample_rule_from_context(context, ctx, atom_weights, invent, max_size, 0)
+        lex.sample_rule_from_context(context, ctx, atom_weights, invent, max_size, 0, rng)
     }
     /// Give the log probability of sampling a Term.
     pub fn logprior_term(
@@ -382,7 +409,10 @@ impl Lexicon {
         let rules2 = trs2.utrs.rules[..(trs2.utrs.len() - background_size)].to_vec();
         let ctx = &self.0.read().expect("poisoned lexicon").ctx;
         let mut trs = TRS::new(&trs1.lex, rules1, ctx)?;
-        trs.utrs.pushes(rules2).unwrap(); // hack?
+        for rule in rules2.into_iter().rev() {
+            let rule = TRS::align_variables(&trs.utrs, rule);
+            trs.utrs.push(rule).unwrap(); // hack?
+        }
         if self.0.read().expect("poisoned lexicon").deterministic {
             trs.utrs.make_deterministic(rng);
         }
@@ -470,7 +500,7 @@ impl Lex {
             .unwrap_or_else(Vec::new))
     }
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
-    fn place_atom(
+    fn place_atom<R: Rng>(
         &mut self,
         atom: &Atom,
         arg_types: Vec<Type>,
@@ -480,6 +510,7 @@ impl Lex {
         max_size: usize,
         size: usize,
         vars: &mut Vec<Variable>,
+        rng: &mut R,
     ) -> Result<Term, SampleError> {
         let mut size = size;
         match *atom {
@@ -504,6 +535,7 @@ impl Lex {
                             max_size,
                             size,
                             vars,
+                            rng,
                         )
                         .map_err(|_| SampleError::Subterm)
                         .and_then(|subterm| {
@@ -691,7 +723,7 @@ impl Lex {
     }
 
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
-    pub fn sample_term(
+    pub fn sample_term<R: Rng>(
         &mut self,
         schema: &TypeSchema,
         ctx: &mut TypeContext,
@@ -700,6 +732,7 @@ impl Lex {
         variable: bool,
         max_size: usize,
         size: usize,
+        rng: &mut R,
     ) -> Result<Term, SampleError> {
         self.sample_term_internal(
             schema,
@@ -710,10 +743,11 @@ impl Lex {
             max_size,
             size,
             &mut vec![],
+            rng,
         )
     }
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
-    pub fn sample_term_internal(
+    pub fn sample_term_internal<R: Rng>(
         &mut self,
         schema: &TypeSchema,
         ctx: &mut TypeContext,
@@ -723,13 +757,14 @@ impl Lex {
         max_size: usize,
         size: usize,
         vars: &mut Vec<Variable>,
+        rng: &mut R,
     ) -> Result<Term, SampleError> {
         if size >= max_size {
             return Err(SampleError::SizeExceeded(size, max_size));
         }
         let tp = schema.instantiate(ctx);
         let (atom, arg_types) =
-            self.prepare_option(vars, atom_weights, invent, variable, &tp, ctx)?;
+            self.prepare_option(vars, atom_weights, invent, variable, &tp, ctx, rng)?;
         self.place_atom(
             &atom,
             arg_types,
@@ -739,9 +774,11 @@ impl Lex {
             max_size,
             size,
             vars,
+            rng,
         )
     }
-    fn prepare_option(
+    #[allow(clippy::too_many_arguments)]
+    fn prepare_option<R: Rng>(
         &mut self,
         vars: &mut Vec<Variable>,
         (vw, cw, ow): (f64, f64, f64),
@@ -749,6 +786,7 @@ impl Lex {
         variable: bool,
         tp: &Type,
         ctx: &mut TypeContext,
+        rng: &mut R,
     ) -> Result<(Atom, Vec<Type>), SampleError> {
         // create options
         let ops = self.signature.operators();
@@ -769,7 +807,7 @@ impl Lex {
             })
             .collect();
         // iterate through a weighted permutation to find an option that typechecks
-        for option in weighted_permutation(&options, &weights, None) {
+        for option in weighted_permutation(&options, &weights, None, rng) {
             let atom = option.unwrap_or_else(|| {
                 let new_var = self.invent_variable(tp);
                 vars.push(new_var.clone());
@@ -783,7 +821,7 @@ impl Lex {
         Err(SampleError::OptionsExhausted)
     }
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
-    pub fn sample_term_from_context(
+    pub fn sample_term_from_context<R: Rng>(
         &mut self,
         context: &Context,
         ctx: &mut TypeContext,
@@ -792,6 +830,7 @@ impl Lex {
         variable: bool,
         max_size: usize,
         size: usize,
+        rng: &mut R,
     ) -> Result<Term, SampleError> {
         let mut map = HashMap::new();
         let context = context.clone();
@@ -810,12 +849,14 @@ impl Lex {
                 max_size,
                 size,
                 &mut context_vars,
+                rng,
             )?;
             context.replace(&p, Context::from(subterm));
         }
         context.to_term().or(Err(SampleError::Subterm))
     }
-    pub fn sample_rule(
+    #[allow(clippy::too_many_arguments)]
+    pub fn sample_rule<R: Rng>(
         &mut self,
         schema: &TypeSchema,
         ctx: &mut TypeContext,
@@ -823,6 +864,7 @@ impl Lex {
         invent: bool,
         max_size: usize,
         size: usize,
+        rng: &mut R,
     ) -> Result<Rule, SampleError> {
         let orig_self = self.clone();
         let orig_ctx = ctx.clone();
@@ -837,6 +879,7 @@ impl Lex {
                 max_size,
                 size,
                 &mut vars,
+                rng,
             )?;
             let rhs = self.sample_term_internal(
                 schema,
@@ -847,6 +890,7 @@ impl Lex {
                 max_size,
                 size,
                 &mut vars,
+                rng,
             )?;
             if let Some(rule) = Rule::new(lhs, vec![rhs]) {
                 return Ok(rule);
@@ -856,7 +900,8 @@ impl Lex {
             }
         }
     }
-    pub fn sample_rule_from_context(
+    #[allow(clippy::too_many_arguments)]
+    pub fn sample_rule_from_context<R: Rng>(
         &mut self,
         mut context: RuleContext,
         ctx: &mut TypeContext,
@@ -864,6 +909,7 @@ impl Lex {
         invent: bool,
         max_size: usize,
         size: usize,
+        rng: &mut R,
     ) -> Result<Rule, SampleError> {
         let mut map = HashMap::new();
         let hole_places = context.holes();
@@ -882,6 +928,7 @@ impl Lex {
                 max_size,
                 size,
                 &mut context_vars,
+                rng,
             )?;
             context = context
                 .replace(&p, Context::from(subterm))
@@ -1092,6 +1139,29 @@ impl GP for Lexicon {
             })
             .collect()
     }
+    fn size(&self, expr: &Self::Expression) -> usize {
+        expr.size()
+    }
+    fn depth(&self, expr: &Self::Expression) -> usize {
+        expr.depth()
+    }
+    /// The number of rules in either TRS without an alpha-equivalent rule in the other.
+    fn distance(&self, expr1: &Self::Expression, expr2: &Self::Expression) -> f64 {
+        let unshared = |trs1: &TRS, trs2: &TRS| {
+            trs1.utrs
+                .rules
+                .iter()
+                .filter(|r1| {
+                    !trs2
+                        .utrs
+                        .rules
+                        .iter()
+                        .any(|r2| Rule::alpha(r1, r2).is_some())
+                })
+                .count()
+        };
+        (unshared(expr1, expr2) + unshared(expr2, expr1)) as f64
+    }
 
     fn validate_offspring(
         &self,
@@ -1117,3 +1187,19 @@ impl GP for Lexicon {
         });
     }
 }
+impl GPCheckpoint for Lexicon {
+    type ParseError = ParseError;
+    /// Rules are written in order, separated by spaces.
+    fn display_expression(&self, expr: &Self::Expression) -> String {
+        expr.to_string().replace('\n', " ")
+    }
+    /// Parsing adds the rules' variables to this lexicon, which is shared with all of its clones,
+    /// so that the parsed TRS belongs to it like the rest of the population. This cannot happen in
+    /// a scratch copy, whose variables would not belong to the lexicon. Variables are added as
+    /// each rule is read, so they remain even if a later rule fails to parse.
+    fn parse_expression(&self, s: &str) -> Result<Self::Expression, Self::ParseError> {
+        // not a copy: the new handle refers to the same underlying lexicon.
+        let mut lex = Lexicon(Arc::clone(&self.0));
+        parse_trs(s, &mut lex)
+    }
+}
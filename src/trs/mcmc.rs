@@ -0,0 +1,626 @@
+//! Metropolis-Hastings sampling of [`TRS`] hypotheses.
+//!
+//! [`TRS`]: struct.TRS.html
+
+use polytype::TypeSchema;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use std::f64;
+use std::iter::once;
+use term_rewriting::{Rule, Term};
+
+use super::{ModelParams, SampleError, TRS};
+use utils::logsumexp;
+
+/// Relative weights of the moves used to propose a new [`TRS`] from the current one.
+///
+/// [`TRS`]: struct.TRS.html
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ProposalWeights {
+    /// The weight of [`TRS::add_rule`].
+    ///
+    /// [`TRS::add_rule`]: struct.TRS.html#method.add_rule
+    pub add_rule: f64,
+    /// The weight of [`TRS::delete_rule`].
+    ///
+    /// [`TRS::delete_rule`]: struct.TRS.html#method.delete_rule
+    pub delete_rule: f64,
+    /// The weight of [`TRS::randomly_move_rule`].
+    ///
+    /// [`TRS::randomly_move_rule`]: struct.TRS.html#method.randomly_move_rule
+    pub move_rule: f64,
+    /// The weight of [`TRS::local_difference`].
+    ///
+    /// [`TRS::local_difference`]: struct.TRS.html#method.local_difference
+    pub local_difference: f64,
+    /// The weight of [`TRS::swap_lhs_and_rhs`].
+    ///
+    /// [`TRS::swap_lhs_and_rhs`]: struct.TRS.html#method.swap_lhs_and_rhs
+    pub swap_lhs_and_rhs: f64,
+}
+impl Default for ProposalWeights {
+    fn default() -> ProposalWeights {
+        ProposalWeights {
+            add_rule: 1.0,
+            delete_rule: 1.0,
+            move_rule: 1.0,
+            local_difference: 1.0,
+            swap_lhs_and_rhs: 1.0,
+        }
+    }
+}
+impl ProposalWeights {
+    fn as_vec(&self) -> Vec<f64> {
+        vec![
+            self.add_rule,
+            self.delete_rule,
+            self.move_rule,
+            self.local_difference,
+            self.swap_lhs_and_rhs,
+        ]
+    }
+}
+
+/// Parameters for sampling with [`TRS::mcmc`].
+///
+/// [`TRS::mcmc`]: struct.TRS.html#method.mcmc
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCMCParams {
+    /// Parameters of the model whose [`posterior`] is sampled.
+    ///
+    /// [`posterior`]: struct.TRS.html#method.posterior
+    pub model: ModelParams,
+    /// The relative weights of each proposal move.
+    pub proposals: ProposalWeights,
+    /// The weight to assign variables, constants, and non-constant operators, respectively, when
+    /// sampling a rule to add.
+    pub atom_weights: (f64, f64, f64),
+    /// The maximum number of nodes a sampled `Term` can have without failing.
+    pub max_sample_size: usize,
+    /// The number of independent chains.
+    pub n_chains: usize,
+    /// The number of initial steps of each chain which are discarded.
+    pub burn_in: usize,
+    /// The number of steps between retained samples.
+    pub thinning: usize,
+    /// The number of samples retained from each chain.
+    pub n_samples: usize,
+}
+impl Default for MCMCParams {
+    fn default() -> MCMCParams {
+        MCMCParams {
+            model: ModelParams::default(),
+            proposals: ProposalWeights::default(),
+            atom_weights: (0.5, 0.25, 0.25),
+            max_sample_size: 20,
+            n_chains: 4,
+            burn_in: 100,
+            thinning: 1,
+            n_samples: 100,
+        }
+    }
+}
+
+/// The retained samples of a Markov chain produced by [`TRS::mcmc`].
+///
+/// [`TRS::mcmc`]: struct.TRS.html#method.mcmc
+#[derive(Debug, Clone)]
+pub struct Chain {
+    /// Each retained sample with its log posterior, in the order visited.
+    pub samples: Vec<(TRS, f64)>,
+    /// The number of steps taken, including burn-in.
+    pub n_steps: usize,
+    /// The number of steps whose proposal was accepted.
+    pub n_accepted: usize,
+}
+impl Chain {
+    /// The fraction of steps whose proposal was accepted.
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.n_steps == 0 {
+            0.0
+        } else {
+            self.n_accepted as f64 / self.n_steps as f64
+        }
+    }
+    /// The retained sample with the greatest log posterior.
+    pub fn best(&self) -> Option<&(TRS, f64)> {
+        self.samples
+            .iter()
+            .max_by(|(_, x), (_, y)| x.partial_cmp(y).expect("found NaN"))
+    }
+}
+
+/// The Gelman-Rubin potential scale reduction factor ("R-hat") of the log posteriors of `chains`.
+///
+/// Values near `1.0` suggest the chains have converged to the same distribution. Returns `None`
+/// with fewer than two chains, fewer than two samples in the shortest chain, or non-finite log
+/// posteriors.
+pub fn potential_scale_reduction(chains: &[Chain]) -> Option<f64> {
+    let n = chains.iter().map(|chain| chain.samples.len()).min()?;
+    let m = chains.len();
+    if m < 2 || n < 2 {
+        return None;
+    }
+    let traces: Vec<Vec<f64>> = chains
+        .iter()
+        .map(|chain| chain.samples[..n].iter().map(|(_, lp)| *lp).collect())
+        .collect();
+    if traces.iter().flatten().any(|lp| !lp.is_finite()) {
+        return None;
+    }
+    let means: Vec<f64> = traces
+        .iter()
+        .map(|trace| trace.iter().sum::<f64>() / n as f64)
+        .collect();
+    let grand_mean = means.iter().sum::<f64>() / m as f64;
+    let between = n as f64 / (m - 1) as f64
+        * means
+            .iter()
+            .map(|mean| (mean - grand_mean).powi(2))
+            .sum::<f64>();
+    let within = traces
+        .iter()
+        .zip(&means)
+        .map(|(trace, mean)| {
+            trace.iter().map(|lp| (lp - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        })
+        .sum::<f64>()
+        / m as f64;
+    if within == 0.0 {
+        return if between == 0.0 { Some(1.0) } else { None };
+    }
+    let variance = (n - 1) as f64 / n as f64 * within + between / n as f64;
+    Some((variance / within).sqrt())
+}
+
+impl TRS {
+    /// Sample from the [`posterior`] given `data` by Metropolis-Hastings, running
+    /// [`n_chains`] chains which each start from this `TRS`.
+    ///
+    /// Proposals are made by the moves weighted in [`proposals`]. The Hastings ratio accounts for
+    /// every move which could have produced a proposal or could reverse it. The probability of
+    /// adding a particular rule is taken to be its [`Lexicon::logprior_rule`], which is exact
+    /// when the lexicon's templates are unconstrained. A move which fails to produce a
+    /// hypothesis is rejected.
+    ///
+    /// [`posterior`]: #method.posterior
+    /// [`n_chains`]: struct.MCMCParams.html#structfield.n_chains
+    /// [`proposals`]: struct.MCMCParams.html#structfield.proposals
+    /// [`Lexicon::logprior_rule`]: struct.Lexicon.html#method.logprior_rule
+    pub fn mcmc<R: Rng>(&self, data: &[Rule], params: &MCMCParams, rng: &mut R) -> Vec<Chain> {
+        (0..params.n_chains)
+            .map(|_| self.run_chain(data, params, rng))
+            .collect()
+    }
+
+    fn run_chain<R: Rng>(&self, data: &[Rule], params: &MCMCParams, rng: &mut R) -> Chain {
+        let moves =
+            WeightedIndex::new(params.proposals.as_vec()).expect("invalid proposal weights");
+        let thinning = params.thinning.max(1);
+        let n_steps = params.burn_in + params.n_samples * thinning;
+        let mut current = self.clone();
+        let mut current_lp = current.posterior(data, params.model);
+        let mut samples = Vec::with_capacity(params.n_samples);
+        let mut n_accepted = 0;
+        for step in 0..n_steps {
+            if let Ok(proposal) = current.propose(moves.sample(rng), params, rng) {
+                let proposal_lp = proposal.posterior(data, params.model);
+                let log_acceptance = proposal_lp - current_lp
+                    + proposal.log_proposal(&current, params)
+                    - current.log_proposal(&proposal, params);
+                if log_acceptance >= 0.0 || rng.gen::<f64>().ln() < log_acceptance {
+                    current = proposal;
+                    current_lp = proposal_lp;
+                    n_accepted += 1;
+                }
+            }
+            if step >= params.burn_in && (step - params.burn_in) % thinning == thinning - 1 {
+                samples.push((current.clone(), current_lp));
+            }
+        }
+        Chain {
+            samples,
+            n_steps,
+            n_accepted,
+        }
+    }
+
+    fn propose<R: Rng>(
+        &self,
+        move_idx: usize,
+        params: &MCMCParams,
+        rng: &mut R,
+    ) -> Result<TRS, SampleError> {
+        match move_idx {
+            0 => {
+                let templates = self
+                    .lex
+                    .0
+                    .read()
+                    .expect("poisoned lexicon")
+                    .templates
+                    .clone();
+                self.add_rule(&templates, params.atom_weights, params.max_sample_size, rng)
+            }
+            1 => self.delete_rule(rng),
+            2 => self.randomly_move_rule(rng),
+            3 => self.local_difference(rng),
+            _ => self.swap_lhs_and_rhs(rng),
+        }
+    }
+
+    /// The log probability of proposing `other` from this `TRS`.
+    ///
+    /// Rather than making every outcome of every move, each move only checks the outcomes which
+    /// could have produced `other`.
+    fn log_proposal(&self, other: &TRS, params: &MCMCParams) -> f64 {
+        let weights = params.proposals.as_vec();
+        let z: f64 = weights.iter().sum();
+        let lps = [
+            self.log_add_proposal(other, params),
+            self.log_delete_proposal(other),
+            self.log_move_proposal(other),
+            self.log_rewrite_proposal(other, |rule| {
+                Some(TRS::local_difference_helper(rule)).filter(|rules| !rules.is_empty())
+            }),
+            self.log_rewrite_proposal(other, |rule| TRS::swap_rule_helper(rule).ok()),
+        ];
+        let lps: Vec<f64> = weights
+            .iter()
+            .zip(&lps)
+            .filter(|(&w, _)| w > 0.0)
+            .map(|(w, lp)| (w / z).ln() + lp)
+            .collect();
+        logsumexp(&lps)
+    }
+
+    fn num_background(&self) -> usize {
+        self.lex
+            .0
+            .read()
+            .expect("poisoned lexicon")
+            .background
+            .len()
+    }
+
+    /// The log probability of proposing `other` by adding a rule to this `TRS`.
+    ///
+    /// An added rule ends up first, merged with any rule that already has its LHS.
+    fn log_add_proposal(&self, other: &TRS, params: &MCMCParams) -> f64 {
+        let rules = &self.utrs.rules;
+        let first = match other.utrs.rules.first() {
+            Some(first) => first,
+            None => return f64::NEG_INFINITY,
+        };
+        let existing = rules
+            .iter()
+            .position(|rule| Term::alpha(&first.lhs, &rule.lhs).is_some());
+        let lps: Vec<f64> = first
+            .clauses()
+            .into_iter()
+            .filter(|clause| match existing {
+                Some(idx) => {
+                    let mut merged = rules[idx].clone();
+                    merged.merge(&TRS::align_variables(&self.utrs, clause.clone()));
+                    let rest = rules.iter().enumerate().filter(|&(i, _)| i != idx);
+                    alpha_equivalent(once(&merged).chain(rest.map(|(_, r)| r)), &other.utrs.rules)
+                }
+                None => alpha_equivalent(once(clause).chain(rules), &other.utrs.rules),
+            })
+            .map(|clause| {
+                let mut ctx = self.ctx.clone();
+                let schema = TypeSchema::Monotype(ctx.new_variable());
+                self.lex
+                    .logprior_rule(&clause, &schema, &mut ctx, params.atom_weights, true)
+                    .unwrap_or(f64::NEG_INFINITY)
+            })
+            .collect();
+        logsumexp(&lps)
+    }
+
+    /// The log probability of proposing `other` by deleting a clause from this `TRS`.
+    fn log_delete_proposal(&self, other: &TRS) -> f64 {
+        let rules = &self.utrs.rules;
+        let deletable: Vec<Rule> = {
+            let background = &self.lex.0.read().expect("poisoned lexicon").background;
+            self.utrs
+                .clauses()
+                .into_iter()
+                .filter(|c| !background.contains(c))
+                .collect()
+        };
+        if deletable.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+        let len = other.utrs.rules.len();
+        if len != rules.len() && len + 1 != rules.len() {
+            return f64::NEG_INFINITY;
+        }
+        let hits = deletable
+            .iter()
+            .filter(|clause| {
+                // the clause is discarded from the first rule with its LHS.
+                let idx = match rules
+                    .iter()
+                    .position(|rule| Term::alpha(&clause.lhs, &rule.lhs).is_some())
+                {
+                    Some(idx) => idx,
+                    None => return false,
+                };
+                let mut reduced = rules[idx].clone();
+                reduced.discard(clause);
+                let expected = rules.iter().enumerate().filter_map(|(i, rule)| {
+                    if i != idx {
+                        Some(rule)
+                    } else if reduced.is_empty() {
+                        None
+                    } else {
+                        Some(&reduced)
+                    }
+                });
+                alpha_equivalent(expected, &other.utrs.rules)
+            })
+            .count();
+        (hits as f64 / deletable.len() as f64).ln()
+    }
+
+    /// The log probability of proposing `other` by moving a rule of this `TRS`.
+    ///
+    /// Moving a rule from `i` to `j` changes exactly the rules between them, so only the first
+    /// and last rules which differ from `other` could have been moved.
+    fn log_move_proposal(&self, other: &TRS) -> f64 {
+        let rules = &self.utrs.rules;
+        let num_background = self.num_background();
+        if num_background + 1 >= rules.len() || rules.len() != other.utrs.rules.len() {
+            return f64::NEG_INFINITY;
+        }
+        let k = rules.len() - num_background;
+        let differs = |i: &usize| Rule::alpha(&rules[*i], &other.utrs.rules[*i]).is_none();
+        let (a, b) = match (
+            (0..rules.len()).find(&differs),
+            (0..rules.len()).rev().find(&differs),
+        ) {
+            (Some(a), Some(b)) if a >= num_background => (a, b),
+            _ => return f64::NEG_INFINITY,
+        };
+        let hits = [(a, b), (b, a)]
+            .iter()
+            .filter(|&&(i, j)| {
+                let mut moved: Vec<&Rule> = rules.iter().collect();
+                let rule = moved.remove(i);
+                moved.insert(j, rule);
+                alpha_equivalent(moved, &other.utrs.rules)
+            })
+            .count();
+        (hits as f64 / (k * (k - 1)) as f64).ln()
+    }
+
+    /// The log probability of proposing `other` by replacing a rule of this `TRS` with the rules
+    /// given by `rewrite`, which are inserted immediately after the background.
+    fn log_rewrite_proposal<F>(&self, other: &TRS, rewrite: F) -> f64
+    where
+        F: Fn(&Rule) -> Option<Vec<Rule>>,
+    {
+        let rules = &self.utrs.rules;
+        let num_background = self.num_background();
+        if num_background >= rules.len() {
+            return f64::NEG_INFINITY;
+        }
+        let hits = (num_background..rules.len())
+            .filter(|&idx| match rewrite(&rules[idx]) {
+                Some(ref new_rules)
+                    if rules.len() - 1 + new_rules.len() == other.utrs.rules.len() =>
+                {
+                    let rest = rules
+                        .iter()
+                        .enumerate()
+                        .skip(num_background)
+                        .filter(|&(i, _)| i != idx)
+                        .map(|(_, rule)| rule);
+                    let expected = rules[..num_background].iter().chain(new_rules).chain(rest);
+                    alpha_equivalent(expected, &other.utrs.rules)
+                }
+                _ => false,
+            })
+            .count();
+        (hits as f64 / (rules.len() - num_background) as f64).ln()
+    }
+}
+
+/// Whether two lists of rules are the same up to renaming the variables of each rule.
+fn alpha_equivalent<'a, I>(rules1: I, rules2: &[Rule]) -> bool
+where
+    I: IntoIterator<Item = &'a Rule>,
+{
+    let mut rules2 = rules2.iter();
+    rules1.into_iter().all(|r1| {
+        rules2
+            .next()
+            .is_some_and(|r2| Rule::alpha(r1, r2).is_some())
+    }) && rules2.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use polytype::Context as TypeContext;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::super::{parse_lexicon, parse_rule, parse_trs, Lexicon};
+    use super::*;
+
+    fn lexicon() -> Lexicon {
+        parse_lexicon(
+            "ZERO: int; SUCC: int -> int; PLUS: int -> int -> int;",
+            "",
+            "[!] = [!];",
+            false,
+            TypeContext::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn log_proposal_test() {
+        let mut lex = lexicon();
+        let trs = parse_trs("PLUS(ZERO x_) = x_; SUCC(ZERO) = ZERO;", &mut lex).unwrap();
+        let moved = parse_trs("SUCC(ZERO) = ZERO; PLUS(ZERO x_) = x_;", &mut lex).unwrap();
+        let deleted = parse_trs("SUCC(ZERO) = ZERO;", &mut lex).unwrap();
+        let params = MCMCParams::default();
+        let close = |x: f64, y: f64| (x - y).abs() < 1e-9;
+
+        // moving either rule, taking the local difference of the second, or adding the second
+        // again (which moves it to the front), each of five moves
+        assert_eq!(trs.log_move_proposal(&moved), 0.0);
+        let local_difference = trs.log_rewrite_proposal(&moved, |rule| {
+            Some(TRS::local_difference_helper(rule)).filter(|rules| !rules.is_empty())
+        });
+        assert!(close(local_difference, 0.5f64.ln()));
+        assert_eq!(trs.log_delete_proposal(&moved), f64::NEG_INFINITY);
+        let add = trs.log_add_proposal(&moved, &params);
+        assert!(add < 0.0);
+        let forward = trs.log_proposal(&moved, &params);
+        assert!(close(forward, (0.2 * (1.0 + 0.5 + add.exp())).ln()));
+
+        // one of five moves, then one of two rules
+        let forward = trs.log_proposal(&deleted, &params);
+        assert!(close(forward, (0.2f64 * 0.5).ln()));
+        // one of five moves, then the prior of the added rule
+        let rule = trs.utrs.rules[0].clone();
+        let mut ctx = trs.ctx.clone();
+        let schema = TypeSchema::Monotype(ctx.new_variable());
+        let logprior = lex
+            .logprior_rule(&rule, &schema, &mut ctx, params.atom_weights, true)
+            .unwrap();
+        let backward = deleted.log_proposal(&trs, &params);
+        assert!(close(backward, 0.2f64.ln() + logprior));
+    }
+
+    #[test]
+    fn moves_without_rules_test() {
+        let mut lex = lexicon();
+        let trs = parse_trs("", &mut lex).unwrap();
+        let one = parse_trs("SUCC(ZERO) = ZERO;", &mut lex).unwrap();
+        let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+        assert!(trs.randomly_move_rule(rng).is_err());
+        assert!(trs.local_difference(rng).is_err());
+        assert!(trs.swap_lhs_and_rhs(rng).is_err());
+        assert!(one.randomly_move_rule(rng).is_err());
+    }
+
+    #[test]
+    fn add_alpha_equivalent_rule_test() {
+        let mut lex = lexicon();
+        let mut ctx = lex.context();
+        let trs = parse_trs("PLUS(x_ y_) = x_;", &mut lex).unwrap();
+        let rule = parse_rule("PLUS(z_ w_) = w_", &mut lex, &mut ctx).unwrap();
+
+        let mut utrs = trs.utrs.clone();
+        utrs.push(TRS::align_variables(&trs.utrs, rule)).unwrap();
+        assert_eq!(utrs.len(), 1);
+        let clauses = utrs.clauses();
+        assert_eq!(clauses.len(), 2);
+        assert_eq!(clauses[1].display(), "PLUS(x_ y_) = y_");
+    }
+
+    #[test]
+    fn mcmc_test() {
+        let mut lex = lexicon();
+        let mut ctx = lex.context();
+        let data = vec![
+            parse_rule("PLUS(ZERO ZERO) = ZERO", &mut lex, &mut ctx).unwrap(),
+            parse_rule("PLUS(ZERO SUCC(ZERO)) = SUCC(ZERO)", &mut lex, &mut ctx).unwrap(),
+        ];
+        let trs = parse_trs("", &mut lex).unwrap();
+        let params = MCMCParams {
+            model: ModelParams {
+                p_partial: 0.01,
+                ..ModelParams::default()
+            },
+            max_sample_size: 5,
+            n_chains: 3,
+            burn_in: 20,
+            thinning: 2,
+            n_samples: 10,
+            ..MCMCParams::default()
+        };
+        let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+        let chains = trs.mcmc(&data, &params, rng);
+
+        assert_eq!(chains.len(), 3);
+        for chain in &chains {
+            assert_eq!(chain.n_steps, 40);
+            assert_eq!(chain.samples.len(), 10);
+            assert!(chain.best().unwrap().1.is_finite());
+        }
+    }
+
+    #[test]
+    fn mcmc_two_states_test() {
+        // the only move swaps the two rules, and the data favor the first order three to one
+        let mut lex = lexicon();
+        let mut ctx = lex.context();
+        let data =
+            vec![parse_rule("PLUS(ZERO SUCC(ZERO)) = SUCC(ZERO)", &mut lex, &mut ctx).unwrap()];
+        let trs = parse_trs("PLUS(ZERO x_) = x_; PLUS(x_ y_) = ZERO;", &mut lex).unwrap();
+        let swapped = parse_trs("PLUS(x_ y_) = ZERO; PLUS(ZERO x_) = x_;", &mut lex).unwrap();
+        let params = MCMCParams {
+            model: ModelParams {
+                p_partial: 0.25,
+                p_observe: 1.0,
+                max_steps: 10,
+                ..ModelParams::default()
+            },
+            proposals: ProposalWeights {
+                add_rule: 0.0,
+                delete_rule: 0.0,
+                move_rule: 1.0,
+                local_difference: 0.0,
+                swap_lhs_and_rhs: 0.0,
+            },
+            n_chains: 1,
+            burn_in: 0,
+            n_samples: 4000,
+            ..MCMCParams::default()
+        };
+        let ratio =
+            (swapped.posterior(&data, params.model) - trs.posterior(&data, params.model)).exp();
+        assert!((ratio - 1.0 / 3.0).abs() < 1e-9);
+        let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+        let chain = &trs.mcmc(&data, &params, rng)[0];
+
+        // the chain spends 1 / (1 + ratio) of its time in the first state, which always accepts
+        // a swap with probability ratio, and the rest in the second, which always accepts.
+        let n_first = chain
+            .samples
+            .iter()
+            .filter(|(sample, _)| alpha_equivalent(&sample.utrs.rules, &trs.utrs.rules))
+            .count();
+        let frequency = n_first as f64 / chain.samples.len() as f64;
+        assert!((frequency - 0.75).abs() < 0.05);
+        assert!((chain.acceptance_rate() - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn potential_scale_reduction_test() {
+        let mut lex = lexicon();
+        let trs = parse_trs("SUCC(ZERO) = ZERO;", &mut lex).unwrap();
+        let chain = |offset: f64| Chain {
+            samples: (0..100)
+                .map(|i| (trs.clone(), offset - (i % 2) as f64))
+                .collect(),
+            n_steps: 100,
+            n_accepted: 50,
+        };
+
+        assert_eq!(potential_scale_reduction(&[chain(0.0)]), None);
+        // converged chains
+        let r_hat = potential_scale_reduction(&[chain(0.0), chain(0.0), chain(0.0)]).unwrap();
+        assert!((r_hat - 1.0).abs() < 0.01);
+        // chains stuck apart
+        let r_hat = potential_scale_reduction(&[chain(0.0), chain(-10.0), chain(0.0)]).unwrap();
+        assert!(r_hat > 2.0);
+    }
+}
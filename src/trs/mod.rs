@@ -46,9 +46,11 @@
 //! ```
 
 mod lexicon;
+mod mcmc;
 pub mod parser;
 mod rewrite;
 pub use self::lexicon::{GeneticParams, Lexicon};
+pub use self::mcmc::{potential_scale_reduction, Chain, MCMCParams, ProposalWeights};
 pub use self::parser::{
     parse_context, parse_lexicon, parse_rule, parse_rulecontext, parse_templates, parse_trs,
 };
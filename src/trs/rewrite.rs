@@ -98,6 +98,27 @@ impl TRS {
         self.utrs.size()
     }
 
+    /// The depth of the deepest term in the underlying [`term_rewriting::TRS`].
+    ///
+    /// [`term_rewriting::TRS`]: https://docs.rs/term_rewriting/~0.3/term_rewriting/struct.TRS.html
+    pub fn depth(&self) -> usize {
+        self.utrs
+            .rules
+            .iter()
+            .flat_map(|rule| once(&rule.lhs).chain(&rule.rhs))
+            .map(TRS::term_depth)
+            .max()
+            .unwrap_or(0)
+    }
+    fn term_depth(term: &Term) -> usize {
+        match *term {
+            Term::Variable(_) => 1,
+            Term::Application { ref args, .. } => {
+                1 + args.iter().map(TRS::term_depth).max().unwrap_or(0)
+            }
+        }
+    }
+
     /// The length of the underlying [`term_rewriting::TRS`].
     ///
     /// [`term_rewriting::TRS`]: https://docs.rs/term_rewriting/~0.3/term_rewriting/struct.TRS.html#method.size
@@ -246,7 +267,9 @@ impl TRS {
             atom_weights,
             true,
             max_size,
+            rng,
         )?;
+        let rule = TRS::align_variables(&trs.utrs, rule);
         trs.lex
             .0
             .write()
@@ -255,6 +278,20 @@ impl TRS {
         trs.utrs.push(rule)?;
         Ok(trs)
     }
+    /// The rule with its variables renamed to those of the rule in `utrs` with an alpha-equivalent
+    /// LHS, if there is one. Adding the rule merges it into that rule, and [`Rule::merge`] does not
+    /// rename variables, so the merged clauses would otherwise use variables missing from the LHS.
+    ///
+    /// [`Rule::merge`]: https://docs.rs/term_rewriting/~0.5/term_rewriting/struct.Rule.html#method.merge
+    pub(crate) fn align_variables(utrs: &UntypedTRS, rule: Rule) -> Rule {
+        let sub = utrs
+            .get(&rule.lhs)
+            .and_then(|(_, existing)| Term::alpha(&rule.lhs, &existing.lhs));
+        match sub {
+            Some(sub) => rule.substitute(&sub),
+            None => rule,
+        }
+    }
     /// Delete a rule from the rewrite system if possible. Background knowledge
     /// cannot be deleted.
     pub fn delete_rule<R: Rng>(&self, rng: &mut R) -> Result<TRS, SampleError> {
@@ -335,7 +372,7 @@ impl TRS {
         let num_rules = self.len();
         let background = &self.lex.0.read().expect("poisoned lexicon").background;
         let num_background = background.len();
-        if num_background < num_rules - 1 {
+        if num_background + 1 < num_rules {
             let i = rng.gen_range(num_background, num_rules);
             let mut j = rng.gen_range(num_background, num_rules);
             while j == i {
@@ -425,7 +462,7 @@ impl TRS {
     }
     /// Given a rule that has similar terms in the lhs and rhs,
     /// returns a list of rules where each similarity is removed one at a time
-    fn local_difference_helper(rule: &Rule) -> Vec<Rule> {
+    pub(crate) fn local_difference_helper(rule: &Rule) -> Vec<Rule> {
         if let Some(rhs) = rule.rhs() {
             TRS::find_differences(&rule.lhs, &rhs)
                 .into_iter()
@@ -557,7 +594,7 @@ impl TRS {
             .expect("poisoned lexicon")
             .background
             .len();
-        if num_background <= num_rules {
+        if num_background < num_rules {
             let idx = rng.gen_range(num_background, num_rules);
             let mut trs = self.clone();
             let new_rules = TRS::swap_rule_helper(&trs.utrs.rules[idx])?;
@@ -570,7 +607,7 @@ impl TRS {
     }
     /// returns a vector of a rules with each rhs being the lhs of the original
     /// rule and each lhs is each rhs of the original.
-    fn swap_rule_helper(rule: &Rule) -> Result<Vec<Rule>, SampleError> {
+    pub(crate) fn swap_rule_helper(rule: &Rule) -> Result<Vec<Rule>, SampleError> {
         let rules = rule
             .clauses()
             .iter()
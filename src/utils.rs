@@ -1,25 +1,35 @@
 use rand::{
     distributions::{Distribution, Uniform},
-    thread_rng,
+    Rng,
 };
 use std::cmp;
 use std::f64;
 
+/// The log of the sum of the exponentials of `lps`, which is negative infinity if every element is
+/// (or if there are none).
 #[inline(always)]
 pub fn logsumexp(lps: &[f64]) -> f64 {
     let largest = lps.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if largest == f64::NEG_INFINITY {
+        return f64::NEG_INFINITY;
+    }
     let x = lps.iter().map(|lp| (lp - largest).exp()).sum::<f64>().ln();
     largest + x
 }
 
-pub fn weighted_permutation<T: Clone>(xs: &[T], ws: &[f64], n: Option<usize>) -> Vec<T> {
+pub fn weighted_permutation<R: Rng, T: Clone>(
+    xs: &[T],
+    ws: &[f64],
+    n: Option<usize>,
+    rng: &mut R,
+) -> Vec<T> {
     let mut ws = ws.to_vec();
     let mut idxs: Vec<_> = (0..(ws.len())).collect();
     let mut permutation = vec![];
     let length = cmp::min(n.unwrap_or_else(|| xs.len()), xs.len());
     while permutation.len() < length {
         let jidxs: Vec<_> = idxs.iter().cloned().enumerate().collect();
-        let &(jdx, idx): &(usize, usize) = weighted_sample(&jidxs, &ws);
+        let &(jdx, idx): &(usize, usize) = weighted_sample(&jidxs, &ws, rng);
         permutation.push(xs[idx].clone());
         idxs.remove(jdx);
         ws.remove(jdx);
@@ -28,10 +38,10 @@ pub fn weighted_permutation<T: Clone>(xs: &[T], ws: &[f64], n: Option<usize>) ->
 }
 
 /// Samples an item from `xs` given the weights `ws`.
-pub fn weighted_sample<'a, T>(xs: &'a [T], ws: &[f64]) -> &'a T {
+pub fn weighted_sample<'a, R: Rng, T>(xs: &'a [T], ws: &[f64], rng: &mut R) -> &'a T {
     assert_eq!(xs.len(), ws.len(), "weighted sample given invalid inputs");
     let total = ws.iter().fold(0f64, |acc, x| acc + x);
-    let threshold: f64 = Uniform::new(0f64, total).sample(&mut thread_rng());
+    let threshold: f64 = Uniform::new(0f64, total).sample(rng);
     let mut cum = 0f64;
     for (wp, x) in ws.iter().zip(xs) {
         cum += *wp;
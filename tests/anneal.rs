@@ -0,0 +1,178 @@
+#[macro_use]
+extern crate polytype;
+extern crate programinduction;
+extern crate rand;
+use polytype::Context as TypeContext;
+use programinduction::lambda::{self, Language};
+use programinduction::pcfg::{self, Grammar, Rule};
+use programinduction::trs;
+use programinduction::{anneal, temper, AnnealParams, Schedule, Task, TemperingParams};
+use rand::{rngs::SmallRng, SeedableRng};
+
+fn arith() -> Grammar {
+    Grammar::new(
+        tp!(EXPR),
+        vec![
+            Rule::new("0", tp!(EXPR), 1.0),
+            Rule::new("1", tp!(EXPR), 1.0),
+            Rule::new("plus", tp!(@arrow[tp!(EXPR), tp!(EXPR), tp!(EXPR)]), 1.0),
+        ],
+    )
+}
+
+fn evaluator(name: &str, inps: &[i32]) -> Result<i32, ()> {
+    match name {
+        "0" => Ok(0),
+        "1" => Ok(1),
+        "plus" => Ok(inps[0] + inps[1]),
+        _ => unreachable!(),
+    }
+}
+
+fn arith_task<'a>(target: i32) -> Task<'a, Grammar, pcfg::AppliedRule, ()> {
+    Task {
+        oracle: Box::new(move |g: &Grammar, expr| match g.eval(expr, &evaluator) {
+            Ok(n) => (n - target).abs() as f64,
+            Err(_) => std::f64::INFINITY,
+        }),
+        tp: ptp!(EXPR),
+        observation: (),
+    }
+}
+
+#[test]
+fn anneal_schedules() {
+    let linear = Schedule::Linear {
+        initial: 2.0,
+        end: 0.0,
+    };
+    assert_eq!(linear.temperature(0, 5), 2.0);
+    assert_eq!(linear.temperature(2, 5), 1.0);
+    assert_eq!(linear.temperature(4, 5), 0.0);
+
+    let exponential = Schedule::Exponential {
+        initial: 2.0,
+        rate: 0.5,
+    };
+    assert_eq!(exponential.temperature(3, 5), 0.25);
+
+    let logarithmic = Schedule::Logarithmic { initial: 2.0 };
+    assert!((logarithmic.temperature(0, 5) - 2.0).abs() < 1e-9);
+    assert!(logarithmic.temperature(4, 5) < logarithmic.temperature(3, 5));
+
+    let ladder = TemperingParams::geometric(0.5, 8.0, 5, 10, 1);
+    assert_eq!(ladder.temperatures, vec![0.5, 1.0, 2.0, 4.0, 8.0]);
+}
+
+#[test]
+fn anneal_pcfg() {
+    let g = arith();
+    let task = arith_task(5);
+    let params = pcfg::GeneticParams::default();
+    let annealparams = AnnealParams {
+        schedule: Schedule::Linear {
+            initial: 1.0,
+            end: 0.0,
+        },
+        n_steps: 2000,
+    };
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let (expr, fitness) = anneal(&g, &params, rng, &annealparams, &task, None).unwrap();
+    assert_eq!(fitness, 0.0);
+    assert_eq!(g.eval(&expr, &evaluator), Ok(5));
+
+    // an initial solution is never lost
+    let initial = g.parse("plus(1,plus(1,plus(1,plus(1,1))))").unwrap();
+    let (_, fitness) = anneal(&g, &params, rng, &annealparams, &task, Some(initial)).unwrap();
+    assert_eq!(fitness, 0.0);
+}
+
+#[test]
+fn temper_pcfg() {
+    let g = arith();
+    let task = arith_task(7);
+    let params = pcfg::GeneticParams::default();
+    let temperingparams = TemperingParams::geometric(0.1, 10.0, 4, 500, 5);
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let tempered = temper(&g, &params, rng, &temperingparams, &task, None).unwrap();
+    assert_eq!(tempered.replicas.len(), 4);
+    assert_eq!(tempered.n_exchanges.len(), 3);
+    assert!(tempered.n_exchanges.iter().any(|&n| n > 0));
+    assert_eq!(tempered.best.1, 0.0);
+    assert_eq!(g.eval(&tempered.best.0, &evaluator), Ok(7));
+}
+
+#[test]
+fn temper_trs() {
+    let mut lex = trs::parse_lexicon(
+        "ZERO: int; SUCC: int -> int; PLUS: int -> int -> int;",
+        "",
+        "PLUS([!] [!]) = [!];",
+        false,
+        TypeContext::default(),
+    )
+    .unwrap();
+    let mut ctx = lex.context();
+    let data = vec![
+        trs::parse_rule("PLUS(ZERO ZERO) = ZERO", &mut lex, &mut ctx).unwrap(),
+        trs::parse_rule("PLUS(ZERO SUCC(ZERO)) = SUCC(ZERO)", &mut lex, &mut ctx).unwrap(),
+    ];
+    let model = trs::ModelParams {
+        p_partial: 0.01,
+        ..trs::ModelParams::default()
+    };
+    let task = trs::task_by_rewrite(&data, model, &lex, vec![]).unwrap();
+    let params = trs::GeneticParams {
+        n_crosses: 1,
+        max_sample_size: 5,
+        p_add: 0.5,
+        p_keep: 0.5,
+        atom_weights: (0.5, 0.25, 0.25),
+    };
+    let temperingparams = TemperingParams::geometric(1.0, 8.0, 3, 30, 3);
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let tempered = temper(&lex, &params, rng, &temperingparams, &task, None).unwrap();
+    assert_eq!(tempered.replicas.len(), 3);
+    let initial = tempered.best.0.clone();
+    assert!(tempered
+        .replicas
+        .iter()
+        .all(|(_, fitness)| tempered.best.1 <= *fitness));
+
+    let annealparams = AnnealParams {
+        schedule: Schedule::Constant(1.0),
+        n_steps: 30,
+    };
+    let (_, fitness) = anneal(&lex, &params, rng, &annealparams, &task, Some(initial)).unwrap();
+    assert!(fitness <= tempered.best.1);
+}
+
+#[test]
+fn anneal_lambda_uninhabited() {
+    let dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    let task = Task {
+        oracle: Box::new(|_: &Language, _: &lambda::Expression| 0.0),
+        tp: ptp!(bool),
+        observation: (),
+    };
+    let params = lambda::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    // no expression has the requested type, so there is nowhere to start
+    let annealparams = AnnealParams {
+        schedule: Schedule::Constant(1.0),
+        n_steps: 10,
+    };
+    assert!(anneal(&dsl, &params, rng, &annealparams, &task, None).is_none());
+    let temperingparams = TemperingParams::geometric(1.0, 4.0, 3, 10, 2);
+    assert!(temper(&dsl, &params, rng, &temperingparams, &task, None).is_none());
+    // even if some replicas are given, the rest cannot be created
+    let initial = vec![dsl.parse("0").unwrap()];
+    assert!(temper(&dsl, &params, rng, &temperingparams, &task, Some(initial)).is_none());
+}
@@ -2,12 +2,12 @@
 extern crate polytype;
 extern crate programinduction;
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use programinduction::domains::{circuits, strings};
 use programinduction::lambda;
 use programinduction::pcfg::{self, Grammar, Rule};
-use programinduction::{ECParams, EC};
+use programinduction::{ECParams, Task, EC};
 
 fn arith_evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
     match name {
@@ -70,6 +70,34 @@ fn explore_arith_pcfg() {
     assert!(frontiers[0].best_solution().is_some());
 }
 
+#[test]
+fn explore_arith_pcfg_sketch() {
+    let g = Grammar::new(
+        tp!(EXPR),
+        vec![
+            Rule::new("0", tp!(EXPR), 1.0),
+            Rule::new("1", tp!(EXPR), 1.0),
+            Rule::new("plus", tp!(@arrow[tp!(EXPR), tp!(EXPR), tp!(EXPR)]), 1.0),
+        ],
+    );
+    let ec_params = ECParams {
+        frontier_limit: 1,
+        search_limit_timeout: None,
+        search_limit_description_length: Some(8.0),
+    };
+    // sentences have no holes, so a sketch is its own only completion
+    let sketch = g.parse("plus(plus(1, 1), plus(1, 1))").unwrap();
+    let task = pcfg::task_by_evaluation(&arith_evaluate, &4, tp!(EXPR)).with_sketch(sketch);
+    let frontiers = g.explore_sketched(&ec_params, &[task]);
+    let solution = &frontiers[0].best_solution().expect("could not solve").0;
+    assert_eq!(g.display(solution), "plus(plus(1,1),plus(1,1))");
+
+    let sketch = g.parse("0").unwrap();
+    let task = pcfg::task_by_evaluation(&arith_evaluate, &4, tp!(EXPR)).with_sketch(sketch);
+    let frontiers = g.explore_sketched(&ec_params, &[task]);
+    assert!(frontiers[0].is_empty());
+}
+
 #[test]
 fn explore_strings() {
     let dsl = strings::dsl();
@@ -100,6 +128,201 @@ fn explore_strings() {
     );
 }
 
+#[test]
+fn explore_strings_sketch() {
+    let dsl = strings::dsl();
+    let examples = vec![(
+        vec![strings::Space::Str("OFJQc>BLVP>eMS".to_string())],
+        strings::Space::Str("OFJQc/BLVP/eMS".to_string()),
+    )];
+    let task = lambda::task_by_evaluation(
+        strings::Evaluator,
+        ptp!(@arrow[tp!(str), tp!(str)]),
+        &examples,
+    )
+    .with_sketch(dsl.parse("(λ (join ?sep (split ?delim $0)))").unwrap());
+
+    // the unsketched search would need a much larger description length
+    let ec_params = ECParams {
+        frontier_limit: 1,
+        search_limit_timeout: None,
+        search_limit_description_length: Some(8.0),
+    };
+
+    let frontiers = dsl.explore_sketched(&ec_params, &[task]);
+    let solution = &frontiers[0].best_solution().expect("could not solve").0;
+    assert_eq!(
+        "(λ (join (char->str /) (split > $0)))",
+        dsl.display(solution)
+    );
+}
+
+#[test]
+fn explore_arith_bottom_up() {
+    let dsl = lambda::Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("plus", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    let tp = ptp!(@arrow[tp!(int), tp!(int)]);
+    let triple = vec![(vec![1], 3), (vec![2], 6), (vec![5], 15)];
+    let plus_three = vec![(vec![1], 4), (vec![2], 5)];
+    let tasks = vec![
+        lambda::task_by_evaluation(
+            lambda::SimpleEvaluator::of(arith_evaluate),
+            tp.clone(),
+            &triple,
+        ),
+        lambda::task_by_evaluation(lambda::SimpleEvaluator::of(arith_evaluate), tp, &plus_three),
+    ];
+    let ec_params = ECParams {
+        frontier_limit: 2,
+        search_limit_timeout: None,
+        search_limit_description_length: None,
+    };
+    let params = lambda::BottomUpParams::default();
+
+    let frontiers = dsl.explore_bottom_up(
+        &ec_params,
+        &params,
+        lambda::SimpleEvaluator::of(arith_evaluate),
+        &tasks,
+    );
+    // observationally equivalent solutions are merged
+    assert_eq!(frontiers[0].len(), 1);
+    assert_eq!(frontiers[1].len(), 1);
+    for (frontier, task) in frontiers.iter().zip(&tasks) {
+        let (ref expr, logprior, _) = frontier[0];
+        assert_eq!((task.oracle)(&dsl, expr), 0.0);
+        assert_eq!(logprior, dsl.likelihood(&task.tp, expr));
+    }
+
+    // too small to solve
+    let params = lambda::BottomUpParams {
+        max_size: 4,
+        ..Default::default()
+    };
+    let frontiers = dsl.explore_bottom_up(
+        &ec_params,
+        &params,
+        lambda::SimpleEvaluator::of(arith_evaluate),
+        &tasks,
+    );
+    assert!(frontiers[0].is_empty());
+}
+
+#[test]
+fn explore_bottom_up_timeout() {
+    let dsl = lambda::Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("plus", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    // nothing returns a bool, so no program is ever a candidate solution
+    let examples = vec![(vec![1], 0), (vec![2], 1)];
+    let task = lambda::task_by_evaluation(
+        lambda::SimpleEvaluator::of(arith_evaluate),
+        ptp!(@arrow[tp!(int), tp!(bool)]),
+        &examples,
+    );
+    let ec_params = ECParams {
+        frontier_limit: 1,
+        search_limit_timeout: Some(Duration::from_millis(100)),
+        search_limit_description_length: None,
+    };
+    let params = lambda::BottomUpParams {
+        max_size: 1000,
+        max_bank_size: usize::max_value(),
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    let frontiers = dsl.explore_bottom_up(
+        &ec_params,
+        &params,
+        lambda::SimpleEvaluator::of(arith_evaluate),
+        &[task],
+    );
+    assert!(frontiers[0].is_empty());
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn ec_arith_contextual() {
+    let dsl = lambda::Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("plus", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    let cl = lambda::ContextualLanguage::new(dsl);
+    let tasks: Vec<_> = (2..6)
+        .map(|n| Task {
+            oracle: Box::new(move |cl: &lambda::ContextualLanguage, expr| {
+                let evaluator = lambda::SimpleEvaluator::of(arith_evaluate);
+                if cl.dsl.eval(expr, evaluator, &[]) == Ok(n) {
+                    0.0
+                } else {
+                    std::f64::NEG_INFINITY
+                }
+            }),
+            tp: ptp!(int),
+            observation: (),
+        })
+        .collect();
+    let ec_params = ECParams {
+        frontier_limit: 1,
+        search_limit_timeout: None,
+        search_limit_description_length: Some(12.0),
+    };
+    let params = lambda::CompressionParams::default();
+
+    let (cl, frontiers) = cl.ec(&ec_params, &params, &tasks);
+    for (frontier, task) in frontiers.iter().zip(&tasks) {
+        let (ref expr, logprior, _) = *frontier.best_solution().unwrap();
+        assert_eq!((task.oracle)(&cl, expr), 0.0);
+        assert_eq!(logprior, cl.likelihood(&task.tp, expr));
+    }
+    let n_productions = cl.dsl.primitives.len() + cl.dsl.invented.len();
+    assert_eq!(cl.library.len(), n_productions);
+    // inventions are used differently at the root than as arguments
+    let root = &cl.no_parent;
+    assert!(cl.library.iter().flatten().any(|ctx| ctx != root));
+}
+
+#[test]
+fn explore_arith_contextual_sketch() {
+    let dsl = lambda::Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("plus", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    let cl = lambda::ContextualLanguage::new(dsl);
+    let oracle = |cl: &lambda::ContextualLanguage, expr: &lambda::Expression| {
+        let evaluator = lambda::SimpleEvaluator::of(arith_evaluate);
+        if cl.dsl.eval(expr, evaluator, &[]) == Ok(5) {
+            0.0
+        } else {
+            std::f64::NEG_INFINITY
+        }
+    };
+    let task = Task {
+        oracle: Box::new(oracle),
+        tp: ptp!(int),
+        observation: (),
+    }
+    .with_sketch(cl.dsl.parse("(plus (plus 1 1) ?)").unwrap());
+    let ec_params = ECParams {
+        frontier_limit: 1,
+        search_limit_timeout: None,
+        search_limit_description_length: Some(12.0),
+    };
+
+    let frontiers = cl.explore_sketched(&ec_params, &[task]);
+    let solution = &frontiers[0].best_solution().expect("could not solve").0;
+    assert_eq!(oracle(&cl, solution), 0.0);
+    assert!(cl.dsl.display(solution).starts_with("(plus (plus 1 1) "));
+}
+
 #[test]
 #[ignore]
 fn ec_strings() {
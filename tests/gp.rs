@@ -2,9 +2,49 @@
 extern crate polytype;
 extern crate programinduction;
 extern crate rand;
+extern crate serde_json;
+use polytype::Context as TypeContext;
+use programinduction::lambda::{self, Language, SimpleEvaluator};
 use programinduction::pcfg::{self, Grammar, Rule};
-use programinduction::{GPParams, GPSelection, Task, GP};
+use programinduction::trs;
+use programinduction::{
+    GPAdaptive, GPAlps, GPBloat, GPCheckpoint, GPDiversity, GPParams, GPSelection, GPSemantics,
+    GPState, GenerationStats, Task, GP,
+};
 use rand::{rngs::SmallRng, SeedableRng};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+fn arith() -> Grammar {
+    Grammar::new(
+        tp!(EXPR),
+        vec![
+            Rule::new("0", tp!(EXPR), 1.0),
+            Rule::new("1", tp!(EXPR), 1.0),
+            Rule::new("plus", tp!(@arrow[tp!(EXPR), tp!(EXPR), tp!(EXPR)]), 1.0),
+        ],
+    )
+}
+
+fn evaluator(name: &str, inps: &[i32]) -> Result<i32, ()> {
+    match name {
+        "0" => Ok(0),
+        "1" => Ok(1),
+        "plus" => Ok(inps[0] + inps[1]),
+        _ => unreachable!(),
+    }
+}
+
+fn arith_task<'a>(target: i32) -> Task<'a, Grammar, pcfg::AppliedRule, ()> {
+    Task {
+        oracle: Box::new(move |g: &Grammar, expr| match g.eval(expr, &evaluator) {
+            Ok(n) => (n - target).abs() as f64,
+            Err(_) => std::f64::INFINITY,
+        }),
+        tp: ptp!(EXPR),
+        observation: (),
+    }
+}
 
 #[test]
 fn gp_sum_arith() {
@@ -48,7 +88,7 @@ fn gp_sum_arith() {
     let generations = 1000;
     let rng = &mut SmallRng::from_seed([1u8; 16]);
 
-    let mut pop = g.init(&params, rng, &gpparams, &task);
+    let mut pop = g.init(&params, rng, &gpparams, &task, None);
     for _ in 0..generations {
         g.evolve(&params, rng, &gpparams, &task, &mut pop)
     }
@@ -58,3 +98,925 @@ fn gp_sum_arith() {
     assert_eq!(6, g.eval(winner, &evaluator).unwrap());
     assert_eq!(0.0, score);
 }
+
+#[test]
+fn gp_tournament_selects_fittest() {
+    let g = arith();
+    let population = vec![
+        (g.parse("1").unwrap(), 0.0),
+        (g.parse("0").unwrap(), 1.0),
+        (g.parse("plus(0,1)").unwrap(), 2.0),
+    ];
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    // when every individual competes, the one with the smallest score wins
+    for _ in 0..10 {
+        assert_eq!(g.tournament(rng, 3, &population), &population[0].0);
+    }
+    // otherwise, the least fit individual can never win
+    for _ in 0..100 {
+        assert_ne!(g.tournament(rng, 2, &population), &population[2].0);
+    }
+}
+
+#[test]
+fn gp_deterministic_given_seed() {
+    let g = arith();
+    let task = arith_task(9);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Drift(0.5),
+        population_size: 20,
+        tournament_size: 3,
+        mutation_prob: 0.5,
+        n_delta: 8,
+    };
+    let params = pcfg::GeneticParams::default();
+    let run = || {
+        let rng = &mut SmallRng::from_seed([7u8; 16]);
+        let mut pop = g.init(&params, rng, &gpparams, &task, None);
+        for _ in 0..50 {
+            g.evolve(&params, rng, &gpparams, &task, &mut pop)
+        }
+        pop
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn gp_lambda_add_two() {
+    fn evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
+        match name {
+            "0" => Ok(0),
+            "1" => Ok(1),
+            "+" => Ok(inps[0] + inps[1]),
+            _ => unreachable!(),
+        }
+    }
+    let mut dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    dsl.add_symmetry_violation(2, 0, 0);
+    dsl.add_symmetry_violation(2, 1, 0);
+    let examples = vec![(1, 3), (4, 6), (7, 9)];
+    let task = Task {
+        oracle: Box::new(|dsl: &Language, expr| {
+            examples
+                .iter()
+                .map(
+                    |&(x, y)| match dsl.eval(expr, SimpleEvaluator::of(evaluate), &[x]) {
+                        Ok(z) => f64::from((z - y).abs()),
+                        Err(_) => std::f64::INFINITY,
+                    },
+                )
+                .sum()
+        }),
+        tp: ptp!(@arrow[tp!(int), tp!(int)]),
+        observation: (),
+    };
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 20,
+        tournament_size: 5,
+        mutation_prob: 0.6,
+        n_delta: 4,
+    };
+    let params = lambda::GeneticParams::default();
+    let generations = 200;
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let mut pop = dsl.init(&params, rng, &gpparams, &task, None);
+    for _ in 0..generations {
+        dsl.evolve(&params, rng, &gpparams, &task, &mut pop)
+    }
+
+    // every individual is well-typed and respects symmetry violations
+    for &(ref expr, _) in &pop {
+        assert_eq!(dsl.infer(expr).unwrap(), task.tp);
+        assert!(!dsl.display(expr).contains("(+ 0"));
+    }
+    // perfect winner is found!
+    let &(ref winner, score) = &pop[0];
+    assert_eq!(Ok(5), dsl.eval(winner, SimpleEvaluator::of(evaluate), &[3]));
+    assert_eq!(0.0, score);
+}
+
+#[test]
+fn gp_lambda_uninhabited() {
+    let dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    let task = Task {
+        oracle: Box::new(|_: &Language, _: &lambda::Expression| 0.0),
+        tp: ptp!(bool),
+        observation: (),
+    };
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 5,
+        mutation_prob: 0.6,
+        n_delta: 4,
+    };
+    let params = lambda::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    // no expression has the requested type, so the population is empty but evolution goes on
+    let mut pop = dsl.init(&params, rng, &gpparams, &task, None);
+    assert!(pop.is_empty());
+    for _ in 0..10 {
+        dsl.evolve(&params, rng, &gpparams, &task, &mut pop)
+    }
+    assert!(pop.is_empty());
+}
+
+#[test]
+fn gp_bloat_limits() {
+    let g = arith();
+    let task = arith_task(20);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 3,
+        mutation_prob: 0.5,
+        n_delta: 4,
+    };
+    let params = pcfg::GeneticParams::default();
+    let seed = vec![g.parse("1").unwrap(); 10];
+    let run = |bloat: GPBloat| {
+        let rng = &mut SmallRng::from_seed([1u8; 16]);
+        let mut state = GPState {
+            bloat,
+            ..Default::default()
+        };
+        let mut pop = g.init(&params, rng, &gpparams, &task, Some(seed.clone()));
+        for _ in 0..50 {
+            g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+        }
+        pop
+    };
+
+    // the target can only be reached by large programs, which evolve when unconstrained
+    let pop = run(GPBloat::default());
+    assert!(pop.iter().any(|(expr, _)| g.size(expr) > 7));
+    assert!(pop.iter().any(|(expr, _)| g.depth(expr) > 3));
+
+    let pop = run(GPBloat {
+        max_size: Some(7),
+        ..Default::default()
+    });
+    assert!(pop.iter().all(|(expr, _)| g.size(expr) <= 7));
+
+    let pop = run(GPBloat {
+        max_depth: Some(3),
+        ..Default::default()
+    });
+    assert!(pop.iter().all(|(expr, _)| g.depth(expr) <= 3));
+}
+
+#[test]
+fn gp_bloat_pressure() {
+    let g = arith();
+    // every program is equally fit, and evaluations of larger programs are counted
+    let n_large = AtomicUsize::new(0);
+    let task = Task {
+        oracle: Box::new(|g: &Grammar, expr| {
+            if g.size(expr) > 1 {
+                n_large.fetch_add(1, AtomicOrdering::SeqCst);
+            }
+            0.0
+        }),
+        tp: ptp!(EXPR),
+        observation: (),
+    };
+    let params = pcfg::GeneticParams::default();
+    let run = |gpparams: &GPParams, bloat: GPBloat, seed: &[&str]| {
+        let rng = &mut SmallRng::from_seed([1u8; 16]);
+        let seed = seed.iter().map(|s| g.parse(s).unwrap()).collect();
+        let mut pop = g.init(&params, rng, gpparams, &task, Some(seed));
+        let mut state = GPState {
+            bloat,
+            ..Default::default()
+        };
+        n_large.store(0, AtomicOrdering::SeqCst);
+        for _ in 0..20 {
+            g.evolve_with_state(&params, rng, gpparams, &task, &mut pop, &mut state);
+        }
+        n_large.load(AtomicOrdering::SeqCst)
+    };
+
+    // under the Tarpeian method, children larger than the average are never evaluated
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 4,
+        tournament_size: 2,
+        mutation_prob: 0.5,
+        n_delta: 4,
+    };
+    let seed = ["0", "0", "0", "0"];
+    assert!(run(&gpparams, GPBloat::default(), &seed) > 0);
+    let tarpeian = GPBloat {
+        tarpeian: Some(1.0),
+        ..Default::default()
+    };
+    assert_eq!(run(&gpparams, tarpeian, &seed), 0);
+
+    // under lexicographic parsimony, tournaments among equally fit programs select the smallest,
+    // so crossover only reproduces it
+    let gpparams = GPParams {
+        population_size: 3,
+        tournament_size: 3,
+        mutation_prob: 0.0,
+        ..gpparams
+    };
+    let large = "plus(plus(0,0),plus(0,0))";
+    let seed = [large, "0", large];
+    assert!(run(&gpparams, GPBloat::default(), &seed) > 0);
+    let parsimony = GPBloat {
+        lexicographic_parsimony: true,
+        ..Default::default()
+    };
+    assert_eq!(run(&gpparams, parsimony, &seed), 0);
+}
+
+#[test]
+fn gp_diversity() {
+    let g = arith();
+    let task = arith_task(6);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 3,
+        mutation_prob: 0.6,
+        n_delta: 4,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    // fitness sharing
+    let mut state = GPState {
+        diversity: Some(GPDiversity::FitnessSharing {
+            radius: 3.0,
+            alpha: 1.0,
+            distance: None,
+        }),
+        ..Default::default()
+    };
+    let mut pop = g.init(&params, rng, &gpparams, &task, None);
+    for _ in 0..100 {
+        g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+    }
+    assert_eq!(pop.len(), 10);
+    assert!(pop.windows(2).all(|w| w[0].1 <= w[1].1));
+
+    // novelty search
+    let rng = &mut SmallRng::from_seed([4u8; 16]);
+    let mut state = GPState {
+        diversity: Some(GPDiversity::Novelty {
+            behavior: Box::new(|expr| vec![f64::from(g.eval(expr, &evaluator).unwrap())]),
+            k: 3,
+            threshold: 1.0,
+            weight: 1.0,
+        }),
+        ..Default::default()
+    };
+    let mut pop = g.init(&params, rng, &gpparams, &task, None);
+    for _ in 0..20 {
+        g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+    }
+    assert!(!state.archive.is_empty());
+    assert!(pop.windows(2).all(|w| w[0].1 <= w[1].1));
+}
+
+#[test]
+fn gp_diversity_preserves_outlier() {
+    let g = arith();
+    let task = arith_task(2);
+
+    // children are copies of the fittest individual, "1", which crowds out the less fit outlier
+    // unless selection favors diversity
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 5,
+        tournament_size: 5,
+        mutation_prob: 1.0,
+        n_delta: 2,
+    };
+    let params = pcfg::GeneticParams {
+        mutation_point: 0.0,
+        mutation_subtree: 0.0,
+        mutation_reproduction: 1.0,
+        ..Default::default()
+    };
+    let outlier = g.parse("plus(plus(1,1),plus(1,1))").unwrap();
+    let run_task = |task: &Task<Grammar, pcfg::AppliedRule, ()>,
+                    mut state: GPState<pcfg::AppliedRule>| {
+        let rng = &mut SmallRng::from_seed([1u8; 16]);
+        let mut seed = vec![g.parse("1").unwrap(); 4];
+        seed.push(outlier.clone());
+        let mut pop = g.init(&params, rng, &gpparams, task, Some(seed));
+        for _ in 0..10 {
+            g.evolve_with_state(&params, rng, &gpparams, task, &mut pop, &mut state);
+        }
+        pop.iter().any(|(expr, _)| expr == &outlier)
+    };
+    let run = |state| run_task(&task, state);
+
+    assert!(!run(GPState::default()));
+    assert!(run(GPState {
+        diversity: Some(GPDiversity::FitnessSharing {
+            radius: 3.0,
+            alpha: 1.0,
+            distance: None,
+        }),
+        ..Default::default()
+    }));
+    assert!(run(GPState {
+        diversity: Some(GPDiversity::Novelty {
+            behavior: Box::new(|expr| vec![f64::from(g.eval(expr, &evaluator).unwrap())]),
+            k: 3,
+            threshold: 1.0,
+            weight: 1.0,
+        }),
+        ..Default::default()
+    }));
+    // crowding penalizes negative fitness too
+    let negative_task = Task {
+        oracle: Box::new(|g: &Grammar, expr| (task.oracle)(g, expr) - 10.0),
+        tp: ptp!(EXPR),
+        observation: (),
+    };
+    assert!(run_task(
+        &negative_task,
+        GPState {
+            diversity: Some(GPDiversity::FitnessSharing {
+                radius: 3.0,
+                alpha: 1.0,
+                distance: None,
+            }),
+            ..Default::default()
+        }
+    ));
+    // distances to behaviors with NaN components are ignored
+    run(GPState {
+        diversity: Some(GPDiversity::Novelty {
+            behavior: Box::new(|expr| match g.eval(expr, &evaluator).unwrap() {
+                1 => vec![1.0, std::f64::NAN],
+                n => vec![f64::from(n), 0.0],
+            }),
+            k: 3,
+            threshold: 1.0,
+            weight: 1.0,
+        }),
+        ..Default::default()
+    });
+}
+
+#[test]
+fn gp_diversity_drift() {
+    let g = arith();
+    // fitness drifts towards a task whose oracle has changed since the population was scored
+    let offset = AtomicUsize::new(0);
+    let task = Task {
+        oracle: Box::new(|g: &Grammar, expr| {
+            (arith_task(6).oracle)(g, expr) + offset.load(AtomicOrdering::SeqCst) as f64
+        }),
+        tp: ptp!(EXPR),
+        observation: (),
+    };
+
+    let gpparams = GPParams {
+        selection: GPSelection::Drift(0.5),
+        population_size: 10,
+        tournament_size: 3,
+        mutation_prob: 0.6,
+        n_delta: 4,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+    let mut state = GPState {
+        diversity: Some(GPDiversity::FitnessSharing {
+            radius: 3.0,
+            alpha: 1.0,
+            distance: None,
+        }),
+        ..Default::default()
+    };
+
+    let mut pop = g.init(&params, rng, &gpparams, &task, None);
+    offset.store(10, AtomicOrdering::SeqCst);
+    g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+
+    // existing individuals have fitness halfway to the new oracle, and children have the new
+    // fitness
+    for (expr, fitness) in &pop {
+        let new_fitness = (task.oracle)(&g, expr);
+        assert!(*fitness == new_fitness || *fitness == new_fitness - 5.0);
+    }
+    assert!(pop.windows(2).all(|w| w[0].1 <= w[1].1));
+}
+
+#[test]
+fn gp_alps() {
+    let g = arith();
+    let task = arith_task(6);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 3,
+        mutation_prob: 0.6,
+        n_delta: 2,
+    };
+    let alps = GPAlps {
+        n_layers: 3,
+        age_gap: 5,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let mut layers = g.init_alps(&params, rng, &gpparams, &alps, &task);
+    for _ in 0..200 {
+        g.evolve_alps(&params, rng, &gpparams, &alps, &task, &mut layers)
+    }
+
+    assert_eq!(layers.generation, 200);
+    assert_eq!(layers.layers.len(), 3);
+    for (i, layer) in layers.layers.iter().enumerate() {
+        assert!(layer.len() <= 10);
+        if i < 2 {
+            assert!(layer.iter().all(|&((_, age), _)| age < 5 * (i + 1)));
+        }
+    }
+    // the oldest layer has been populated by migration
+    assert!(!layers.layers[2].is_empty());
+    // perfect winner is found!
+    let &((ref winner, _), score) = layers.best().unwrap();
+    assert_eq!(6, g.eval(winner, &evaluator).unwrap());
+    assert_eq!(0.0, score);
+}
+
+#[test]
+fn gp_alps_injection() {
+    let g = arith();
+    let task = arith_task(6);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 3,
+        mutation_prob: 0.6,
+        n_delta: 4,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    for &n_layers in &[1, 3] {
+        let alps = GPAlps {
+            n_layers,
+            age_gap: 3,
+        };
+        let mut layers = g.init_alps(&params, rng, &gpparams, &alps, &task);
+        for generation in 1..=12 {
+            g.evolve_alps(&params, rng, &gpparams, &alps, &task, &mut layers);
+            if generation % alps.age_gap == 0 {
+                // the youngest layer holds only the fresh individuals from genesis
+                assert_eq!(layers.layers[0].len(), 10);
+                assert!(layers.layers[0].iter().all(|&((_, age), _)| age == 0));
+            }
+        }
+        if n_layers > 1 {
+            // displaced individuals compete in the next layer
+            assert!(!layers.layers[1].is_empty());
+        }
+    }
+}
+
+#[test]
+fn gp_alps_with_state() {
+    let g = arith();
+    let task = arith_task(20);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 3,
+        mutation_prob: 0.5,
+        n_delta: 4,
+    };
+    // no fresh individuals are injected, so only offspring are new
+    let alps = GPAlps {
+        n_layers: 3,
+        age_gap: 100,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+    let mut state = GPState {
+        adaptive: Some(GPAdaptive::new(0.1, 0.3)),
+        bloat: GPBloat {
+            max_size: Some(7),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut layers = g.init_alps(&params, rng, &gpparams, &alps, &task);
+    let max_size = layers.layers[0]
+        .iter()
+        .map(|((expr, _), _)| g.size(expr))
+        .fold(7, usize::max);
+    for generation in 1..=30 {
+        let stats = g.evolve_alps_with_state(
+            &params,
+            rng,
+            &gpparams,
+            &alps,
+            &task,
+            &mut layers,
+            &mut state,
+        );
+        assert_eq!(stats.generation, generation);
+        let n_individuals: usize = layers.layers.iter().map(Vec::len).sum();
+        assert_eq!(stats.population_size, n_individuals);
+        assert!(stats.n_mutations + stats.n_crossovers <= 3 * gpparams.n_delta);
+        assert!(stats.operator_probabilities.is_some());
+    }
+    assert_eq!(state.generation, 30);
+    // offspring are subject to bloat control
+    assert!(layers
+        .layers
+        .iter()
+        .flatten()
+        .all(|((expr, _), _)| g.size(expr) <= max_size));
+}
+
+#[test]
+fn gp_adaptive_operators() {
+    let g = arith();
+    let task = arith_task(40);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 3,
+        mutation_prob: 0.5,
+        n_delta: 4,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let mut state = GPState {
+        adaptive: Some(GPAdaptive::new(0.05, 0.2)),
+        ..Default::default()
+    };
+    let mut pop = g.init(&params, rng, &gpparams, &task, None);
+    for _ in 0..20 {
+        g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+    }
+
+    // crossover, then point, subtree, and reproduction mutations
+    let probs = state.adaptive.unwrap().probabilities();
+    assert_eq!(probs.len(), 4);
+    assert!(probs.iter().all(|&p| p >= 0.05));
+    assert!((probs.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    // reproduction never improves fitness
+    assert!((probs[3] - 0.05).abs() < 1e-9);
+
+    // a minimum probability above uniform selects uniformly
+    let adaptive = GPAdaptive {
+        rewards: vec![0.5, 0.0, 0.0, 0.0],
+        ..GPAdaptive::new(0.5, 0.2)
+    };
+    assert_eq!(adaptive.probabilities(), vec![0.25; 4]);
+    let mut state = GPState {
+        adaptive: Some(GPAdaptive::new(0.5, 0.2)),
+        ..Default::default()
+    };
+    for _ in 0..5 {
+        g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+    }
+}
+
+#[test]
+fn gp_checkpoint() {
+    let g = arith();
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 5,
+        mutation_prob: 0.6,
+        n_delta: 1,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let task = arith_task(6);
+    let mut pop = g.init(&params, rng, &gpparams, &task, None);
+    for _ in 0..1000 {
+        g.evolve(&params, rng, &gpparams, &task, &mut pop)
+    }
+
+    let mut saved = Vec::new();
+    g.save_population(&pop, &mut saved).unwrap();
+    let loaded = g.load_population(&saved[..]).unwrap();
+    assert_eq!(loaded, pop);
+
+    // warm start a related task from the saved individuals
+    let task = arith_task(7);
+    let seed = loaded.into_iter().take(4).map(|(expr, _)| expr).collect();
+    let seeded = g.init(&params, rng, &gpparams, &task, Some(seed));
+    assert_eq!(seeded.len(), gpparams.population_size);
+    assert!(pop
+        .iter()
+        .take(4)
+        .all(|(expr, _)| seeded.iter().any(|(other, _)| other == expr)));
+    assert!(seeded[0].1 <= 1.0);
+
+    assert!(g.load_population(&b"0\tminus(0,1)\n"[..]).is_err());
+}
+
+#[test]
+fn gp_checkpoint_trs() {
+    let lex = trs::parse_lexicon(
+        "ZERO: int; SUCC: int -> int; PLUS: int -> int -> int;",
+        "",
+        "",
+        false,
+        TypeContext::default(),
+    )
+    .unwrap();
+    let population = vec![
+        (
+            trs::parse_trs("PLUS(ZERO x_) = x_;", &mut lex.clone()).unwrap(),
+            0.5,
+        ),
+        (
+            trs::parse_trs(
+                "PLUS(ZERO x_) = x_; PLUS(SUCC(x_) y_) = SUCC(PLUS(x_ y_));",
+                &mut lex.clone(),
+            )
+            .unwrap(),
+            1.5,
+        ),
+    ];
+
+    let mut saved = Vec::new();
+    lex.save_population(&population, &mut saved).unwrap();
+    let loaded = lex.load_population(&saved[..]).unwrap();
+
+    assert_eq!(loaded.len(), 2);
+    for ((trs1, f1), (trs2, f2)) in population.iter().zip(&loaded) {
+        assert_eq!(trs1.to_string(), trs2.to_string());
+        assert_eq!(f1, f2);
+    }
+}
+
+#[test]
+fn gp_genesis_trs_seeded() {
+    let params = trs::GeneticParams {
+        n_crosses: 1,
+        max_sample_size: 5,
+        p_add: 0.5,
+        p_keep: 0.5,
+        atom_weights: (0.5, 0.25, 0.25),
+    };
+    let sample = || {
+        let lex = trs::parse_lexicon(
+            "ZERO: int; SUCC: int -> int; PLUS: int -> int -> int;",
+            "",
+            "PLUS([!] [!]) = [!];",
+            false,
+            TypeContext::default(),
+        )
+        .unwrap();
+        let rng = &mut SmallRng::from_seed([1u8; 16]);
+        let population = lex.genesis(&params, rng, 10, &ptp!(int));
+        let mutants: Vec<_> = population
+            .iter()
+            .flat_map(|trs| lex.mutate(&params, rng, trs, &vec![]))
+            .collect();
+        population
+            .iter()
+            .chain(&mutants)
+            .map(|trs| trs.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // sampling only draws from the given rng
+    assert_eq!(sample(), sample());
+}
+
+#[test]
+fn gp_generation_stats() {
+    let g = arith();
+    let task = arith_task(6);
+
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 10,
+        tournament_size: 5,
+        mutation_prob: 0.6,
+        n_delta: 3,
+    };
+    let params = pcfg::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    let mut state = GPState {
+        adaptive: Some(GPAdaptive::new(0.05, 0.2)),
+        ..Default::default()
+    };
+    let mut pop = g.init(&params, rng, &gpparams, &task, None);
+    let log: Vec<_> = (0..100)
+        .map(|_| g.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state))
+        .collect();
+
+    for (i, stats) in log.iter().enumerate() {
+        assert_eq!(stats.generation, i + 1);
+        assert_eq!(stats.population_size, 10);
+        assert_eq!(stats.n_mutations + stats.n_crossovers, 3);
+        assert_eq!(stats.n_discarded, 0);
+        assert!(stats.n_unique >= 1 && stats.n_unique <= 10);
+        assert!(stats.mean_size >= 1.0);
+        assert_eq!(stats.operator_probabilities.as_ref().unwrap().len(), 4);
+    }
+    // deterministic selection never loses the fittest individual
+    let best: Vec<_> = log
+        .iter()
+        .map(|stats| stats.fitness.as_ref().unwrap().best)
+        .collect();
+    assert!(best.windows(2).all(|w| w[1] <= w[0]));
+    let last = log.last().unwrap().fitness.clone().unwrap();
+    assert_eq!(last.best, pop[0].1);
+    assert!(last.best <= last.median && last.median <= last.worst);
+
+    let json = serde_json::to_string(&log).unwrap();
+    let parsed: Vec<GenerationStats> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.len(), log.len());
+    assert_eq!(parsed[99].n_unique, log[99].n_unique);
+}
+
+#[test]
+fn gp_pcfg_crossover_operators() {
+    let g = arith();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+    let parent1 = g.parse("plus(0,plus(0,0))").unwrap();
+    let parent2 = g
+        .parse("plus(plus(1,plus(1,1)),plus(1,plus(1,plus(1,1))))")
+        .unwrap();
+
+    let size_fair = pcfg::GeneticParams {
+        crossover: pcfg::Crossover::SizeFair,
+        ..Default::default()
+    };
+    for _ in 0..100 {
+        let children = g.crossover(&size_fair, rng, &parent1, &parent2, &());
+        assert_eq!(children.len(), 2);
+        // parent1 has five nodes, so at most eleven may be inserted
+        assert!(g.size(&children[0]) <= 5 - 1 + 11);
+    }
+
+    // the subtree at the same position is exchanged, even when the parents differ in shape
+    let homologous = pcfg::GeneticParams {
+        crossover: pcfg::Crossover::Homologous,
+        ..Default::default()
+    };
+    let parent1 = g.parse("plus(0,0)").unwrap();
+    let parent2 = g.parse("plus(plus(1,1),1)").unwrap();
+    let mut seen = HashSet::new();
+    for _ in 0..100 {
+        let children = g.crossover(&homologous, rng, &parent1, &parent2, &());
+        let child = g.display(&children[0]);
+        assert!(
+            ["plus(plus(1,1),1)", "plus(plus(1,1),0)", "plus(0,1)"].contains(&child.as_str()),
+            "{} is not a homologous child",
+            child
+        );
+        seen.insert(child);
+    }
+    assert_eq!(seen.len(), 3);
+
+    // under a skewed grammar, improbable subtrees are replaced by probable ones
+    let g = Grammar::new(
+        tp!(EXPR),
+        vec![
+            Rule::new("0", tp!(EXPR), 10.0),
+            Rule::new("1", tp!(EXPR), 0.1),
+            Rule::new("plus", tp!(@arrow[tp!(EXPR), tp!(EXPR), tp!(EXPR)]), 1.0),
+        ],
+    );
+    let likelihood = pcfg::GeneticParams {
+        crossover: pcfg::Crossover::Likelihood,
+        ..Default::default()
+    };
+    let parent1 = g.parse("plus(1,1)").unwrap();
+    let parent2 = g.parse("plus(0,1)").unwrap();
+    let improved = (0..100)
+        .map(|_| g.crossover(&likelihood, rng, &parent1, &parent2, &())[0].clone())
+        .filter(|child| g.likelihood(child) > g.likelihood(&parent1))
+        .count();
+    assert!(improved > 50);
+}
+
+#[test]
+fn gp_semantic_operators() {
+    fn evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
+        match name {
+            "0" => Ok(0),
+            "1" => Ok(1),
+            "+" => Ok(inps[0] + inps[1]),
+            _ => unreachable!(),
+        }
+    }
+    let dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    let examples = vec![(1, 3), (4, 6), (7, 9)];
+    let outputs = |dsl: &Language, expr: &lambda::Expression| -> Vec<f64> {
+        examples
+            .iter()
+            .map(
+                |&(x, _)| match dsl.eval(expr, SimpleEvaluator::of(evaluate), &[x]) {
+                    Ok(z) => f64::from(z),
+                    Err(_) => std::f64::NAN,
+                },
+            )
+            .collect()
+    };
+    let task = Task {
+        oracle: Box::new(|dsl: &Language, expr| {
+            outputs(dsl, expr)
+                .into_iter()
+                .zip(&examples)
+                .map(|(z, &(_, y))| (z - f64::from(y)).abs())
+                .sum()
+        }),
+        tp: ptp!(@arrow[tp!(int), tp!(int)]),
+        observation: (),
+    };
+    let params = lambda::GeneticParams::default();
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    // rejecting semantically equivalent offspring still finds the target
+    let gpparams = GPParams {
+        selection: GPSelection::Deterministic,
+        population_size: 20,
+        tournament_size: 5,
+        mutation_prob: 0.6,
+        n_delta: 4,
+    };
+    let mut state = GPState {
+        semantics: Some(GPSemantics::new(Box::new(|expr| outputs(&dsl, expr)))),
+        ..Default::default()
+    };
+    let mut pop = dsl.init(&params, rng, &gpparams, &task, None);
+    let mut n_discarded = 0;
+    for _ in 0..200 {
+        let stats = dsl.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+        n_discarded += stats.n_discarded;
+    }
+    assert_eq!(pop[0].1, 0.0);
+    // equivalent offspring are counted as discarded
+    assert!(n_discarded > 0);
+
+    // geometric crossover alone never widens the range of outputs on any example
+    let gpparams = GPParams {
+        mutation_prob: 0.0,
+        ..gpparams
+    };
+    let mut state = GPState {
+        semantics: Some(GPSemantics {
+            reject_equivalent: false,
+            max_attempts: 1000,
+            ..GPSemantics::new(Box::new(|expr| outputs(&dsl, expr)))
+        }),
+        ..Default::default()
+    };
+    let range = |pop: &[(lambda::Expression, f64)]| -> Vec<(f64, f64)> {
+        (0..examples.len())
+            .map(|i| {
+                pop.iter().map(|(expr, _)| outputs(&dsl, expr)[i]).fold(
+                    (std::f64::INFINITY, std::f64::NEG_INFINITY),
+                    |(lo, hi), z| (lo.min(z), hi.max(z)),
+                )
+            })
+            .collect()
+    };
+    let mut pop = dsl.init(&params, rng, &gpparams, &task, None);
+    let initial = range(&pop);
+    for _ in 0..50 {
+        dsl.evolve_with_state(&params, rng, &gpparams, &task, &mut pop, &mut state);
+    }
+    for ((lo, hi), (lo0, hi0)) in range(&pop).into_iter().zip(initial) {
+        assert!(lo0 <= lo && hi <= hi0);
+    }
+}
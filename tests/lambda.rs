@@ -1,8 +1,12 @@
 #[macro_use]
 extern crate polytype;
 extern crate programinduction;
+extern crate rand;
+extern crate serde_json;
 
+use polytype::Context;
 use programinduction::lambda::*;
+use rand::{rngs::SmallRng, SeedableRng};
 
 #[test]
 fn lambda_expression_parse_primitive() {
@@ -321,6 +325,7 @@ fn lambda_eval_firstclass() {
         fn lift(&self, f: LiftedFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
             Ok(ListSpace::Func(f))
         }
+        fn limit_error(&self) -> Self::Error {}
     }
 
     let dsl = Language::uniform(vec![
@@ -409,6 +414,9 @@ fn lambda_lazy_eval() {
                 _ => unreachable!(),
             }
         }
+        fn limit_error(&self) -> Self::Error {
+            ListError("evaluation stopped")
+        }
     }
 
     let dsl = Language::uniform(vec![
@@ -489,6 +497,7 @@ fn lambda_lazy_eval_firstclass() {
         fn lift(&self, f: LiftedLazyFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
             Ok(ListSpace::Func(f))
         }
+        fn limit_error(&self) -> Self::Error {}
     }
 
     let dsl = Language::uniform(vec![
@@ -530,3 +539,1079 @@ fn lambda_lazy_eval_firstclass() {
     let expr = dsl.parse("(λ (λ (map (λ (+ $0 1)) $0)))").unwrap();
     assert!((task.oracle)(&dsl, &expr).is_infinite());
 }
+
+#[test]
+fn lambda_sample() {
+    let mut dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    dsl.add_symmetry_violation(2, 0, 0);
+    let rng = &mut SmallRng::from_seed([1u8; 16]);
+
+    // well-typed and free of symmetry violations
+    let req = ptp!(@arrow[tp!(int), tp!(int)]);
+    for _ in 0..100 {
+        let expr = dsl.sample(&req, 3, rng).unwrap();
+        let mut ctx = Context::default();
+        let tp = dsl.infer(&expr).unwrap().instantiate(&mut ctx);
+        let req_tp = req.instantiate(&mut ctx);
+        assert!(ctx.unify(&tp, &req_tp).is_ok());
+        assert!(!dsl.display(&expr).contains("(+ 0"));
+    }
+
+    // a depth of one permits no applications
+    let n_zeros = (0..1000)
+        .filter(|_| dsl.sample(&ptp!(int), 1, rng).unwrap() == Expression::Primitive(0))
+        .count();
+    assert!(n_zeros > 400 && n_zeros < 600);
+
+    // nothing can be sampled without depth, or for an uninhabited type
+    assert_eq!(dsl.sample(&ptp!(int), 0, rng), None);
+    assert_eq!(dsl.sample(&ptp!(bool), 3, rng), None);
+}
+
+#[test]
+fn lambda_beta_normalize() {
+    let mut dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+
+    let normalize = |s: &str| {
+        let mut expr = dsl.parse(s).unwrap();
+        assert!(expr.beta_normalize(100));
+        dsl.display(&expr)
+    };
+    assert_eq!(normalize("(λ ((λ (λ (+ $1 $0))) $0))"), "(λ (λ (+ $1 $0)))");
+    // free variables of the argument are shifted under binders
+    assert_eq!(normalize("(λ ((λ (λ $1)) (λ $1)))"), "(λ (λ (λ $2)))");
+    // reduction happens inside arguments
+    assert_eq!(normalize("(+ ((λ $0) 1) ((λ 0) 1))"), "(+ 1 0)");
+
+    // equivalence modulo inventions, beta, and eta
+    let invention = dsl.parse("(λ (+ $0 $0))").unwrap();
+    dsl.invent(invention, -0.5).unwrap();
+    let double = dsl.parse("#(λ (+ $0 $0))").unwrap();
+    let a = dsl.parse("(λ (#(λ (+ $0 $0)) (+ 1 $0)))").unwrap();
+    let b = dsl.parse("(λ (+ (+ 1 $0) (+ 1 $0)))").unwrap();
+    let c = dsl.parse("(λ (+ (+ 1 $0) (+ $0 1)))").unwrap();
+    assert!(dsl.equivalent(&a, &b, 100));
+    assert!(!dsl.equivalent(&a, &c, 100));
+    assert!(!dsl.equivalent(&double, &dsl.parse("+").unwrap(), 100));
+    assert_eq!(
+        dsl.display(&dsl.canonicalize(&double, 100).unwrap()),
+        "(λ (+ $0 $0))"
+    );
+}
+
+#[test]
+fn lambda_sketch() {
+    let dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("true", ptp!(bool)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        ("if", ptp!(0; @arrow[tp!(bool), tp!(0), tp!(0), tp!(0)])),
+        ("id", ptp!(0; @arrow[tp!(0), tp!(0)])),
+    ]);
+
+    let sketch = dsl.parse("(λ (+ (?f ?x) $0))").unwrap();
+    assert_eq!(dsl.display(&sketch), "(λ (+ (? ?) $0))");
+    assert!(dsl.parse("(+ ?f-1 0)").is_err());
+    assert_eq!(
+        dsl.infer(&sketch).unwrap(),
+        ptp!(@arrow[tp!(int), tp!(int)])
+    );
+
+    // the type of the second hole depends on how the first is filled
+    let req = ptp!(@arrow[tp!(int), tp!(int)]);
+    let completions: Vec<_> = dsl.enumerate_sketch(sketch, req.clone()).take(50).collect();
+    assert_eq!(completions.len(), 50);
+    for (expr, logprior) in &completions {
+        assert!(!dsl.display(expr).contains('?'));
+        let mut ctx = Context::default();
+        let tp = dsl.infer(expr).unwrap().instantiate(&mut ctx);
+        let req_tp = req.instantiate(&mut ctx);
+        assert!(ctx.unify(&tp, &req_tp).is_ok());
+        assert!(*logprior < 0.0);
+    }
+    let displayed: Vec<_> = completions
+        .iter()
+        .map(|(expr, _)| dsl.display(expr))
+        .collect();
+    assert!(displayed.contains(&String::from("(λ (+ ((λ (id $0)) $0) $0))")));
+    assert!(displayed.contains(&String::from("(λ (+ ((λ 0) true) $0))")));
+    assert!(!displayed.contains(&String::from("(λ (+ ((λ $0) true) $0))")));
+
+    // a sketch without holes is its own completion, and an ill-typed one has none
+    let full = dsl.parse("(λ (+ $0 0))").unwrap();
+    let completions: Vec<_> = dsl.enumerate_sketch(full.clone(), req.clone()).collect();
+    assert_eq!(completions, vec![(full, 0.0)]);
+    let ill_typed = dsl.parse("(λ (+ ? true))").unwrap();
+    assert_eq!(dsl.enumerate_sketch(ill_typed, req).count(), 0);
+}
+
+#[test]
+fn lambda_eval_sketch() {
+    fn evaluate(name: &str, inps: &[i32]) -> Result<i32, ()> {
+        match name {
+            "0" => Ok(0),
+            "+" => Ok(inps[0] + inps[1]),
+            _ => unreachable!(),
+        }
+    }
+    let dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    let limits = EvalLimits::default();
+
+    let sketch = dsl.parse("(λ (+ ? $0))").unwrap();
+    let evaluated = dsl.eval_with_limits(&sketch, SimpleEvaluator::of(evaluate), &[1], &limits);
+    assert_eq!(evaluated, Err(EvalError::Hole));
+    // without limits, the evaluator's limit error is returned instead
+    let evaluated = dsl.eval(&sketch, SimpleEvaluator::of(evaluate), &[1]);
+    assert_eq!(evaluated, Err(()));
+
+    // a hole is only an error once evaluation reaches it
+    let sketch = dsl.parse("(λ ((λ $1) (λ ?)))").unwrap();
+    let evaluated = dsl.eval_with_limits(&sketch, SimpleEvaluator::of(evaluate), &[1], &limits);
+    assert_eq!(evaluated, Ok(1));
+}
+
+#[test]
+fn lambda_eval_limits() {
+    #[derive(Clone, PartialEq)]
+    enum ListSpace {
+        Num(i32),
+        List(Vec<i32>),
+        Func(LiftedFunction<ListSpace, ListEvaluator>),
+    }
+
+    #[derive(Clone)]
+    struct ListEvaluator;
+    impl Evaluator for ListEvaluator {
+        type Space = ListSpace;
+        type Error = ();
+        fn evaluate(
+            &self,
+            primitive: &str,
+            inps: &[Self::Space],
+        ) -> Result<Self::Space, Self::Error> {
+            match primitive {
+                "1" => Ok(ListSpace::Num(1)),
+                "+" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Num(x), &ListSpace::Num(y)) => Ok(ListSpace::Num(x + y)),
+                    _ => unreachable!(),
+                },
+                "map" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Func(ref f), &ListSpace::List(ref xs)) => {
+                        let mut ys = Vec::new();
+                        for &x in xs {
+                            match f.eval(&[ListSpace::Num(x)])? {
+                                ListSpace::Num(y) => ys.push(y),
+                                _ => panic!("map given invalid function"),
+                            }
+                        }
+                        Ok(ListSpace::List(ys))
+                    }
+                    _ => unreachable!(),
+                },
+                // like map, but ignores failures of the function
+                "map_or_0" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Func(ref f), &ListSpace::List(ref xs)) => {
+                        let ys = xs.iter().map(|&x| match f.eval(&[ListSpace::Num(x)]) {
+                            Ok(ListSpace::Num(y)) => y,
+                            _ => 0,
+                        });
+                        Ok(ListSpace::List(ys.collect()))
+                    }
+                    _ => unreachable!(),
+                },
+                "fn" => Ok(inps[0].clone()),
+                _ => unreachable!(),
+            }
+        }
+        fn lift(&self, f: LiftedFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
+            Ok(ListSpace::Func(f))
+        }
+        fn limit_error(&self) -> Self::Error {}
+    }
+
+    let dsl = Language::uniform(vec![
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        (
+            "map",
+            ptp!(@arrow[
+                tp!(@arrow[tp!(int), tp!(int)]),
+                tp!(list(tp!(int))),
+                tp!(list(tp!(int)))
+            ]),
+        ),
+        (
+            "map_or_0",
+            ptp!(@arrow[
+                tp!(@arrow[tp!(int), tp!(int)]),
+                tp!(list(tp!(int))),
+                tp!(list(tp!(int)))
+            ]),
+        ),
+        ("fn", ptp!(@arrow[tp!(@arrow[tp!(int), tp!(int)]), tp!(fn)])),
+    ]);
+    let no_limits = EvalLimits::default();
+    let fuel = EvalLimits {
+        fuel: Some(1000),
+        ..Default::default()
+    };
+
+    // a term which grows forever under reduction
+    let expr = dsl.parse("((λ ($0 $0 $0)) (λ ($0 $0 $0)))").unwrap();
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &[], &fuel);
+    assert!(evaluated == Err(EvalError::OutOfFuel));
+
+    // deep nesting
+    let expr = dsl.parse("(+ 1 (+ 1 (+ 1 (+ 1 (+ 1 1)))))").unwrap();
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &[], &no_limits);
+    assert!(evaluated == Ok(ListSpace::Num(6)));
+    let max_depth = EvalLimits {
+        max_depth: Some(4),
+        ..Default::default()
+    };
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &[], &max_depth);
+    assert!(evaluated == Err(EvalError::MaxDepthExceeded));
+
+    let timeout = EvalLimits {
+        timeout: Some(std::time::Duration::from_secs(0)),
+        ..Default::default()
+    };
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &[], &timeout);
+    assert!(evaluated == Err(EvalError::Timeout));
+
+    // limits are shared with lifted functions
+    let expr = dsl.parse("(λ (map (λ (+ $0 (+ 1 1))) $0))").unwrap();
+    let inps = [ListSpace::List((0..100).collect())];
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &inps, &no_limits);
+    assert!(evaluated == Ok(ListSpace::List((2..102).collect())));
+    let fuel = EvalLimits {
+        fuel: Some(100),
+        ..Default::default()
+    };
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &inps, &fuel);
+    assert!(evaluated == Err(EvalError::OutOfFuel));
+
+    // an exhausted limit is reported even if the evaluator ignores it
+    let expr = dsl.parse("(λ (map_or_0 (λ (+ $0 (+ 1 1))) $0))").unwrap();
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &inps, &no_limits);
+    assert!(evaluated == Ok(ListSpace::List((2..102).collect())));
+    let evaluated = dsl.eval_with_limits(&expr, ListEvaluator, &inps, &fuel);
+    assert!(evaluated == Err(EvalError::OutOfFuel));
+
+    // a lifted function keeps its limits after evaluation returns
+    let expr = dsl.parse("(fn (λ (+ $0 1)))").unwrap();
+    let f = match dsl.eval_with_limits(&expr, ListEvaluator, &[], &fuel) {
+        Ok(ListSpace::Func(f)) => f,
+        _ => panic!("evaluation did not give a function"),
+    };
+    assert!(f.eval(&[ListSpace::Num(1)]) == Ok(ListSpace::Num(2)));
+    let results: Vec<_> = (0..100).map(|_| f.eval(&[ListSpace::Num(1)])).collect();
+    assert!(results.contains(&Err(())));
+}
+
+#[test]
+fn lambda_fix() {
+    #[derive(Clone, Debug, PartialEq)]
+    enum ListSpace {
+        Bool(bool),
+        Num(i32),
+        List(Vec<i32>),
+    }
+
+    struct ListEvaluator;
+    impl LazyEvaluator for ListEvaluator {
+        type Space = ListSpace;
+        type Error = ();
+        fn lazy_evaluate(
+            &self,
+            primitive: &str,
+            inps: &[LiftedLazyFunction<Self::Space, Self>],
+        ) -> Result<Self::Space, Self::Error> {
+            match primitive {
+                "0" => Ok(ListSpace::Num(0)),
+                "1" => Ok(ListSpace::Num(1)),
+                "+" => match (inps[0].eval(&[])?, inps[1].eval(&[])?) {
+                    (ListSpace::Num(x), ListSpace::Num(y)) => Ok(ListSpace::Num(x + y)),
+                    _ => unreachable!(),
+                },
+                "if" => match inps[0].eval(&[])? {
+                    ListSpace::Bool(true) => inps[1].eval(&[]),
+                    ListSpace::Bool(false) => inps[2].eval(&[]),
+                    _ => unreachable!(),
+                },
+                "empty?" => match inps[0].eval(&[])? {
+                    ListSpace::List(xs) => Ok(ListSpace::Bool(xs.is_empty())),
+                    _ => unreachable!(),
+                },
+                "cdr" => match inps[0].eval(&[])? {
+                    ListSpace::List(ref xs) if !xs.is_empty() => {
+                        Ok(ListSpace::List(xs[1..].to_vec()))
+                    }
+                    _ => Err(()),
+                },
+                _ => unreachable!(),
+            }
+        }
+        fn limit_error(&self) -> Self::Error {}
+    }
+
+    struct NumEvaluator;
+    impl Evaluator for NumEvaluator {
+        type Space = ListSpace;
+        type Error = ();
+        fn evaluate(
+            &self,
+            primitive: &str,
+            inps: &[Self::Space],
+        ) -> Result<Self::Space, Self::Error> {
+            match primitive {
+                "0" => Ok(ListSpace::Num(0)),
+                "1" => Ok(ListSpace::Num(1)),
+                "+" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Num(x), &ListSpace::Num(y)) => Ok(ListSpace::Num(x + y)),
+                    _ => unreachable!(),
+                },
+                _ => Err(()),
+            }
+        }
+        fn limit_error(&self) -> Self::Error {}
+    }
+
+    let dsl = Language::uniform(vec![
+        fix_primitive(),
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        ("if", ptp!(0; @arrow[tp!(bool), tp!(0), tp!(0), tp!(0)])),
+        ("empty?", ptp!(0; @arrow[tp!(list(tp!(0))), tp!(bool)])),
+        ("cdr", ptp!(0; @arrow[tp!(list(tp!(0))), tp!(list(tp!(0)))])),
+    ]);
+    let fuel = EvalLimits {
+        fuel: Some(200),
+        ..Default::default()
+    };
+
+    // recursion terminates with a lazy conditional
+    let length = dsl
+        .parse("(λ (fix (λ (λ (if (empty? $0) 0 (+ 1 ($1 (cdr $0)))))) $0))")
+        .unwrap();
+    let tp = dsl.infer(&length).unwrap();
+    assert_eq!(
+        tp.instantiate(&mut Context::default()).returns(),
+        Some(&tp!(int))
+    );
+    let inps = [ListSpace::List(vec![3, 1, 4, 1, 5])];
+    let evaluated = dsl.lazy_eval(&length, ListEvaluator, &inps);
+    assert_eq!(evaluated, Ok(ListSpace::Num(5)));
+    let evaluated = dsl.lazy_eval_with_limits(&length, ListEvaluator, &inps, &fuel);
+    assert_eq!(evaluated, Ok(ListSpace::Num(5)));
+
+    // a fixed point which doesn't recurse
+    let expr = dsl.parse("(fix (λ (λ (+ 1 $0))) 1)").unwrap();
+    let evaluated = dsl.eval(&expr, NumEvaluator, &[]);
+    assert_eq!(evaluated, Ok(ListSpace::Num(2)));
+
+    // runaway recursion is stopped by limits
+    let expr = dsl.parse("(fix (λ (λ (+ 1 ($1 $0)))) 1)").unwrap();
+    let evaluated = dsl.eval_with_limits(&expr, NumEvaluator, &[], &fuel);
+    assert_eq!(evaluated, Err(EvalError::OutOfFuel));
+    let evaluated = dsl.lazy_eval_with_limits(&expr, ListEvaluator, &[], &fuel);
+    assert_eq!(evaluated, Err(EvalError::OutOfFuel));
+
+    // enumeration avoids trivially non-terminating recursion
+    let fix = &Expression::Primitive(0);
+    let f = dsl.parse("(λ (λ (+ 1 ($1 $0))))").unwrap();
+    assert!(dsl.violates_symmetry(fix, 0, &f));
+    let f = dsl.parse("(λ (λ ($1 (cdr $0))))").unwrap();
+    assert!(dsl.violates_symmetry(fix, 0, &f));
+    let f = dsl
+        .parse("(λ (λ (if (empty? $0) 0 (+ 1 ($1 (cdr $0))))))")
+        .unwrap();
+    assert!(!dsl.violates_symmetry(fix, 0, &f));
+    let f = dsl.parse("(λ (λ (+ 1 $0)))").unwrap();
+    assert!(!dsl.violates_symmetry(fix, 0, &f));
+}
+
+#[test]
+fn lambda_compiled() {
+    use std::sync::Arc;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum ListSpace {
+        Bool(bool),
+        Num(i32),
+        List(Vec<i32>),
+        Func(LiftedFunction<ListSpace, ListEvaluator>),
+    }
+
+    #[derive(Clone)]
+    struct ListEvaluator;
+    impl Evaluator for ListEvaluator {
+        type Space = ListSpace;
+        type Error = &'static str;
+        fn evaluate(
+            &self,
+            primitive: &str,
+            inps: &[Self::Space],
+        ) -> Result<Self::Space, Self::Error> {
+            match primitive {
+                "1" => Ok(ListSpace::Num(1)),
+                "+" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Num(x), &ListSpace::Num(y)) => Ok(ListSpace::Num(x + y)),
+                    _ => unreachable!(),
+                },
+                "car" => match inps[0] {
+                    ListSpace::List(ref xs) if !xs.is_empty() => Ok(ListSpace::Num(xs[0])),
+                    ListSpace::List(_) => Err("car of empty list"),
+                    _ => unreachable!(),
+                },
+                "map" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Func(ref f), &ListSpace::List(ref xs)) => {
+                        let mut ys = Vec::new();
+                        for &x in xs {
+                            match f.eval(&[ListSpace::Num(x)])? {
+                                ListSpace::Num(y) => ys.push(y),
+                                _ => panic!("map given invalid function"),
+                            }
+                        }
+                        Ok(ListSpace::List(ys))
+                    }
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        }
+        fn lift(&self, f: LiftedFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
+            Ok(ListSpace::Func(f))
+        }
+        fn limit_error(&self) -> Self::Error {
+            "evaluation stopped"
+        }
+    }
+
+    struct LazyListEvaluator;
+    impl LazyEvaluator for LazyListEvaluator {
+        type Space = ListSpace;
+        type Error = &'static str;
+        fn lazy_evaluate(
+            &self,
+            primitive: &str,
+            inps: &[LiftedLazyFunction<Self::Space, Self>],
+        ) -> Result<Self::Space, Self::Error> {
+            match primitive {
+                "1" => Ok(ListSpace::Num(1)),
+                "+" => match (inps[0].eval(&[])?, inps[1].eval(&[])?) {
+                    (ListSpace::Num(x), ListSpace::Num(y)) => Ok(ListSpace::Num(x + y)),
+                    _ => unreachable!(),
+                },
+                "car" => match inps[0].eval(&[])? {
+                    ListSpace::List(ref xs) if !xs.is_empty() => Ok(ListSpace::Num(xs[0])),
+                    ListSpace::List(_) => Err("car of empty list"),
+                    _ => unreachable!(),
+                },
+                "empty?" => match inps[0].eval(&[])? {
+                    ListSpace::List(xs) => Ok(ListSpace::Bool(xs.is_empty())),
+                    _ => unreachable!(),
+                },
+                "if" => match inps[0].eval(&[])? {
+                    ListSpace::Bool(true) => inps[1].eval(&[]),
+                    ListSpace::Bool(false) => inps[2].eval(&[]),
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        }
+        fn limit_error(&self) -> Self::Error {
+            "evaluation stopped"
+        }
+    }
+
+    let dsl = Language::uniform(vec![
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        ("car", ptp!(@arrow[tp!(list(tp!(int))), tp!(int)])),
+        ("empty?", ptp!(@arrow[tp!(list(tp!(int))), tp!(bool)])),
+        ("if", ptp!(0; @arrow[tp!(bool), tp!(0), tp!(0), tp!(0)])),
+        (
+            "map",
+            ptp!(@arrow[
+                tp!(@arrow[tp!(int), tp!(int)]),
+                tp!(list(tp!(int))),
+                tp!(list(tp!(int)))
+            ]),
+        ),
+    ]);
+    let evaluator = Arc::new(ListEvaluator);
+    let lazy_evaluator = Arc::new(LazyListEvaluator);
+    let inputs: Vec<Vec<ListSpace>> = vec![
+        vec![ListSpace::List(vec![3, 5])],
+        vec![ListSpace::List(vec![])],
+        vec![ListSpace::List((0..100).collect())],
+    ];
+    let fuel = EvalLimits {
+        fuel: Some(100),
+        ..Default::default()
+    };
+
+    // a lifted function, and an evaluator error on the empty list
+    let exprs = vec![
+        "(λ (map (λ (+ $0 1)) $0))",
+        "(λ (+ (car $0) 1))",
+        "(λ (car (map (λ (+ (+ 1 1) $0)) $0)))",
+        "(λ (+ ((λ (+ $0 1)) (car $0)) (car $0)))",
+    ];
+    for name in exprs {
+        let expr = dsl.parse(name).unwrap();
+        let compiled = dsl.compile(&expr);
+        let batch = compiled.eval_batch(&evaluator, inputs.iter().map(|inps| &inps[..]));
+        let limited =
+            compiled.eval_batch_with_limits(&evaluator, inputs.iter().map(|inps| &inps[..]), &fuel);
+        for (i, inps) in inputs.iter().enumerate() {
+            let expected = dsl.eval_arc(&expr, &evaluator, inps);
+            assert_eq!(compiled.eval(&evaluator, inps), expected);
+            assert_eq!(batch[i], expected);
+            let expected = dsl.eval_with_limits(&expr, ListEvaluator, inps, &fuel);
+            assert_eq!(compiled.eval_with_limits(&evaluator, inps, &fuel), expected);
+            assert_eq!(limited[i], expected);
+        }
+        if name.contains("car") {
+            assert_eq!(batch[1], Err("car of empty list"));
+        }
+    }
+    let expr = dsl.parse("(λ (map (λ (+ $0 1)) $0))").unwrap();
+    let limited = dsl.compile(&expr).eval_batch_with_limits(
+        &evaluator,
+        inputs.iter().map(|inps| &inps[..]),
+        &fuel,
+    );
+    assert_eq!(limited[0], Ok(ListSpace::List(vec![4, 6])));
+    assert_eq!(limited[2], Err(EvalError::OutOfFuel));
+
+    // the untaken branch is not evaluated
+    let expr = dsl.parse("(λ (if (empty? $0) 1 (car $0)))").unwrap();
+    let compiled = dsl.compile(&expr);
+    let batch: Vec<_> = compiled
+        .lazy_eval_batch(&lazy_evaluator, inputs.iter().map(|inps| &inps[..]))
+        .collect();
+    let limited: Vec<_> = compiled
+        .lazy_eval_batch_with_limits(&lazy_evaluator, inputs.iter().map(|inps| &inps[..]), &fuel)
+        .collect();
+    for (i, inps) in inputs.iter().enumerate() {
+        let expected = dsl.lazy_eval_arc(&expr, &lazy_evaluator, inps);
+        assert_eq!(compiled.lazy_eval(&lazy_evaluator, inps), expected);
+        assert_eq!(batch[i], expected);
+        let expected = dsl.lazy_eval_with_limits(&expr, LazyListEvaluator, inps, &fuel);
+        assert_eq!(
+            compiled.lazy_eval_with_limits(&lazy_evaluator, inps, &fuel),
+            expected
+        );
+        assert_eq!(limited[i], expected);
+    }
+    assert_eq!(
+        batch,
+        vec![
+            Ok(ListSpace::Num(3)),
+            Ok(ListSpace::Num(1)),
+            Ok(ListSpace::Num(0))
+        ]
+    );
+}
+
+#[test]
+fn lambda_eval_trace() {
+    #[derive(Clone, Debug, PartialEq)]
+    enum ListSpace {
+        Num(i32),
+        List(Vec<i32>),
+        Func(LiftedFunction<ListSpace, ListEvaluator>),
+    }
+
+    #[derive(Clone)]
+    struct ListEvaluator;
+    impl Evaluator for ListEvaluator {
+        type Space = ListSpace;
+        type Error = ();
+        fn evaluate(
+            &self,
+            primitive: &str,
+            inps: &[Self::Space],
+        ) -> Result<Self::Space, Self::Error> {
+            match primitive {
+                "1" => Ok(ListSpace::Num(1)),
+                "+" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Num(x), &ListSpace::Num(y)) => Ok(ListSpace::Num(x + y)),
+                    _ => unreachable!(),
+                },
+                "map" => match (&inps[0], &inps[1]) {
+                    (&ListSpace::Func(ref f), &ListSpace::List(ref xs)) => {
+                        let mut ys = Vec::new();
+                        for &x in xs {
+                            match f.eval(&[ListSpace::Num(x)])? {
+                                ListSpace::Num(y) => ys.push(y),
+                                _ => panic!("map given invalid function"),
+                            }
+                        }
+                        Ok(ListSpace::List(ys))
+                    }
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        }
+        fn lift(&self, f: LiftedFunction<Self::Space, Self>) -> Result<Self::Space, ()> {
+            Ok(ListSpace::Func(f))
+        }
+        fn limit_error(&self) -> Self::Error {}
+    }
+
+    let dsl = Language::uniform(vec![
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        (
+            "map",
+            ptp!(@arrow[
+                tp!(@arrow[tp!(int), tp!(int)]),
+                tp!(list(tp!(int))),
+                tp!(list(tp!(int)))
+            ]),
+        ),
+    ]);
+    let expr = dsl.parse("(λ (map (λ (+ $0 1)) $0))").unwrap();
+    let inps = [ListSpace::List(vec![3, 5])];
+    let (evaluated, trace) = dsl.eval_with_trace(&expr, ListEvaluator, &inps);
+    assert_eq!(evaluated, Ok(ListSpace::List(vec![4, 6])));
+    let func =
+        "Func(LiftedFunction(Abstraction(1, Application([Primitive(+), Index(0), Primitive(1)]))))";
+    assert_eq!(
+        trace.to_string(),
+        format!(
+            "(map {} List([3, 5])) = List([4, 6])
+  (λ Num(3)) = Num(4)
+    1 = Num(1)
+    (+ Num(3) Num(1)) = Num(4)
+  (λ Num(5)) = Num(6)
+    1 = Num(1)
+    (+ Num(5) Num(1)) = Num(6)
+",
+            func
+        )
+    );
+
+    let json = serde_json::to_value(&trace).unwrap();
+    assert_eq!(json["events"].as_array().unwrap().len(), 7);
+    assert_eq!(
+        json["events"][0],
+        serde_json::json!({
+            "kind": "primitive",
+            "depth": 0,
+            "name": "map",
+            "args": [func, "List([3, 5])"],
+            "result": "List([4, 6])",
+        })
+    );
+    assert_eq!(
+        json["events"][1],
+        serde_json::json!({
+            "kind": "lifted",
+            "depth": 1,
+            "args": ["Num(3)"],
+            "result": "Num(4)",
+        })
+    );
+}
+
+#[test]
+fn lambda_prune() {
+    use programinduction::ECFrontier;
+
+    let mut dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        ("*", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+    ]);
+    dsl.add_symmetry_violation(2, 0, 0);
+    dsl.add_symmetry_violation(2, 1, 2);
+    dsl.add_symmetry_violation(3, 0, 1);
+    dsl.invent(dsl.parse("(* 0)").unwrap(), -1.0).unwrap();
+    dsl.invent(dsl.parse("(+ 1)").unwrap(), -1.0).unwrap();
+    dsl.invent(dsl.parse("(λ (#(+ 1) (#(+ 1) $0)))").unwrap(), -1.0)
+        .unwrap();
+    dsl.invent(dsl.parse("(λ (#(* 0) $0))").unwrap(), -1.0)
+        .unwrap();
+
+    let exprs = vec![
+        dsl.parse("(#(λ (#(+ 1) (#(+ 1) $0))) 1)").unwrap(),
+        dsl.parse("(λ (+ $0 1))").unwrap(),
+    ];
+    let mut frontiers = vec![
+        ECFrontier(vec![(exprs[0].clone(), -3.0, 0.0)]),
+        ECFrontier::default(),
+        ECFrontier(vec![(exprs[1].clone(), -4.0, 0.0)]),
+    ];
+    let mut pruned = dsl.clone();
+    let mut pruned_frontiers = frontiers.clone();
+    pruned.prune_invented(&mut pruned_frontiers);
+    assert_eq!(pruned.primitives.len(), 4);
+    assert_eq!(pruned.invented.len(), 2);
+    assert_eq!(pruned.symmetry_violations, dsl.symmetry_violations);
+    assert_eq!(
+        pruned.display(&pruned_frontiers[0][0].0),
+        "(#(λ (#(+ 1) (#(+ 1) $0))) 1)"
+    );
+
+    dsl.name_invented(1, "inc").unwrap();
+    dsl.name_invented(3, "times_zero").unwrap();
+    let displayed: Vec<_> = exprs.iter().map(|expr| dsl.display(expr)).collect();
+    dsl.prune(&mut frontiers);
+    assert_eq!(dsl.invented_name(0), Some("inc"));
+    assert_eq!(dsl.invented_name(1), None);
+    assert_eq!(dsl.invented_by_name("times_zero"), None);
+    let names: Vec<_> = dsl.primitives.iter().map(|p| p.0.as_str()).collect();
+    assert_eq!(names, vec!["1", "+"]);
+    let invented: Vec<_> = dsl.invented.iter().map(|i| dsl.display(&i.0)).collect();
+    assert_eq!(invented, vec!["(+ 1)", "(λ (#inc (#inc $0)))"]);
+    assert_eq!(dsl.symmetry_violations, vec![(1, 1, 1)]);
+    for (frontier, displayed) in frontiers.iter().filter(|f| !f.is_empty()).zip(displayed) {
+        assert_eq!(dsl.display(&frontier[0].0), displayed);
+    }
+    assert_eq!(frontiers[0][0].1, -3.0);
+    assert!(dsl.infer(&frontiers[0][0].0).is_ok());
+}
+
+#[test]
+fn lambda_invented_names() {
+    use std::collections::HashMap;
+
+    let mut dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        (
+            "map",
+            ptp!(0, 1; @arrow[tp!(@arrow[tp!(0), tp!(1)]), tp!(list(tp!(0))), tp!(list(tp!(1)))]),
+        ),
+    ]);
+    dsl.invent(dsl.parse("(+ 1)").unwrap(), -1.0).unwrap();
+    dsl.invent(dsl.parse("(λ (map #(+ 1) $0))").unwrap(), -1.0)
+        .unwrap();
+    dsl.invent(
+        dsl.parse("(λ (#(λ (map #(+ 1) $0)) (#(λ (map #(+ 1) $0)) $0)))")
+            .unwrap(),
+        -1.0,
+    )
+    .unwrap();
+    let expr = dsl
+        .parse("(#(λ (#(λ (map #(+ 1) $0)) (#(λ (map #(+ 1) $0)) $0))) $0)")
+        .unwrap();
+
+    // names are optional
+    assert_eq!(dsl.invented_name(0), None);
+    assert_eq!(
+        dsl.display_library(),
+        "#(+ 1) : int → int
+#(λ (map #(+ 1) $0)) : list(int) → list(int)
+#(λ (#(λ (map #(+ 1) $0)) (#(λ (map #(+ 1) $0)) $0))) : list(int) → list(int)
+"
+    );
+
+    dsl.name_invented(1, "incr_all").unwrap();
+    dsl.name_inventions();
+    assert_eq!(dsl.invented_name(0), Some("f0"));
+    assert_eq!(dsl.invented_name(1), Some("incr_all"));
+    assert_eq!(dsl.invented_name(2), Some("f2"));
+    assert_eq!(dsl.display(&expr), "(#f2 $0)");
+    assert_eq!(dsl.parse("(#f2 $0)").unwrap(), expr);
+    assert_eq!(
+        dsl.parse("(λ (incr_all $0))").unwrap_err().msg,
+        "unexpected end of expression"
+    );
+    assert!(dsl.parse("(#f3 $0)").is_err());
+    assert_eq!(
+        dsl.display_library(),
+        "#f0 = (+ 1) : int → int
+#incr_all = (λ (map #f0 $0)) : list(int) → list(int)
+#f2 = (λ (#incr_all (#incr_all $0))) : list(int) → list(int)
+"
+    );
+
+    let mut conversions = HashMap::new();
+    conversions.insert(String::from("f2"), String::from("incr-twice"));
+    let expr = dsl.parse("(λ (#f2 (#incr_all $0)))").unwrap();
+    assert_eq!(
+        dsl.lispify(&expr, &conversions),
+        "(λ (a) (incr-twice (incr_all a)))"
+    );
+
+    // renaming
+    dsl.name_invented(2, "incr_twice").unwrap();
+    assert_eq!(dsl.display(&expr), "(λ (#incr_twice (#incr_all $0)))");
+    assert_eq!(dsl.invented_by_name("incr_twice"), Some(2));
+    assert_eq!(dsl.invented_names[2], Some(String::from("incr_twice")));
+    assert_eq!(dsl.invented_by_name("f2"), None);
+
+    // invalid names
+    assert_eq!(
+        dsl.name_invented(3, "incr_thrice"),
+        Err(NameError::InvalidInvention(3))
+    );
+    assert_eq!(
+        dsl.name_invented(0, "incr one"),
+        Err(NameError::InvalidName(String::from("incr one")))
+    );
+    assert_eq!(
+        dsl.name_invented(0, ""),
+        Err(NameError::InvalidName(String::new()))
+    );
+    assert_eq!(
+        dsl.name_invented(0, "incr_all"),
+        Err(NameError::NameInUse(String::from("incr_all")))
+    );
+    assert_eq!(dsl.invented_name(0), Some("f0"));
+
+    // names stay with their inventions
+    dsl.invented.pop();
+    assert_eq!(dsl.invented_by_name("incr_twice"), None);
+    dsl.invent(dsl.parse("(#f0 1)").unwrap(), -1.0).unwrap();
+    assert_eq!(dsl.invented_name(2), None);
+}
+
+#[test]
+fn lambda_named_syntax() {
+    let mut dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("x", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        (
+            "map",
+            ptp!(0, 1; @arrow[tp!(@arrow[tp!(0), tp!(1)]), tp!(list(tp!(0))), tp!(list(tp!(1)))]),
+        ),
+    ]);
+    dsl.invent(dsl.parse("(λ (+ $0 1))").unwrap(), -1.0)
+        .unwrap();
+    dsl.invent(dsl.parse("(λ (λ (+ $0 $1)))").unwrap(), -1.0)
+        .unwrap();
+    dsl.name_invented(1, "add").unwrap();
+
+    // binders are resolved to De Bruijn indices, innermost first
+    let expr = dsl.parse_named("(lambda (a b) (+ b a))").unwrap();
+    assert_eq!(expr, dsl.parse("(λ (λ (+ $0 $1)))").unwrap());
+    assert_eq!(dsl.display_named(&expr), "(λ (y z) (+ z y))");
+    let expr = dsl.parse_named("(λ (a) (λ (a) (+ a 1)))").unwrap();
+    assert_eq!(expr, dsl.parse("(λ (λ (+ $0 1)))").unwrap());
+    let expr = dsl.parse_named("(λ (a) (+ a x))").unwrap();
+    assert_eq!(expr, dsl.parse("(λ (+ $0 x))").unwrap());
+    let expr = dsl.parse_named("(λ (x) (+ x x))").unwrap();
+    assert_eq!(expr, dsl.parse("(λ (+ $0 $0))").unwrap());
+    let expr = dsl.parse_named("(map #(λ (n) (+ n 1)) $0)").unwrap();
+    assert_eq!(expr, dsl.parse("(map #(λ (+ $0 1)) $0)").unwrap());
+    assert_eq!(dsl.display_named(&expr), "(map #(λ (y) (+ y 1)) $0)");
+    let expr = dsl.parse_named("(λ (n) (#add n ?))").unwrap();
+    assert_eq!(expr, dsl.parse("(λ (#add $0 ?))").unwrap());
+
+    // round trips
+    let exprs: Vec<_> = dsl
+        .enumerate(ptp!(@arrow[tp!(list(tp!(int))), tp!(list(tp!(int)))]))
+        .take(500)
+        .map(|(expr, _)| expr)
+        .collect();
+    for expr in exprs {
+        let named = dsl.display_named(&expr);
+        assert_eq!(dsl.parse_named(&named).unwrap(), expr, "{}", named);
+    }
+    let expr = dsl.parse("(λ ((λ (λ (+ $3 (+ $0 $1)))) $0))").unwrap();
+    let named = dsl.display_named(&expr);
+    assert_eq!(named, "(λ (y) ((λ (z u) (+ $0 (+ u z))) y))");
+    assert_eq!(dsl.parse_named(&named).unwrap(), expr);
+
+    // type annotations
+    let expr = dsl
+        .parse_named("(λ ((f : int → int) (xs : list(int))) (map f xs))")
+        .unwrap();
+    assert_eq!(expr, dsl.parse("(λ (λ (map $1 $0)))").unwrap());
+    assert!(dsl.parse_named("(λ ((n : t0)) (+ n 1))").is_ok());
+    let inp = "(λ (f (xs : int)) (map f xs))";
+    let err = dsl.parse_named(inp).unwrap_err();
+    assert_eq!(err.location, inp.find("int").unwrap());
+    assert_eq!(
+        err.msg,
+        "type annotation is inconsistent with the expression"
+    );
+    let inp = "(λ ((n : int)) (λ ((m : bool)) (+ n m)))";
+    let err = dsl.parse_named(inp).unwrap_err();
+    assert_eq!(err.location, inp.find("bool").unwrap());
+
+    // invalid syntax
+    assert!(dsl.parse_named("(λ (+ $0 1))").is_err());
+    assert!(dsl.parse_named("(λ () 1)").is_err());
+    assert!(dsl.parse_named("(λ (n) (+ n m))").is_err());
+    assert!(dsl.parse_named("(λ ((n : foo(()) 1)").is_err());
+    assert!(dsl.parse_named("(+ 1 1").is_err());
+    assert!(dsl.parse_named("(+ 1 1))").is_err());
+}
+
+#[test]
+fn lambda_export() {
+    use std::collections::HashMap;
+
+    let mut dsl = Language::uniform(vec![
+        ("0", ptp!(int)),
+        ("1", ptp!(int)),
+        ("+", ptp!(@arrow[tp!(int), tp!(int), tp!(int)])),
+        (
+            "fold",
+            ptp!(0, 1; @arrow[
+                tp!(@arrow[tp!(0), tp!(1), tp!(1)]),
+                tp!(1),
+                tp!(list(tp!(0))),
+                tp!(1)
+            ]),
+        ),
+        ("neg", ptp!(@arrow[tp!(int), tp!(int)])),
+    ]);
+    dsl.invent(dsl.parse("(+ 1)").unwrap(), -1.0).unwrap();
+    dsl.invent(dsl.parse("(λ (λ (#(+ 1) (+ $0 $1))))").unwrap(), -1.0)
+        .unwrap();
+    dsl.invent(dsl.parse("(#(+ 1) 0)").unwrap(), -1.0).unwrap();
+    dsl.name_invented(1, "add-incr").unwrap();
+    let expr = dsl
+        .parse("(λ (λ (fold #(λ (λ (#(+ 1) (+ $0 $1)))) (+ $0 #(#(+ 1) 0)) $1)))")
+        .unwrap();
+
+    let python: HashMap<String, String> = vec![
+        ("0", "0"),
+        ("1", "1"),
+        ("+", "{0} + {1}"),
+        (
+            "fold",
+            "functools.reduce(lambda acc, x: {0}(x)(acc), {2}, {1})",
+        ),
+    ]
+    .into_iter()
+    .map(|(name, template)| (String::from(name), String::from(template)))
+    .collect();
+    assert_eq!(
+        dsl.export(&expr, "sum plus", ExportTarget::Python, &python)
+            .unwrap(),
+        "\
+def f0(y):
+    return (1 + y)
+
+def add_incr(y, z):
+    return f0((z + y))
+
+def f2():
+    return f0(0)
+
+def sum_plus(y, z):
+    return (functools.reduce(lambda acc, x: (lambda u: lambda v: add_incr(u, v))(x)(acc), y, (z + f2())))
+"
+    );
+
+    let scheme: HashMap<String, String> = vec![
+        ("0", "0"),
+        ("1", "1"),
+        ("+", "(+ {0} {1})"),
+        ("fold", "(fold-left (lambda (acc x) (({0} x) acc)) {1} {2})"),
+    ]
+    .into_iter()
+    .map(|(name, template)| (String::from(name), String::from(template)))
+    .collect();
+    assert_eq!(
+        dsl.export(&expr, "sum-plus", ExportTarget::Scheme, &scheme)
+            .unwrap(),
+        "\
+(define (f0 y) (+ 1 y))
+(define (add-incr y z) (f0 (+ z y)))
+(define (f2) (f0 0))
+(define (sum-plus y z) (fold-left (lambda (acc x) (((lambda (u) (lambda (v) (add-incr u v))) x) acc)) (+ z (f2)) y))
+"
+    );
+
+    // partial applications and variables applied to arguments
+    let expr = dsl.parse("(λ (λ ($0 (+ $1))))").unwrap();
+    assert_eq!(
+        dsl.export(&expr, "f", ExportTarget::Python, &python)
+            .unwrap(),
+        "def f(y, z):\n    return z((lambda u: (y + u)))\n"
+    );
+    assert_eq!(
+        dsl.export(&expr, "f", ExportTarget::Scheme, &scheme)
+            .unwrap(),
+        "(define (f y z) (z (lambda (u) (+ y u))))\n"
+    );
+
+    // variables bound in templates do not capture generated variables
+    let expr = dsl
+        .parse("(λ (λ (fold (λ (λ (+ $0 (+ $1 $3)))) 0 $0)))")
+        .unwrap();
+    assert_eq!(
+        dsl.export(&expr, "f", ExportTarget::Python, &python)
+            .unwrap(),
+        "def f(y, z):\n    return (functools.reduce(lambda acc, x: (lambda u: lambda v: (v + (u + y)))(x)(acc), z, 0))\n"
+    );
+
+    // helper names do not clash with each other or with the function
+    let mut named = dsl.clone();
+    named.name_invented(0, "add_incr").unwrap();
+    named.name_invented(2, "f0").unwrap();
+    let expr = named.parse("(λ (#add-incr $0 #f0))").unwrap();
+    assert_eq!(
+        named
+            .export(&expr, "f0", ExportTarget::Python, &python)
+            .unwrap(),
+        "\
+def add_incr(y):
+    return (1 + y)
+
+def add_incr_(y, z):
+    return add_incr((z + y))
+
+def f0_():
+    return add_incr(0)
+
+def f0(y):
+    return add_incr_(y, f0_())
+"
+    );
+    assert_eq!(
+        named
+            .export(&expr, "f0", ExportTarget::Scheme, &scheme)
+            .unwrap(),
+        "\
+(define (add_incr y) (+ 1 y))
+(define (add-incr y z) (add_incr (+ z y)))
+(define (f0_) (add_incr 0))
+(define (f0 y) (add-incr y (f0_)))
+"
+    );
+
+    // errors
+    let expr = dsl.parse("(neg 1)").unwrap();
+    match dsl.export(&expr, "f", ExportTarget::Python, &python) {
+        Err(ExportError::MissingTemplate(name)) => assert_eq!(name, "neg"),
+        _ => panic!("exported without a template"),
+    }
+    let expr = dsl.parse("(+ $0 1)").unwrap();
+    match dsl.export(&expr, "f", ExportTarget::Python, &python) {
+        Err(ExportError::NotClosed) => (),
+        _ => panic!("exported with a free variable"),
+    }
+    let expr = dsl.parse("(+ 1 1)").unwrap();
+    match dsl.export(&expr, "fold-left", ExportTarget::Scheme, &scheme) {
+        Err(ExportError::NameCollision(name)) => assert_eq!(name, "fold-left"),
+        _ => panic!("exported with a name used by a template"),
+    }
+}